@@ -0,0 +1,54 @@
+use focusboard_core::storage::Storage;
+use log::warn;
+use serde_json::Value;
+use std::fs;
+use std::path::PathBuf;
+
+/// The app's bundle identifier from `tauri.conf.json`. Tauri names the app's data folders
+/// after it.
+const APP_IDENTIFIER: &str = "com.stenberg.focusboard";
+
+/// The settings store the app writes, and the key under which it keeps a moved data directory.
+/// See `settings.rs` in the app.
+const SETTINGS_STORE: &str = "settings.json";
+const DATA_DIRECTORY: &str = "dataDirectory";
+
+/// Tauri's app local data directory, where the app keeps its data unless the user moved it.
+fn default_root() -> Option<PathBuf> {
+    dirs::data_local_dir().map(|dir| dir.join(APP_IDENTIFIER))
+}
+
+/// The data directory the user chose in the app, read from the app's settings store.
+fn configured_root() -> Option<PathBuf> {
+    let path = dirs::data_dir()?.join(APP_IDENTIFIER).join(SETTINGS_STORE);
+    let text = fs::read_to_string(&path).ok()?;
+
+    let settings: Value = serde_json::from_str(&text)
+        .map_err(|e| warn!("Failed to read settings {:?}: {:#}", path, e))
+        .ok()?;
+
+    settings
+        .get(DATA_DIRECTORY)
+        .and_then(Value::as_str)
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Where to find the database: `root` if given, otherwise the same folder the app opens.
+pub fn resolve(root: Option<PathBuf>) -> Option<Storage> {
+    if let Some(root) = root {
+        return Some(Storage::new(root));
+    }
+
+    match configured_root() {
+        Some(root) if root.is_dir() => Some(Storage::new(root)),
+        Some(root) => {
+            warn!(
+                "Configured data directory {:?} is not available, using the default",
+                root
+            );
+            default_root().map(Storage::new)
+        }
+        None => default_root().map(Storage::new),
+    }
+}
@@ -0,0 +1,651 @@
+mod location;
+
+use clap::{Parser, Subcommand};
+use focusboard_core::backup::{self, BackupInfo};
+use focusboard_core::db::{init_db, InitError};
+use focusboard_core::models::{CalendarEvent, Note};
+use focusboard_core::recurrence::RecurrenceRule;
+use focusboard_core::storage::Storage;
+use focusboard_core::{html, markdown};
+use focusboard_core::{AppError, EventService, NoteService, TabService, TimerService};
+use log::{error, info, LevelFilter, Log, Metadata, Record};
+use serde::Serialize;
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::{ConnectOptions, Connection, SqlitePool};
+use std::fs;
+use std::io::{self, IsTerminal, Read};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use time::{macros::format_description, Duration, OffsetDateTime};
+
+/// Read and write FocusBoard's notes, events and backups from the shell.
+///
+/// Works on the same database as the desktop app, and can run while the app is open.
+#[derive(Parser)]
+#[command(name = "focusboard-cli", version)]
+struct Cli {
+    /// Folder holding FocusBoard's data (the one containing `database/data.db`). Defaults to
+    /// the folder the app uses.
+    #[arg(long, global = true, env = "FOCUSBOARD_DATA_DIR")]
+    data_dir: Option<PathBuf>,
+
+    /// Print results as JSON, and errors as JSON on stderr.
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// Log what is going on to stderr.
+    #[arg(short, long, global = true)]
+    verbose: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Add, list, show and search notes.
+    #[command(subcommand)]
+    Note(NoteCommand),
+    /// List tabs.
+    #[command(subcommand)]
+    Tab(TabCommand),
+    /// Add and list calendar events.
+    #[command(subcommand)]
+    Event(EventCommand),
+    /// Show the saved timer.
+    #[command(subcommand)]
+    Timer(TimerCommand),
+    /// Create, list and restore backups.
+    #[command(subcommand)]
+    Backup(BackupCommand),
+}
+
+#[derive(Subcommand)]
+enum NoteCommand {
+    /// Add a note. The content is Markdown, read from standard input unless `--content` is given.
+    Add {
+        title: String,
+        #[arg(long)]
+        content: Option<String>,
+        /// Tab to add the note to; without it the note is not in any tab.
+        #[arg(long)]
+        tab: Option<i64>,
+    },
+    /// List notes in their board order.
+    List {
+        /// Only notes in this tab.
+        #[arg(long)]
+        tab: Option<i64>,
+    },
+    /// Print a note as Markdown.
+    Show { id: i64 },
+    /// Search the title and text of notes. Words are ANDed, "quoted text" is a phrase and a
+    /// trailing * matches a prefix.
+    Search {
+        query: String,
+        #[arg(long)]
+        tab: Option<i64>,
+        #[arg(long, default_value_t = 50)]
+        limit: i64,
+    },
+}
+
+#[derive(Subcommand)]
+enum TabCommand {
+    /// List tabs in their board order.
+    List,
+}
+
+#[derive(Subcommand)]
+enum EventCommand {
+    /// Add an event on DATE (YYYY-MM-DD).
+    Add {
+        date: String,
+        name: String,
+        /// Start time, HH:MM.
+        #[arg(long, value_parser = parse_time)]
+        start: Option<i32>,
+        /// End time, HH:MM.
+        #[arg(long, value_parser = parse_time)]
+        end: Option<i32>,
+        #[arg(long, default_value = "#36A2EB")]
+        color: String,
+        /// Repeat the event by an RFC 5545 rule, e.g. "FREQ=WEEKLY;COUNT=10".
+        #[arg(long)]
+        repeat: Option<RecurrenceRule>,
+    },
+    /// List events, with recurring ones expanded, from one date through another.
+    List {
+        /// First day, YYYY-MM-DD. Defaults to today.
+        #[arg(long)]
+        from: Option<String>,
+        /// Last day, YYYY-MM-DD. Defaults to the first day.
+        #[arg(long)]
+        to: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum TimerCommand {
+    /// Show the timer's message and remaining time.
+    Status,
+}
+
+#[derive(Subcommand)]
+enum BackupCommand {
+    /// Back the database up into a compressed archive, by default in the backup folder.
+    Create {
+        /// Archive to write, or a folder to write it into.
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// List the backups in the backup folder, newest first.
+    List,
+    /// Replace the database with a backup. Quit the app first.
+    Restore { path: PathBuf },
+}
+
+/// Writes log records to stderr, for `--verbose`.
+struct StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, _: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        if record.target().starts_with("focusboard") {
+            eprintln!("{} | {}", record.level(), record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    if cli.verbose {
+        let _ = log::set_logger(&StderrLogger).map(|_| log::set_max_level(LevelFilter::Info));
+    }
+
+    match run(&cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            if cli.json {
+                eprintln!("{}", serde_json::to_string(&e).unwrap_or_default());
+            } else {
+                eprintln!("error: {}", e);
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(cli: &Cli) -> Result<(), AppError> {
+    let storage = location::resolve(cli.data_dir.clone()).ok_or_else(|| {
+        AppError::not_found("Could not find FocusBoard's data folder. Pass --data-dir.")
+    })?;
+
+    let mut database = Database {
+        storage: &storage,
+        pool: None,
+    };
+    let result = dispatch(cli, &storage, &mut database).await;
+    database.close().await;
+    result
+}
+
+/// The database, opened the first time a command asks for it. Listing and restoring backups
+/// never do: a restore needs the database closed.
+struct Database<'a> {
+    storage: &'a Storage,
+    pool: Option<SqlitePool>,
+}
+
+impl Database<'_> {
+    async fn pool(&mut self) -> Result<SqlitePool, AppError> {
+        if let Some(pool) = &self.pool {
+            return Ok(pool.clone());
+        }
+        let pool = open(self.storage).await?;
+        self.pool = Some(pool.clone());
+        Ok(pool)
+    }
+
+    /// Closing the last connection checkpoints the WAL, so nothing is left half-written.
+    async fn close(self) {
+        if let Some(pool) = self.pool {
+            pool.close().await;
+        }
+    }
+}
+
+async fn dispatch(
+    cli: &Cli,
+    storage: &Storage,
+    database: &mut Database<'_>,
+) -> Result<(), AppError> {
+    let json = cli.json;
+
+    match &cli.command {
+        Command::Note(NoteCommand::Add {
+            title,
+            content,
+            tab,
+        }) => {
+            let content = match content {
+                Some(content) => content.clone(),
+                None => read_stdin()?,
+            };
+            let note = NoteService::new(database.pool().await?)
+                .create(html::escape(title), markdown::to_html(&content), *tab)
+                .await?;
+            print(json, &note, |note| note.id.to_string())
+        }
+        Command::Note(NoteCommand::List { tab }) => {
+            let mut listed = NoteService::new(database.pool().await?).list().await?;
+            if let Some(tab) = tab {
+                listed.retain(|note| note.tab_id == Some(*tab));
+            }
+            print(json, &listed, |notes| {
+                lines(notes, |note| {
+                    format!("{}\t{}", note.id, html::to_plain_text(&note.title))
+                })
+            })
+        }
+        Command::Note(NoteCommand::Show { id }) => {
+            let note = NoteService::new(database.pool().await?).get(*id).await?;
+            print(json, &note, show_note)
+        }
+        Command::Note(NoteCommand::Search { query, tab, limit }) => {
+            let hits = NoteService::new(database.pool().await?)
+                .search(query.clone(), *tab, Some(*limit))
+                .await?;
+            print(json, &hits, |hits| {
+                lines(hits, |hit| {
+                    format!(
+                        "{}\t{}\t{}",
+                        hit.id,
+                        html::to_plain_text(&hit.title),
+                        html::to_plain_text(&hit.snippet).replace('\n', " ")
+                    )
+                })
+            })
+        }
+        Command::Tab(TabCommand::List) => {
+            let tabs = TabService::new(database.pool().await?).list().await?;
+            print(json, &tabs, |tabs| {
+                lines(tabs, |tab| format!("{}\t{}", tab.id, tab.name))
+            })
+        }
+        Command::Event(EventCommand::Add {
+            date,
+            name,
+            start,
+            end,
+            color,
+            repeat,
+        }) => {
+            let event = EventService::new(database.pool().await?)
+                .create(
+                    date.clone(),
+                    name.clone(),
+                    *start,
+                    *end,
+                    color.clone(),
+                    repeat.clone(),
+                )
+                .await?;
+            print(json, &event, |event| event.id.to_string())
+        }
+        Command::Event(EventCommand::List { from, to }) => {
+            let from = match from {
+                Some(from) => from.clone(),
+                None => today()?,
+            };
+            let to = to.clone().unwrap_or_else(|| from.clone());
+            let events = EventService::new(database.pool().await?)
+                .range(from, to)
+                .await?;
+            print(json, &events, |events| lines(events, describe_event))
+        }
+        Command::Timer(TimerCommand::Status) => {
+            let timer = TimerService::new(database.pool().await?).get().await?;
+            print(json, &timer, |timer| {
+                format!(
+                    "{}\t{} of {} left",
+                    timer.message,
+                    clock(timer.duration),
+                    clock(timer.initial_duration)
+                )
+            })
+        }
+        Command::Backup(BackupCommand::Create { output }) => {
+            let pool = database.pool().await?;
+            let summary = create_backup(storage, &pool, output.as_deref()).await?;
+            print(json, &summary, |summary| summary.path.clone())
+        }
+        Command::Backup(BackupCommand::List) => list_backups(json, storage).await,
+        Command::Backup(BackupCommand::Restore { path }) => {
+            let restored = restore(storage, path).await?;
+            print(json, &restored, describe_backup)
+        }
+    }
+}
+
+/// Opens the database the app uses, migrating it if it is older than this build. Unlike the
+/// app, a missing database is an error rather than a reason to create one.
+async fn open(storage: &Storage) -> Result<SqlitePool, AppError> {
+    let path = storage.database_path();
+    if !path.is_file() {
+        return Err(AppError::not_found(format!(
+            "There is no FocusBoard database at {:?}. Open the app once, or pass --data-dir.",
+            path
+        )));
+    }
+
+    info!("Opening {:?}", path);
+    init_db(&storage.database_url(), &storage.backup_dir())
+        .await
+        .map_err(|e| {
+            error!("Failed to open database {:?}: {:#}", path, e);
+            AppError::database(open_error_message(&e))
+        })
+}
+
+/// What went wrong opening the database, without the SQLite error, which is only logged.
+fn open_error_message(error: &InitError) -> String {
+    match error {
+        InitError::Connect(_) => "Failed to open the database.".to_string(),
+        InitError::Backup { path, .. } => format!(
+            "Failed to back up the database to {:?} before upgrading it. Nothing was changed.",
+            path
+        ),
+        InitError::Migration {
+            version, backup, ..
+        } => {
+            let mut message = format!(
+                "Failed to upgrade the database to version {}. No changes from this step were \
+                 saved.",
+                version
+            );
+            if let Some(backup) = backup {
+                message.push_str(&format!(
+                    " A copy of the database from before the upgrade is at {:?}.",
+                    backup
+                ));
+            }
+            message
+        }
+        InitError::TooNew { .. } => error.to_string(),
+    }
+}
+
+fn print<T: Serialize>(
+    json: bool,
+    value: &T,
+    text: impl FnOnce(&T) -> String,
+) -> Result<(), AppError> {
+    if json {
+        let json = serde_json::to_string_pretty(value).map_err(|e| {
+            error!("Failed to serialize output: {:#}", e);
+            AppError::io("Failed to write the output.")
+        })?;
+        println!("{}", json);
+    } else {
+        let text = text(value);
+        if !text.is_empty() {
+            println!("{}", text);
+        }
+    }
+
+    Ok(())
+}
+
+fn lines<T>(items: &[T], line: impl Fn(&T) -> String) -> String {
+    items.iter().map(line).collect::<Vec<_>>().join("\n")
+}
+
+fn read_stdin() -> Result<String, AppError> {
+    let mut stdin = io::stdin();
+    if stdin.is_terminal() {
+        return Ok(String::new());
+    }
+
+    let mut content = String::new();
+    stdin.read_to_string(&mut content).map_err(|e| {
+        error!("Failed to read standard input: {:#}", e);
+        AppError::io("Failed to read the note from standard input.")
+    })?;
+    Ok(content)
+}
+
+fn today() -> Result<String, AppError> {
+    OffsetDateTime::now_local()
+        .unwrap_or_else(|_| OffsetDateTime::now_utc())
+        .date()
+        .format(&format_description!("[year]-[month]-[day]"))
+        .map_err(|e| {
+            error!("Failed to format today's date: {:#}", e);
+            AppError::validation("Failed to work out today's date. Pass --from.")
+        })
+}
+
+/// Parses `HH:MM` into seconds since midnight, the unit events are stored in.
+fn parse_time(time: &str) -> Result<i32, String> {
+    let parsed = time::Time::parse(time, &format_description!("[hour]:[minute]"))
+        .map_err(|_| format!("invalid time {:?}, expected HH:MM", time))?;
+
+    Ok(i32::from(parsed.hour()) * 3600 + i32::from(parsed.minute()) * 60)
+}
+
+/// Seconds as `H:MM:SS`, or `M:SS` under an hour.
+fn clock(seconds: i32) -> String {
+    let duration = Duration::seconds(i64::from(seconds.max(0)));
+    let (hours, minutes, seconds) = (
+        duration.whole_hours(),
+        duration.whole_minutes() % 60,
+        duration.whole_seconds() % 60,
+    );
+
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+fn show_note(note: &Note) -> String {
+    format!(
+        "# {}\n\n{}",
+        html::to_plain_text(&note.title),
+        markdown::from_html(&note.content)
+    )
+}
+
+fn describe_event(event: &CalendarEvent) -> String {
+    let time = |seconds: Option<i32>| {
+        seconds.map_or("--:--".to_string(), |seconds| {
+            format!("{:02}:{:02}", seconds / 3600, seconds % 3600 / 60)
+        })
+    };
+    let times = match (event.event_start, event.event_end) {
+        (None, None) => "all day".to_string(),
+        (start, end) => format!("{}-{}", time(start), time(end)),
+    };
+
+    format!(
+        "{}\t{}\t{}\t{}",
+        event.event_date, times, event.event_name, event.id
+    )
+}
+
+fn describe_backup(info: &BackupInfo) -> String {
+    match &info.stats {
+        Some(stats) => format!(
+            "{}\t{}\t{} notes, {} tabs, {} events",
+            info.path, info.created_at, stats.note_count, stats.tab_count, stats.event_count
+        ),
+        None => format!(
+            "{}\t{}\t{}",
+            info.path,
+            info.created_at,
+            info.error.as_deref().unwrap_or_default()
+        ),
+    }
+}
+
+async fn create_backup(
+    storage: &Storage,
+    pool: &SqlitePool,
+    output: Option<&Path>,
+) -> Result<backup::BackupSummary, AppError> {
+    let path = match output {
+        Some(output) if output.is_dir() => backup::timestamped_path(output, backup::MANUAL_PREFIX),
+        Some(output) => output.to_path_buf(),
+        None => backup::timestamped_path(&storage.backup_dir(), backup::MANUAL_PREFIX),
+    };
+
+    if path.exists() {
+        return Err(AppError::conflict(format!("{:?} already exists.", path)));
+    }
+
+    backup::write_backup(pool, &path).await.map_err(|e| {
+        error!("Failed to back up database to {:?}: {:#}", path, e);
+        AppError::database("Failed to back up the database. Please try again")
+    })
+}
+
+async fn list_backups(json: bool, storage: &Storage) -> Result<(), AppError> {
+    let paths = backup::find_backups(&storage.backup_dir()).map_err(|e| {
+        error!(
+            "Failed to read backups in {:?}: {:#}",
+            storage.backup_dir(),
+            e
+        );
+        AppError::io("Failed to read the backup folder.")
+    })?;
+
+    let mut backups = Vec::with_capacity(paths.len());
+    for path in paths {
+        backups.push(backup::describe(&path).await);
+    }
+
+    print(json, &backups, |backups| lines(backups, describe_backup))
+}
+
+/// Replaces the database with the backup at `path`, the way the app's restore does: the backup
+/// is checked first and the current data is archived as a `pre-restore` backup, which is put
+/// back if the restored database can't be opened.
+async fn restore(storage: &Storage, path: &Path) -> Result<BackupInfo, AppError> {
+    let source = if path.is_dir() {
+        path.join(backup::BACKUP_DB_FILE)
+    } else {
+        path.to_path_buf()
+    };
+    if !source.is_file() {
+        return Err(AppError::not_found("The selected backup does not exist."));
+    }
+
+    let live = storage.database_path();
+    if in_use(&live).await? {
+        return Err(AppError::conflict(
+            "FocusBoard seems to be running. Quit it before restoring a backup.",
+        ));
+    }
+
+    let staged = backup::with_suffix(&live, "-restore");
+    if let Err(e) = backup::stage_backup(&source, &staged).await {
+        let _ = fs::remove_file(&staged);
+        return Err(e);
+    }
+
+    let pool = open(storage).await?;
+    let safety_path = backup::timestamped_path(&storage.backup_dir(), backup::PRE_RESTORE_PREFIX);
+    let safety = backup::write_backup(&pool, &safety_path).await;
+    pool.close().await;
+
+    let safety = safety.map_err(|e| {
+        error!("Failed to back up database to {:?}: {:#}", safety_path, e);
+        let _ = fs::remove_file(&staged);
+        AppError::database(
+            "Failed to back up the current data before restoring. Nothing was changed.",
+        )
+    })?;
+    if !safety.integrity_ok {
+        let _ = fs::remove_file(&staged);
+        return Err(AppError::database(
+            "The backup of the current data failed its integrity check. Nothing was changed.",
+        ));
+    }
+
+    if let Err(restore_error) = swap_in(storage, &staged).await {
+        error!("Restoring {:?} failed, rolling back", source);
+
+        let rollback = match backup::extract_database(&safety_path, &staged) {
+            Ok(_) => swap_in(storage, &staged).await,
+            Err(e) => Err(AppError::io(e.to_string())),
+        };
+        if let Err(e) = rollback {
+            error!("Failed to roll back to {:?}: {:#}", safety_path, e);
+            return Err(AppError::database(format!(
+                "Restoring the backup failed ({}), and so did putting the previous data back. \
+                 It is saved in {:?}.",
+                restore_error, safety_path
+            )));
+        }
+
+        return Err(AppError::database(format!(
+            "Restoring the backup failed: {}. Your previous data was kept.",
+            restore_error
+        )));
+    }
+
+    info!(
+        "Restored database from {:?}; previous data saved to {:?}",
+        source, safety_path
+    );
+
+    Ok(backup::describe(&source).await)
+}
+
+/// Moves `staged` over the live database and checks that the result opens.
+async fn swap_in(storage: &Storage, staged: &Path) -> Result<(), AppError> {
+    let live = storage.database_path();
+    backup::replace_database_file(&live, staged).map_err(|e| {
+        error!("Failed to move {:?} to {:?}: {:#}", staged, live, e);
+        AppError::io("Failed to replace the database file.")
+    })?;
+
+    open(storage).await?.close().await;
+    Ok(())
+}
+
+/// Whether another process, normally the app, has the database at `path` open. Taking a
+/// database out of WAL mode needs the only connection to it, so it fails while anything else
+/// is using the file. The next `open` puts it back in WAL mode.
+async fn in_use(path: &Path) -> Result<bool, AppError> {
+    let mut conn = SqliteConnectOptions::new()
+        .filename(path)
+        .connect()
+        .await
+        .map_err(|e| {
+            error!("Failed to open database {:?}: {:#}", path, e);
+            AppError::database("Failed to open the database.")
+        })?;
+
+    let mode = sqlx::query_scalar::<_, String>("PRAGMA journal_mode = DELETE")
+        .fetch_one(&mut conn)
+        .await;
+    let _ = conn.close().await;
+
+    match mode {
+        Ok(mode) => Ok(!mode.eq_ignore_ascii_case("delete")),
+        Err(e) => {
+            info!("Database {:?} is in use: {:#}", path, e);
+            Ok(true)
+        }
+    }
+}
@@ -0,0 +1,683 @@
+use crate::db;
+use crate::error::AppError;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use specta::Type;
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::{ConnectOptions, Connection, SqliteConnection, SqlitePool};
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+use time::format_description::well_known::Rfc3339;
+use time::{macros::format_description, OffsetDateTime, UtcOffset};
+
+/// File name of the database inside a backup archive (and inside older backup folders).
+pub const BACKUP_DB_FILE: &str = "data.db";
+pub const MANIFEST_FILE: &str = "manifest.json";
+/// `sha256sum`-compatible checksums of the other files in the archive.
+pub const CHECKSUMS_FILE: &str = "SHA256SUMS";
+pub const ARCHIVE_EXTENSION: &str = "tar.gz";
+const MANIFEST_FORMAT: u32 = 1;
+
+/// Archive name prefixes, followed by `_<local timestamp>`.
+pub const MANUAL_PREFIX: &str = "database-backup";
+pub const AUTOMATIC_PREFIX: &str = "auto-backup";
+pub const PRE_RESTORE_PREFIX: &str = "pre-restore";
+pub const PRE_MOVE_PREFIX: &str = "pre-move";
+
+/// Appended to an automatic backup that failed its integrity check, so it is neither listed,
+/// restored nor counted by rotation, but is still there to look at.
+pub const CORRUPT_SUFFIX: &str = ".corrupt";
+
+/// How many automatic backups to keep: the newest one of each of the last `daily` days,
+/// `weekly` ISO weeks and `monthly` months that have one. The newest backup is always kept.
+#[derive(Debug, Clone, Copy)]
+pub struct RetentionPolicy {
+    pub daily: usize,
+    pub weekly: usize,
+    pub monthly: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Type)]
+pub struct BackupSummary {
+    pub path: String,
+    /// Size of the compressed archive.
+    pub size_bytes: u64,
+    pub duration_ms: u64,
+    pub integrity_ok: bool,
+    /// Output of `PRAGMA integrity_check` on the copy; `["ok"]` when healthy.
+    pub integrity_check: Vec<String>,
+}
+
+/// Counts read from a backup without opening it as the live database.
+#[derive(Serialize, Deserialize, Debug, Clone, Type)]
+pub struct BackupStats {
+    pub schema_version: i64,
+    pub note_count: i64,
+    pub tab_count: i64,
+    pub event_count: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ManifestFile {
+    pub name: String,
+    pub size_bytes: u64,
+    pub sha256: String,
+}
+
+/// `manifest.json` at the root of every backup archive.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BackupManifest {
+    pub format: u32,
+    pub app_version: String,
+    /// RFC 3339, local time.
+    pub created_at: String,
+    #[serde(flatten)]
+    pub stats: BackupStats,
+    pub files: Vec<ManifestFile>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Type)]
+pub struct BackupInfo {
+    /// Path of the backup archive, or of the database file for older backups.
+    pub path: String,
+    pub name: String,
+    /// Modification time of the backup, UTC, in the same format as the app's tables.
+    pub created_at: String,
+    pub size_bytes: u64,
+    /// FocusBoard version that wrote the backup, when it has a manifest.
+    pub app_version: Option<String>,
+    /// `None` when the backup could not be read; `error` says why.
+    pub stats: Option<BackupStats>,
+    pub error: Option<String>,
+}
+
+/// `<prefix>_<local timestamp>.tar.gz`.
+pub fn archive_name(prefix: &str) -> String {
+    let timestamp = OffsetDateTime::now_local()
+        .unwrap_or_else(|_| OffsetDateTime::now_utc())
+        .format(&format_description!(
+            "[year]-[month]-[day]_T[hour]H-[minute]M-[second]S"
+        ))
+        .unwrap_or_default();
+
+    format!("{}_{}.{}", prefix, timestamp, ARCHIVE_EXTENSION)
+}
+
+pub fn timestamped_path(backup_dir: &Path, prefix: &str) -> PathBuf {
+    backup_dir.join(archive_name(prefix))
+}
+
+pub fn is_archive(path: &Path) -> bool {
+    path.to_string_lossy()
+        .ends_with(&format!(".{}", ARCHIVE_EXTENSION))
+}
+
+/// `path` with `suffix` appended to its file name, e.g. the `-wal` file of a database.
+pub fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+/// Writes a consistent, self-contained copy of the live database with `VACUUM INTO`, checks
+/// its integrity, and packs it into a compressed archive at `dest` together with a manifest
+/// and checksums. `dest` must not exist yet.
+pub async fn write_backup(pool: &SqlitePool, dest: &Path) -> Result<BackupSummary, sqlx::Error> {
+    let started = Instant::now();
+
+    if dest.exists() {
+        return Err(io::Error::new(ErrorKind::AlreadyExists, "backup already exists").into());
+    }
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let snapshot = with_suffix(dest, ".db-partial");
+    let _ = fs::remove_file(&snapshot);
+
+    // VACUUM INTO reads a single snapshot of the database, WAL included, so the copy is
+    // never torn by writes that land while it runs.
+    let result = async {
+        sqlx::query("VACUUM INTO ?")
+            .bind(snapshot.to_string_lossy().into_owned())
+            .execute(pool)
+            .await?;
+
+        let integrity_check = integrity_check(&snapshot).await?;
+        let stats = read_stats(&snapshot).await?;
+        write_archive(&snapshot, stats, dest)?;
+
+        Ok::<_, sqlx::Error>(integrity_check)
+    }
+    .await;
+    let _ = fs::remove_file(&snapshot);
+
+    let integrity_check = result?;
+    let integrity_ok = integrity_check == ["ok"];
+    let size_bytes = fs::metadata(dest)?.len();
+    let duration_ms = started.elapsed().as_millis() as u64;
+
+    if integrity_ok {
+        info!(
+            "Database backed up to {:?} ({} bytes in {} ms)",
+            dest, size_bytes, duration_ms
+        );
+    } else {
+        error!(
+            "Backup {:?} failed its integrity check: {}",
+            dest,
+            integrity_check.join("; ")
+        );
+    }
+
+    Ok(BackupSummary {
+        path: dest.to_string_lossy().into_owned(),
+        size_bytes,
+        duration_ms,
+        integrity_ok,
+        integrity_check,
+    })
+}
+
+fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn append_bytes<W: Write>(
+    archive: &mut tar::Builder<W>,
+    name: &str,
+    bytes: &[u8],
+) -> io::Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(bytes.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(
+        SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs()),
+    );
+    header.set_cksum();
+    archive.append_data(&mut header, name, bytes)
+}
+
+/// Packs the database file at `database` into a gzipped tarball at `dest`. The archive is
+/// written under a temporary name and renamed once complete.
+fn write_archive(database: &Path, stats: BackupStats, dest: &Path) -> io::Result<()> {
+    let database_sha256 = sha256_file(database)?;
+    let manifest = BackupManifest {
+        format: MANIFEST_FORMAT,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at: OffsetDateTime::now_local()
+            .unwrap_or_else(|_| OffsetDateTime::now_utc())
+            .format(&Rfc3339)
+            .unwrap_or_default(),
+        stats,
+        files: vec![ManifestFile {
+            name: BACKUP_DB_FILE.to_string(),
+            size_bytes: fs::metadata(database)?.len(),
+            sha256: database_sha256.clone(),
+        }],
+    };
+    let manifest_json = serde_json::to_vec_pretty(&manifest)?;
+    let checksums = format!(
+        "{}  {}\n{:x}  {}\n",
+        database_sha256,
+        BACKUP_DB_FILE,
+        Sha256::digest(&manifest_json),
+        MANIFEST_FILE
+    );
+
+    let partial = with_suffix(dest, ".partial");
+    let result = (|| {
+        let encoder = GzEncoder::new(File::create(&partial)?, Compression::default());
+        let mut archive = tar::Builder::new(encoder);
+        append_bytes(&mut archive, MANIFEST_FILE, &manifest_json)?;
+        append_bytes(&mut archive, CHECKSUMS_FILE, checksums.as_bytes())?;
+        archive.append_path_with_name(database, BACKUP_DB_FILE)?;
+        archive.into_inner()?.finish()?.sync_all()?;
+        fs::rename(&partial, dest)
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+    result
+}
+
+/// Reads the manifest of the archive at `path` without unpacking the database.
+pub fn read_manifest(path: &Path) -> io::Result<BackupManifest> {
+    let mut archive = tar::Archive::new(GzDecoder::new(File::open(path)?));
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if entry.path()?.as_os_str() == MANIFEST_FILE {
+            let mut json = Vec::new();
+            entry.read_to_end(&mut json)?;
+            return Ok(serde_json::from_slice(&json)?);
+        }
+    }
+
+    Err(io::Error::new(
+        ErrorKind::InvalidData,
+        "backup archive has no manifest",
+    ))
+}
+
+/// Unpacks the database from the archive at `path` to `dest`, checking it against the
+/// manifest's size and SHA-256. Returns the manifest.
+pub fn extract_database(path: &Path, dest: &Path) -> io::Result<BackupManifest> {
+    let manifest = read_manifest(path)?;
+    let expected = manifest
+        .files
+        .iter()
+        .find(|file| file.name == BACKUP_DB_FILE)
+        .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "manifest lists no database"))?;
+
+    let mut archive = tar::Archive::new(GzDecoder::new(File::open(path)?));
+    let mut found = false;
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if entry.path()?.as_os_str() == BACKUP_DB_FILE {
+            io::copy(&mut entry, &mut File::create(dest)?)?;
+            found = true;
+            break;
+        }
+    }
+    if !found {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            "backup archive has no database",
+        ));
+    }
+
+    let size_bytes = fs::metadata(dest)?.len();
+    let sha256 = sha256_file(dest)?;
+    if size_bytes != expected.size_bytes || sha256 != expected.sha256 {
+        let _ = fs::remove_file(dest);
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "database checksum mismatch: expected {}, found {}",
+                expected.sha256, sha256
+            ),
+        ));
+    }
+
+    Ok(manifest)
+}
+
+/// Opens the database file at `path` read-only, outside the app's pool.
+pub async fn open_read_only(path: &Path) -> Result<SqliteConnection, sqlx::Error> {
+    SqliteConnectOptions::new()
+        .filename(path)
+        .read_only(true)
+        .connect()
+        .await
+}
+
+/// Runs `PRAGMA integrity_check` against the database file at `path`.
+pub async fn integrity_check(path: &Path) -> Result<Vec<String>, sqlx::Error> {
+    let mut conn = open_read_only(path).await?;
+
+    let rows: Vec<(String,)> = sqlx::query_as("PRAGMA integrity_check")
+        .fetch_all(&mut conn)
+        .await?;
+
+    conn.close().await?;
+
+    Ok(rows.into_iter().map(|(line,)| line).collect())
+}
+
+/// Copies the database at `source` (WAL included) into a single new file at `dest`, without
+/// writing to `source`.
+pub async fn copy_database(source: &Path, dest: &Path) -> Result<(), sqlx::Error> {
+    let mut conn = open_read_only(source).await?;
+
+    sqlx::query("VACUUM INTO ?")
+        .bind(dest.to_string_lossy().into_owned())
+        .execute(&mut conn)
+        .await?;
+
+    conn.close().await
+}
+
+pub async fn read_stats(path: &Path) -> Result<BackupStats, sqlx::Error> {
+    let mut conn = open_read_only(path).await?;
+
+    let (schema_version,): (i64,) = sqlx::query_as("PRAGMA user_version")
+        .fetch_one(&mut conn)
+        .await?;
+    let (note_count, tab_count, event_count): (i64, i64, i64) = sqlx::query_as(
+        r#"
+        SELECT
+            (SELECT COUNT(*) FROM notes),
+            (SELECT COUNT(*) FROM tabs),
+            (SELECT COUNT(*) FROM events)
+        "#,
+    )
+    .fetch_one(&mut conn)
+    .await?;
+
+    conn.close().await?;
+
+    Ok(BackupStats {
+        schema_version,
+        note_count,
+        tab_count,
+        event_count,
+    })
+}
+
+/// Every backup in `backup_dir`, newest first: archives, plus database files left by
+/// pre-migration backups and by older versions, which wrote folders holding a `data.db`.
+pub fn find_backups(backup_dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let entries = match fs::read_dir(backup_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    let mut backups = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_dir() {
+            let file = path.join(BACKUP_DB_FILE);
+            if file.is_file() {
+                backups.push(file);
+            }
+        } else if is_archive(&path) || path.extension().is_some_and(|ext| ext == "db") {
+            backups.push(path);
+        }
+    }
+
+    backups.sort_by_key(|path| {
+        std::cmp::Reverse(
+            fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .ok(),
+        )
+    });
+
+    Ok(backups)
+}
+
+pub async fn describe(path: &Path) -> BackupInfo {
+    let name = if path.file_name().is_some_and(|name| name == BACKUP_DB_FILE) {
+        path.parent().and_then(|parent| parent.file_name())
+    } else {
+        path.file_name()
+    }
+    .map(|name| {
+        let name = name.to_string_lossy();
+        name.strip_suffix(&format!(".{}", ARCHIVE_EXTENSION))
+            .unwrap_or(&name)
+            .to_string()
+    })
+    .unwrap_or_default();
+
+    let metadata = fs::metadata(path).ok();
+    let created_at = metadata
+        .as_ref()
+        .and_then(|metadata| metadata.modified().ok())
+        .map(OffsetDateTime::from)
+        .and_then(|modified| {
+            modified
+                .format(&format_description!(
+                    "[year]-[month]-[day] [hour]:[minute]:[second]"
+                ))
+                .ok()
+        })
+        .unwrap_or_default();
+
+    let read = if is_archive(path) {
+        read_manifest(path)
+            .map(|manifest| (manifest.stats, Some(manifest.app_version)))
+            .map_err(|e| e.to_string())
+    } else {
+        read_stats(path)
+            .await
+            .map(|stats| (stats, None))
+            .map_err(|e| e.to_string())
+    };
+    let (stats, app_version, error) = match read {
+        Ok((stats, app_version)) => (Some(stats), app_version, None),
+        Err(e) => {
+            error!("Failed to read backup {:?}: {}", path, e);
+            (
+                None,
+                None,
+                Some("The backup could not be read.".to_string()),
+            )
+        }
+    };
+
+    BackupInfo {
+        path: path.to_string_lossy().into_owned(),
+        name,
+        created_at,
+        size_bytes: metadata.map_or(0, |metadata| metadata.len()),
+        app_version,
+        stats,
+        error,
+    }
+}
+
+/// Unpacks or copies the backup at `source` to `staged` and checks that it is an intact
+/// FocusBoard database this build can open.
+pub async fn stage_backup(source: &Path, staged: &Path) -> Result<(), AppError> {
+    let _ = fs::remove_file(staged);
+
+    if is_archive(source) {
+        extract_database(source, staged).map_err(|e| {
+            error!("Failed to unpack backup {:?}: {:#}", source, e);
+            match e.kind() {
+                ErrorKind::InvalidData => AppError::validation(
+                    "The selected backup is damaged or incomplete and can't be restored.",
+                ),
+                _ => AppError::io("Failed to read the selected backup."),
+            }
+        })?;
+    } else {
+        copy_database(source, staged).await.map_err(|e| {
+            error!("Failed to stage backup {:?}: {:#}", source, e);
+            AppError::io("Failed to read the selected backup.")
+        })?;
+    }
+
+    let stats = read_stats(staged).await.map_err(|e| {
+        error!("Failed to read backup {:?}: {:#}", source, e);
+        AppError::database("The selected backup is not a FocusBoard database.")
+    })?;
+    if stats.schema_version > db::latest_version() {
+        return Err(AppError::validation(format!(
+            "The selected backup uses schema version {}, but this version of FocusBoard only \
+             supports up to version {}. Please update FocusBoard first.",
+            stats.schema_version,
+            db::latest_version()
+        ))
+        .with_details(&stats));
+    }
+
+    let integrity = integrity_check(staged).await.map_err(|e| {
+        error!("Failed to check backup {:?}: {:#}", source, e);
+        AppError::database("Failed to check the selected backup.")
+    })?;
+    if integrity != ["ok"] {
+        error!(
+            "Backup {:?} failed its integrity check: {}",
+            source,
+            integrity.join("; ")
+        );
+        return Err(
+            AppError::validation("The selected backup is damaged and can't be restored.")
+                .with_details(&integrity),
+        );
+    }
+
+    Ok(())
+}
+
+fn remove_sidecar_files(path: &Path) -> io::Result<()> {
+    for suffix in ["-wal", "-shm"] {
+        match fs::remove_file(with_suffix(path, suffix)) {
+            Err(e) if e.kind() != ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Moves `staged` over the database file at `live`, dropping the old WAL and shared-memory
+/// files. The pool using `live` must be closed first.
+pub fn replace_database_file(live: &Path, staged: &Path) -> io::Result<()> {
+    remove_sidecar_files(live)?;
+    fs::rename(staged, live)
+}
+
+/// Deletes the database file at `path` along with its WAL and shared-memory files.
+pub fn remove_database_file(path: &Path) -> io::Result<()> {
+    remove_sidecar_files(path)?;
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Automatic backups in `backup_dir` with the time each was taken, newest first. Older
+/// versions wrote folders rather than archives; both are handled.
+fn automatic_backups(backup_dir: &Path) -> io::Result<Vec<(PathBuf, SystemTime)>> {
+    let entries = match fs::read_dir(backup_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    let mut backups = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        let is_automatic = path.file_name().is_some_and(|name| {
+            name.to_string_lossy()
+                .starts_with(&format!("{}_", AUTOMATIC_PREFIX))
+        });
+        let is_corrupt = path.to_string_lossy().ends_with(CORRUPT_SUFFIX);
+        if !is_automatic || is_corrupt {
+            continue;
+        }
+        let file = if path.is_dir() {
+            path.join(BACKUP_DB_FILE)
+        } else {
+            path.clone()
+        };
+        if let Ok(modified) = fs::metadata(file).and_then(|m| m.modified()) {
+            backups.push((path, modified));
+        }
+    }
+
+    backups.sort_by_key(|(_, taken)| std::cmp::Reverse(*taken));
+    Ok(backups)
+}
+
+/// Whether the newest automatic backup is at least `interval` old, or there is none.
+pub fn automatic_backup_due(backup_dir: &Path, interval: Duration) -> bool {
+    match automatic_backups(backup_dir) {
+        Ok(backups) => backups
+            .first()
+            .is_none_or(|(_, taken)| taken.elapsed().map_or(true, |elapsed| elapsed >= interval)),
+        Err(e) => {
+            error!("Failed to read backups in {:?}: {:#}", backup_dir, e);
+            false
+        }
+    }
+}
+
+/// Takes an automatic backup, then prunes older automatic backups by `policy`. Manual,
+/// pre-migration and pre-restore backups are never pruned. A backup that fails its integrity
+/// check is set aside with [`CORRUPT_SUFFIX`] and reported as an error, and nothing is pruned.
+pub async fn automatic_backup(
+    pool: &SqlitePool,
+    backup_dir: &Path,
+    policy: RetentionPolicy,
+) -> Result<BackupSummary, sqlx::Error> {
+    let dest = timestamped_path(backup_dir, AUTOMATIC_PREFIX);
+    let summary = write_backup(pool, &dest).await?;
+
+    // A corrupt copy must not take the place of a good backup in rotation, so nothing is pruned.
+    if !summary.integrity_ok {
+        let corrupt = with_suffix(&dest, CORRUPT_SUFFIX);
+        if let Err(e) = fs::rename(&dest, &corrupt) {
+            error!("Failed to set aside corrupt backup {:?}: {:#}", dest, e);
+            let _ = fs::remove_file(&dest);
+        }
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "backup failed its integrity check: {}",
+                summary.integrity_check.join("; ")
+            ),
+        )
+        .into());
+    }
+
+    match prune_automatic_backups(backup_dir, policy) {
+        Ok(0) => {}
+        Ok(pruned) => info!("Pruned {} old automatic backup(s)", pruned),
+        Err(e) => error!("Failed to prune automatic backups: {:#}", e),
+    }
+
+    Ok(summary)
+}
+
+/// Grandfather-father-son pruning of automatic backups. Returns how many were deleted.
+pub fn prune_automatic_backups(backup_dir: &Path, policy: RetentionPolicy) -> io::Result<usize> {
+    let backups = automatic_backups(backup_dir)?;
+    let offset = UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC);
+    // Day, ISO week and month of each backup, matching the policy's fields.
+    let periods: Vec<[(i32, u16); 3]> = backups
+        .iter()
+        .map(|(_, taken)| {
+            let date = OffsetDateTime::from(*taken).to_offset(offset).date();
+            let (week_year, week, _) = date.to_iso_week_date();
+            [
+                (date.year(), date.ordinal()),
+                (week_year, u16::from(week)),
+                (date.year(), u16::from(date.month() as u8)),
+            ]
+        })
+        .collect();
+
+    let mut keep = HashSet::from([0]);
+    for (slot, limit) in [policy.daily, policy.weekly, policy.monthly]
+        .into_iter()
+        .enumerate()
+    {
+        let mut seen = HashSet::new();
+        for (index, backup_periods) in periods.iter().enumerate() {
+            if seen.len() < limit && seen.insert(backup_periods[slot]) {
+                keep.insert(index);
+            }
+        }
+    }
+
+    let mut pruned = 0;
+    for (index, (path, _)) in backups.iter().enumerate() {
+        if !keep.contains(&index) {
+            if path.is_dir() {
+                fs::remove_dir_all(path)?;
+            } else {
+                fs::remove_file(path)?;
+            }
+            pruned += 1;
+        }
+    }
+
+    Ok(pruned)
+}
@@ -0,0 +1,498 @@
+use crate::html;
+use log::{error, info};
+use sqlx::{
+    sqlite::{SqliteConnectOptions, SqlitePoolOptions},
+    Executor, SqliteConnection, SqlitePool,
+};
+use std::fmt;
+use std::fs;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use time::{macros::format_description, OffsetDateTime};
+
+/// A single schema change. Migrations are applied in ascending `version` order, each one
+/// inside its own transaction, and `PRAGMA user_version` records the last one applied.
+/// Never edit a migration that has shipped; add a new one instead.
+pub struct Migration {
+    pub version: i64,
+    pub description: &'static str,
+    pub sql: &'static str,
+    /// Runs after `sql`, in the same transaction, for data changes SQL alone can't express.
+    pub after: Option<MigrationHook>,
+}
+
+pub type MigrationHook =
+    for<'c> fn(
+        &'c mut SqliteConnection,
+    ) -> Pin<Box<dyn Future<Output = Result<(), sqlx::Error>> + Send + 'c>>;
+
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "initial schema",
+        sql: "CREATE TABLE IF NOT EXISTS tabs (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            order_id INTEGER,
+            created_at TEXT DEFAULT (datetime('now')),
+            updated_at TEXT DEFAULT (datetime('now'))
+        );
+
+        CREATE TABLE IF NOT EXISTS notes (
+            id INTEGER PRIMARY KEY,
+            title TEXT NOT NULL,
+            content TEXT,
+            tab_id INTEGER,
+            order_id INTEGER,
+            created_at TEXT DEFAULT (datetime('now')),
+            updated_at TEXT DEFAULT (datetime('now')),
+            FOREIGN KEY (tab_id) REFERENCES tabs(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS timers (
+            id INTEGER PRIMARY KEY,
+            initial_duration INTEGER,
+            duration INTEGER,
+            message TEXT
+        );
+
+        CREATE TABLE IF NOT EXISTS events (
+            id INTEGER PRIMARY KEY,
+            event_date TEXT NOT NULL,
+            year_month TEXT,
+            event_name TEXT,
+            event_start INTEGER,
+            event_end INTEGER,
+            color TEXT
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_notes_tab_order
+        ON notes(tab_id, order_id);",
+        after: None,
+    },
+    Migration {
+        version: 2,
+        description: "full-text search over notes",
+        sql: "ALTER TABLE notes ADD COLUMN title_text TEXT NOT NULL DEFAULT '';
+            ALTER TABLE notes ADD COLUMN content_text TEXT NOT NULL DEFAULT '';
+
+            CREATE VIRTUAL TABLE notes_fts USING fts5(
+                title_text,
+                content_text,
+                content = 'notes',
+                content_rowid = 'id',
+                tokenize = 'unicode61 remove_diacritics 2',
+                prefix = '2 3'
+            );",
+        after: Some(backfill_note_search_text),
+    },
+    Migration {
+        version: 3,
+        description: "keep the note search index in sync",
+        sql: "CREATE TRIGGER notes_fts_insert AFTER INSERT ON notes BEGIN
+                INSERT INTO notes_fts (rowid, title_text, content_text)
+                VALUES (new.id, new.title_text, new.content_text);
+            END;
+
+            CREATE TRIGGER notes_fts_delete AFTER DELETE ON notes BEGIN
+                INSERT INTO notes_fts (notes_fts, rowid, title_text, content_text)
+                VALUES ('delete', old.id, old.title_text, old.content_text);
+            END;
+
+            CREATE TRIGGER notes_fts_update AFTER UPDATE OF title_text, content_text ON notes BEGIN
+                INSERT INTO notes_fts (notes_fts, rowid, title_text, content_text)
+                VALUES ('delete', old.id, old.title_text, old.content_text);
+                INSERT INTO notes_fts (rowid, title_text, content_text)
+                VALUES (new.id, new.title_text, new.content_text);
+            END;",
+        after: None,
+    },
+    Migration {
+        version: 4,
+        description: "trash bin for notes and tabs",
+        sql: "ALTER TABLE tabs ADD COLUMN deleted_at TEXT;
+            ALTER TABLE notes ADD COLUMN deleted_at TEXT;
+            ALTER TABLE notes ADD COLUMN trashed_with_tab INTEGER NOT NULL DEFAULT 0;
+
+            CREATE INDEX idx_tabs_deleted_at ON tabs(deleted_at);
+            CREATE INDEX idx_notes_deleted_at ON notes(deleted_at);",
+        after: None,
+    },
+    Migration {
+        version: 5,
+        description: "note revision history",
+        sql: "CREATE TABLE note_revisions (
+                id INTEGER PRIMARY KEY,
+                note_id INTEGER NOT NULL,
+                title TEXT NOT NULL,
+                content TEXT,
+                created_at TEXT DEFAULT (datetime('now')),
+                updated_at TEXT DEFAULT (datetime('now')),
+                FOREIGN KEY (note_id) REFERENCES notes(id) ON DELETE CASCADE
+            );
+
+            CREATE INDEX idx_note_revisions_note ON note_revisions(note_id, id);",
+        after: None,
+    },
+    Migration {
+        version: 6,
+        description: "index events by date",
+        sql: "UPDATE events SET year_month = substr(event_date, 1, 7);
+
+            CREATE INDEX idx_events_date ON events(event_date, event_start);",
+        after: None,
+    },
+    Migration {
+        version: 7,
+        description: "recurring events",
+        sql: "ALTER TABLE events ADD COLUMN recurrence TEXT;
+
+            CREATE TABLE event_exceptions (
+                id INTEGER PRIMARY KEY,
+                event_id INTEGER NOT NULL,
+                occurrence_date TEXT NOT NULL,
+                cancelled INTEGER NOT NULL DEFAULT 0,
+                event_name TEXT,
+                event_start INTEGER,
+                event_end INTEGER,
+                FOREIGN KEY (event_id) REFERENCES events(id) ON DELETE CASCADE,
+                UNIQUE (event_id, occurrence_date)
+            );",
+        after: None,
+    },
+    Migration {
+        version: 8,
+        description: "calendar UIDs for iCalendar import and export",
+        sql: "ALTER TABLE events ADD COLUMN uid TEXT;
+
+            UPDATE events SET uid = lower(hex(randomblob(16))) || '@focusboard';
+
+            CREATE UNIQUE INDEX idx_events_uid ON events(uid);
+
+            CREATE TRIGGER events_default_uid AFTER INSERT ON events
+            WHEN new.uid IS NULL BEGIN
+                UPDATE events SET uid = lower(hex(randomblob(16))) || '@focusboard'
+                WHERE id = new.id;
+            END;",
+        after: None,
+    },
+    Migration {
+        version: 9,
+        description: "row versions for conflict detection",
+        sql: "ALTER TABLE notes ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+            ALTER TABLE events ADD COLUMN version INTEGER NOT NULL DEFAULT 1;",
+        after: None,
+    },
+    Migration {
+        version: 10,
+        description: "note tags",
+        sql: "CREATE TABLE tags (
+                id INTEGER PRIMARY KEY,
+                name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+                created_at TEXT DEFAULT (datetime('now')),
+                updated_at TEXT DEFAULT (datetime('now'))
+            );
+
+            CREATE TABLE note_tags (
+                note_id INTEGER NOT NULL,
+                tag_id INTEGER NOT NULL,
+                PRIMARY KEY (note_id, tag_id),
+                FOREIGN KEY (note_id) REFERENCES notes(id) ON DELETE CASCADE,
+                FOREIGN KEY (tag_id) REFERENCES tags(id) ON DELETE CASCADE
+            );
+
+            CREATE INDEX idx_note_tags_tag ON note_tags(tag_id, note_id);",
+        after: None,
+    },
+    Migration {
+        version: 11,
+        description: "nested tabs",
+        sql: "ALTER TABLE tabs ADD COLUMN parent_id INTEGER REFERENCES tabs(id) ON DELETE CASCADE;
+            ALTER TABLE tabs ADD COLUMN trashed_with_parent INTEGER NOT NULL DEFAULT 0;
+
+            CREATE INDEX idx_tabs_parent ON tabs(parent_id, order_id);",
+        after: None,
+    },
+];
+
+/// The schema version a fully migrated database is at.
+pub fn latest_version() -> i64 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+#[derive(Debug)]
+pub enum InitError {
+    Connect(sqlx::Error),
+    Backup {
+        path: PathBuf,
+        source: sqlx::Error,
+    },
+    Migration {
+        version: i64,
+        description: &'static str,
+        backup: Option<PathBuf>,
+        source: sqlx::Error,
+    },
+    /// The database was written by a newer FocusBoard than this one.
+    TooNew {
+        found: i64,
+        supported: i64,
+    },
+}
+
+impl fmt::Display for InitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InitError::Connect(e) => write!(f, "Failed to open the database: {}", e),
+            InitError::Backup { path, source } => write!(
+                f,
+                "Failed to back up the database to {:?} before upgrading it: {}",
+                path, source
+            ),
+            InitError::Migration {
+                version,
+                description,
+                backup,
+                source,
+            } => {
+                write!(
+                    f,
+                    "Failed to upgrade the database to version {} ({}): {}. No changes from this step were saved.",
+                    version, description, source
+                )?;
+                if let Some(backup) = backup {
+                    write!(f, " A copy of the database from before the upgrade is at {:?}.", backup)?;
+                }
+                Ok(())
+            }
+            InitError::TooNew { found, supported } => write!(
+                f,
+                "The database is at schema version {} but this version of FocusBoard only supports up to {}. Please update FocusBoard.",
+                found, supported
+            ),
+        }
+    }
+}
+
+impl std::error::Error for InitError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            InitError::Connect(e) => Some(e),
+            InitError::Backup { source, .. } => Some(source),
+            InitError::Migration { source, .. } => Some(source),
+            InitError::TooNew { .. } => None,
+        }
+    }
+}
+
+impl From<sqlx::Error> for InitError {
+    fn from(e: sqlx::Error) -> Self {
+        InitError::Connect(e)
+    }
+}
+
+fn pool_options() -> SqlitePoolOptions {
+    SqlitePoolOptions::new().after_connect(|conn, _| {
+        Box::pin(async move {
+            conn.execute(sqlx::query(
+                "PRAGMA journal_mode = WAL;\
+                PRAGMA foreign_keys = ON;\
+                PRAGMA auto_vacuum = INCREMENTAL;\
+                PRAGMA optimize;",
+            ))
+            .await?;
+
+            Ok(())
+        })
+    })
+}
+
+pub async fn init_db(db_url: &str, backup_dir: &Path) -> Result<SqlitePool, InitError> {
+    let db = pool_options().connect(db_url).await?;
+
+    let mut conn = db.acquire().await?;
+    migrate(&mut conn, backup_dir).await?;
+
+    Ok(db)
+}
+
+/// A fully migrated, empty database that lives only as long as the returned pool. Every
+/// connection to `:memory:` is its own database, so the pool holds exactly one.
+pub async fn open_in_memory() -> Result<SqlitePool, InitError> {
+    let options = SqliteConnectOptions::new().in_memory(true);
+    let db = pool_options()
+        .max_connections(1)
+        .min_connections(1)
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect_with(options)
+        .await?;
+
+    let mut conn = db.acquire().await?;
+    // A fresh database has no tables, so this never needs the backup directory.
+    migrate(&mut conn, Path::new("")).await?;
+
+    Ok(db)
+}
+
+pub async fn schema_version(conn: &mut SqliteConnection) -> Result<i64, sqlx::Error> {
+    let (version,): (i64,) = sqlx::query_as("PRAGMA user_version")
+        .fetch_one(&mut *conn)
+        .await?;
+
+    Ok(version)
+}
+
+async fn migrate(conn: &mut SqliteConnection, backup_dir: &Path) -> Result<(), InitError> {
+    let current = schema_version(conn).await?;
+    let latest = latest_version();
+
+    if current > latest {
+        return Err(InitError::TooNew {
+            found: current,
+            supported: latest,
+        });
+    }
+
+    let pending: Vec<&Migration> = MIGRATIONS.iter().filter(|m| m.version > current).collect();
+    if pending.is_empty() {
+        info!("Database schema is up to date (version {})", current);
+        return Ok(());
+    }
+
+    let (table_count,): (i64,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%'",
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let backup = if table_count > 0 {
+        Some(backup_before_migration(conn, backup_dir, current, latest).await?)
+    } else {
+        None
+    };
+
+    for migration in pending {
+        info!(
+            "Applying database migration {}: {}",
+            migration.version, migration.description
+        );
+
+        apply_migration(conn, migration).await.map_err(|e| {
+            error!(
+                "Database migration {} ({}) failed: {:#}",
+                migration.version, migration.description, e
+            );
+            InitError::Migration {
+                version: migration.version,
+                description: migration.description,
+                backup: backup.clone(),
+                source: e,
+            }
+        })?;
+    }
+
+    info!(
+        "Database schema migrated from version {} to {}",
+        current, latest
+    );
+
+    Ok(())
+}
+
+async fn apply_migration(
+    conn: &mut SqliteConnection,
+    migration: &Migration,
+) -> Result<(), sqlx::Error> {
+    let mut transaction = sqlx::Connection::begin(&mut *conn).await?;
+
+    transaction.execute(sqlx::raw_sql(migration.sql)).await?;
+
+    sqlx::query(&format!("PRAGMA user_version = {}", migration.version))
+        .execute(&mut *transaction)
+        .await?;
+
+    if let Some(after) = migration.after {
+        after(&mut transaction).await?;
+    }
+
+    transaction.commit().await
+}
+
+fn backfill_note_search_text(
+    conn: &mut SqliteConnection,
+) -> Pin<Box<dyn Future<Output = Result<(), sqlx::Error>> + Send + '_>> {
+    Box::pin(async move {
+        let notes: Vec<(i64, String, Option<String>)> =
+            sqlx::query_as("SELECT id, title, content FROM notes")
+                .fetch_all(&mut *conn)
+                .await?;
+
+        for (id, title, content) in notes {
+            sqlx::query("UPDATE notes SET title_text = ?, content_text = ? WHERE id = ?")
+                .bind(html::to_plain_text(&title))
+                .bind(html::to_plain_text(content.as_deref().unwrap_or("")))
+                .bind(id)
+                .execute(&mut *conn)
+                .await?;
+        }
+
+        // Index everything in one go; the sync triggers only exist from the next migration on.
+        sqlx::query("INSERT INTO notes_fts (notes_fts) VALUES ('rebuild')")
+            .execute(&mut *conn)
+            .await?;
+
+        Ok(())
+    })
+}
+
+async fn backup_before_migration(
+    conn: &mut SqliteConnection,
+    backup_dir: &Path,
+    from: i64,
+    to: i64,
+) -> Result<PathBuf, InitError> {
+    let timestamp = OffsetDateTime::now_local()
+        .unwrap_or_else(|_| OffsetDateTime::now_utc())
+        .format(&format_description!(
+            "[year]-[month]-[day]_T[hour]H-[minute]M-[second]S"
+        ))
+        .unwrap_or_default();
+
+    let path = backup_dir.join(format!(
+        "pre-migration_v{}-to-v{}_{}.db",
+        from, to, timestamp
+    ));
+
+    if let Err(e) = fs::create_dir_all(backup_dir) {
+        error!(
+            "Failed to create backup directory {:?}: {:#}",
+            backup_dir, e
+        );
+        return Err(InitError::Backup {
+            path,
+            source: sqlx::Error::Io(e),
+        });
+    }
+
+    let path_str = path
+        .to_string_lossy()
+        .replace("\\", "/")
+        .replace('\'', "''");
+
+    sqlx::query(&format!("VACUUM INTO '{}'", path_str))
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| {
+            error!("Failed to back up database to {:?}: {:#}", path, e);
+            InitError::Backup {
+                path: path.clone(),
+                source: e,
+            }
+        })?;
+
+    info!("Pre-migration backup written to {:?}", path);
+
+    Ok(path)
+}
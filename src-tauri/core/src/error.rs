@@ -0,0 +1,106 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use specta::Type;
+use std::fmt;
+
+/// The error every service and command returns. It serializes as
+/// `{ "code": "not_found", "message": "...", "details": ... }`, so the frontend can branch on
+/// `code` and show `message` as is. Messages are written for users; the underlying SQL or I/O
+/// error is logged, never returned.
+#[derive(Serialize, Deserialize, Debug, Clone, Type)]
+#[serde(tag = "code", rename_all = "snake_case")]
+pub enum AppError {
+    /// The requested record does not exist (or is in the trash when it must not be).
+    NotFound {
+        message: String,
+        details: Option<Value>,
+    },
+    /// The request itself is invalid; retrying it unchanged will fail again.
+    Validation {
+        message: String,
+        details: Option<Value>,
+    },
+    /// The request clashes with the current state, e.g. a file or record already exists.
+    Conflict {
+        message: String,
+        details: Option<Value>,
+    },
+    /// The database failed; retrying may help.
+    Database {
+        message: String,
+        details: Option<Value>,
+    },
+    /// Reading or writing a file failed.
+    Io {
+        message: String,
+        details: Option<Value>,
+    },
+}
+
+impl AppError {
+    pub fn not_found(message: impl Into<String>) -> Self {
+        AppError::NotFound {
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn validation(message: impl Into<String>) -> Self {
+        AppError::Validation {
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn conflict(message: impl Into<String>) -> Self {
+        AppError::Conflict {
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn database(message: impl Into<String>) -> Self {
+        AppError::Database {
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn io(message: impl Into<String>) -> Self {
+        AppError::Io {
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    /// Attaches machine-readable context, e.g. the record a conflict was detected against.
+    pub fn with_details(mut self, value: impl Serialize) -> Self {
+        let value = serde_json::to_value(value).ok();
+        match &mut self {
+            AppError::NotFound { details, .. }
+            | AppError::Validation { details, .. }
+            | AppError::Conflict { details, .. }
+            | AppError::Database { details, .. }
+            | AppError::Io { details, .. } => *details = value,
+        }
+        self
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            AppError::NotFound { message, .. }
+            | AppError::Validation { message, .. }
+            | AppError::Conflict { message, .. }
+            | AppError::Database { message, .. }
+            | AppError::Io { message, .. } => message,
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for AppError {}
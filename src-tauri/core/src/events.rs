@@ -0,0 +1,672 @@
+use crate::error::AppError;
+use crate::ics::{self, IcsEvent};
+use crate::models::{CalendarEvent, IcsExportSummary, IcsImportSummary};
+use crate::recurrence::RecurrenceRule;
+use log::{error, info};
+use sqlx::{query_as, FromRow, SqlitePool};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use time::{macros::format_description, Date};
+
+/// Calendar events, their recurrence and iCalendar import and export.
+#[derive(Clone)]
+pub struct EventService {
+    pool: SqlitePool,
+}
+
+impl EventService {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create(
+        &self,
+        event_date: String,
+        event_name: String,
+        event_start: Option<i32>,
+        event_end: Option<i32>,
+        color: String,
+        recurrence: Option<RecurrenceRule>,
+    ) -> Result<CalendarEvent, AppError> {
+        let date = parse_event_date(&event_date)?;
+        if let Some(rule) = &recurrence {
+            rule.validate().map_err(AppError::validation)?;
+        }
+
+        let event = query_as::<_, CalendarEvent>(
+            r#"
+            INSERT INTO events (event_date, year_month, event_name, event_start, event_end, color, recurrence)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            RETURNING id, event_date, year_month, event_name, event_start, event_end, color, recurrence, version
+            "#
+        )
+        .bind(&event_date)
+        .bind(event_year_month(date))
+        .bind(event_name)
+        .bind(event_start)
+        .bind(event_end)
+        .bind(color)
+        .bind(recurrence.map(|rule| rule.to_string()))
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| {
+            error!("Failed to insert event to calendar: {:#}", e);
+            AppError::database("Failed to add event. Please try again")
+        })?;
+
+        Ok(event)
+    }
+
+    pub async fn month(
+        &self,
+        year_month: String,
+        onwards: Option<bool>,
+    ) -> Result<Vec<CalendarEvent>, AppError> {
+        let query = if onwards.unwrap_or(false) {
+            "SELECT * FROM events WHERE year_month >= ? ORDER BY year_month, event_start ASC"
+        } else {
+            "SELECT * FROM events WHERE year_month = ? ORDER BY event_start ASC"
+        };
+
+        let events = query_as::<_, CalendarEvent>(query)
+            .bind(year_month)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| {
+                error!("Failed to retrieve events: {:#}", e);
+                AppError::database("Failed to load events. Please try again")
+            })?;
+
+        Ok(events)
+    }
+
+    /// Events from `start_date` through `end_date`, both inclusive. Recurring events are expanded
+    /// into one entry per occurrence, with their exceptions applied.
+    pub async fn range(
+        &self,
+        start_date: String,
+        end_date: String,
+    ) -> Result<Vec<CalendarEvent>, AppError> {
+        let start = parse_event_date(&start_date)?;
+        let end = parse_event_date(&end_date)?;
+        if start > end {
+            return Err(AppError::validation(
+                "The start date must not be after the end date",
+            ));
+        }
+
+        let load_error = |e: sqlx::Error| {
+            error!(
+                "Failed to retrieve events from {} to {}: {:#}",
+                start_date, end_date, e
+            );
+            AppError::database("Failed to load events. Please try again")
+        };
+
+        let mut events = query_as::<_, CalendarEvent>(
+            r#"
+            SELECT * FROM events
+            WHERE recurrence IS NULL AND event_date BETWEEN ? AND ?
+            "#,
+        )
+        .bind(&start_date)
+        .bind(&end_date)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(load_error)?;
+
+        let series = query_as::<_, CalendarEvent>(
+            "SELECT * FROM events WHERE recurrence IS NOT NULL AND event_date <= ?",
+        )
+        .bind(&end_date)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(load_error)?;
+
+        let exceptions: HashMap<(i64, String), EventException> = query_as::<_, EventException>(
+            "SELECT * FROM event_exceptions WHERE occurrence_date BETWEEN ? AND ?",
+        )
+        .bind(&start_date)
+        .bind(&end_date)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(load_error)?
+        .into_iter()
+        .map(|exception| {
+            (
+                (exception.event_id, exception.occurrence_date.clone()),
+                exception,
+            )
+        })
+        .collect();
+
+        for event in series {
+            let (Some(rule), Ok(first)) = (&event.recurrence, parse_event_date(&event.event_date))
+            else {
+                continue;
+            };
+
+            for date in rule.occurrences(first, start, end) {
+                let date_str = date.to_string();
+                let mut occurrence = CalendarEvent {
+                    event_date: date_str.clone(),
+                    year_month: event_year_month(date),
+                    occurrence_date: Some(date_str.clone()),
+                    ..event.clone()
+                };
+
+                if let Some(exception) = exceptions.get(&(event.id, date_str)) {
+                    if exception.cancelled {
+                        continue;
+                    }
+                    if let Some(name) = &exception.event_name {
+                        occurrence.event_name = name.clone();
+                    }
+                    occurrence.event_start = exception.event_start;
+                    occurrence.event_end = exception.event_end;
+                }
+
+                events.push(occurrence);
+            }
+        }
+
+        events.sort_by(|a, b| {
+            (&a.event_date, a.event_start, a.id).cmp(&(&b.event_date, b.event_start, b.id))
+        });
+
+        Ok(events)
+    }
+
+    pub async fn get(&self, id: i64) -> Result<CalendarEvent, AppError> {
+        query_as::<_, CalendarEvent>("SELECT * FROM events WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| {
+                error!("Failed to fetch event {}: {:#}", id, e);
+                AppError::database("Failed to load event. Please try again")
+            })?
+            .ok_or_else(|| AppError::not_found(format!("Event with id {id} not found")))
+    }
+
+    /// Sets or clears the recurrence rule of an event. Exceptions that no longer match an
+    /// occurrence are left in place but have no effect.
+    pub async fn set_recurrence(
+        &self,
+        id: i64,
+        recurrence: Option<RecurrenceRule>,
+    ) -> Result<CalendarEvent, AppError> {
+        if let Some(rule) = &recurrence {
+            rule.validate().map_err(AppError::validation)?;
+        }
+
+        query_as::<_, CalendarEvent>(
+            "UPDATE events SET recurrence = ?, version = version + 1 WHERE id = ? RETURNING *",
+        )
+        .bind(recurrence.map(|rule| rule.to_string()))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| {
+            error!("Failed to set recurrence of event {}: {:#}", id, e);
+            AppError::database("Failed to update event. Please try again")
+        })?
+        .ok_or_else(|| AppError::not_found(format!("Event with id {id} not found")))
+    }
+
+    /// Saves the event if it is still at `version`, the same way `NoteService::update` does. With
+    /// `occurrence_date` set on a recurring event only that occurrence is changed, but the version
+    /// of the whole series is bumped. Returns the event row, not the occurrence.
+    pub async fn update(
+        &self,
+        id: i64,
+        version: i64,
+        event_name: String,
+        event_start: Option<i32>,
+        event_end: Option<i32>,
+        occurrence_date: Option<String>,
+    ) -> Result<CalendarEvent, AppError> {
+        let update_error = |e: sqlx::Error| {
+            error!("Failed to update event {}: {:#}", id, e);
+            AppError::database("Failed to update event. Please try again")
+        };
+
+        let mut transaction = self.pool.begin().await.map_err(|e| {
+            error!("Failed to start transaction: {:#}", e);
+            AppError::database("Failed to start transaction")
+        })?;
+
+        let current = query_as::<_, CalendarEvent>("SELECT * FROM events WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&mut *transaction)
+            .await
+            .map_err(update_error)?
+            .ok_or_else(|| AppError::not_found(format!("Event with id {id} not found")))?;
+
+        if current.version != version {
+            return Err(AppError::conflict(
+                "This event was changed somewhere else since you opened it",
+            )
+            .with_details(&current));
+        }
+
+        let occurrence_date = occurrence_date.filter(|_| current.recurrence.is_some());
+
+        let event = if let Some(occurrence_date) = occurrence_date {
+            check_occurrence(&current, &occurrence_date)?;
+
+            sqlx::query(
+                r#"
+                INSERT INTO event_exceptions (event_id, occurrence_date, event_name, event_start, event_end)
+                VALUES (?, ?, ?, ?, ?)
+                ON CONFLICT (event_id, occurrence_date) DO UPDATE
+                SET event_name = excluded.event_name,
+                    event_start = excluded.event_start,
+                    event_end = excluded.event_end
+                "#,
+            )
+            .bind(id)
+            .bind(&occurrence_date)
+            .bind(event_name)
+            .bind(event_start)
+            .bind(event_end)
+            .execute(&mut *transaction)
+            .await
+            .map_err(|e| {
+                error!(
+                    "Failed to update occurrence {} of event {}: {:#}",
+                    occurrence_date, id, e
+                );
+                AppError::database("Failed to update event. Please try again")
+            })?;
+
+            query_as::<_, CalendarEvent>(
+                "UPDATE events SET version = version + 1 WHERE id = ? RETURNING *",
+            )
+            .bind(id)
+            .fetch_one(&mut *transaction)
+            .await
+            .map_err(update_error)?
+        } else {
+            query_as::<_, CalendarEvent>(
+                r#"
+                UPDATE events
+                SET event_name = ?, event_start = ?, event_end = ?, version = version + 1
+                WHERE id = ?
+                RETURNING *
+                "#,
+            )
+            .bind(event_name)
+            .bind(event_start)
+            .bind(event_end)
+            .bind(id)
+            .fetch_one(&mut *transaction)
+            .await
+            .map_err(update_error)?
+        };
+
+        transaction.commit().await.map_err(|e| {
+            error!("Failed to commit transaction: {:#}", e);
+            AppError::database("Failed to commit transaction")
+        })?;
+
+        Ok(event)
+    }
+
+    /// Deletes the event if it is still at `version`, the same way `update` saves it. With
+    /// `occurrence_date` set on a recurring event only that occurrence is cancelled, and the
+    /// version of the series is bumped.
+    pub async fn delete(
+        &self,
+        id: i64,
+        version: i64,
+        occurrence_date: Option<String>,
+    ) -> Result<(), AppError> {
+        let delete_error = |e: sqlx::Error| {
+            error!("Failed to delete event {}: {:#}", id, e);
+            AppError::database("Failed to delete event. Please try again")
+        };
+
+        let mut transaction = self.pool.begin().await.map_err(|e| {
+            error!("Failed to start transaction: {:#}", e);
+            AppError::database("Failed to start transaction")
+        })?;
+
+        let current = query_as::<_, CalendarEvent>("SELECT * FROM events WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&mut *transaction)
+            .await
+            .map_err(delete_error)?
+            .ok_or_else(|| AppError::not_found(format!("Event with id {id} not found")))?;
+
+        if current.version != version {
+            return Err(AppError::conflict(
+                "This event was changed somewhere else since you opened it",
+            )
+            .with_details(&current));
+        }
+
+        let occurrence_date = occurrence_date.filter(|_| current.recurrence.is_some());
+
+        if let Some(occurrence_date) = occurrence_date {
+            check_occurrence(&current, &occurrence_date)?;
+
+            sqlx::query(
+                r#"
+                INSERT INTO event_exceptions (event_id, occurrence_date, cancelled)
+                VALUES (?, ?, 1)
+                ON CONFLICT (event_id, occurrence_date) DO UPDATE SET cancelled = 1
+                "#,
+            )
+            .bind(id)
+            .bind(&occurrence_date)
+            .execute(&mut *transaction)
+            .await
+            .map_err(|e| {
+                error!(
+                    "Failed to delete occurrence {} of event {}: {:#}",
+                    occurrence_date, id, e
+                );
+                AppError::database("Failed to delete event. Please try again")
+            })?;
+
+            sqlx::query("UPDATE events SET version = version + 1 WHERE id = ?")
+                .bind(id)
+                .execute(&mut *transaction)
+                .await
+                .map_err(delete_error)?;
+        } else {
+            sqlx::query("DELETE FROM events WHERE id = ?")
+                .bind(id)
+                .execute(&mut *transaction)
+                .await
+                .map_err(delete_error)?;
+        }
+
+        transaction.commit().await.map_err(|e| {
+            error!("Failed to commit transaction: {:#}", e);
+            AppError::database("Failed to commit transaction")
+        })?;
+
+        Ok(())
+    }
+
+    /// Writes every event to the iCalendar file at `path`.
+    pub async fn export_ics(&self, path: &Path) -> Result<IcsExportSummary, AppError> {
+        let export_error = |e: sqlx::Error| {
+            error!("Failed to read events for export: {:#}", e);
+            AppError::database("Failed to export events. Please try again")
+        };
+
+        let events =
+            query_as::<_, CalendarEvent>("SELECT * FROM events ORDER BY event_date, event_start")
+                .fetch_all(&self.pool)
+                .await
+                .map_err(export_error)?;
+
+        let uids: HashMap<i64, String> = query_as::<_, (i64, String)>("SELECT id, uid FROM events")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(export_error)?
+            .into_iter()
+            .collect();
+
+        let exceptions = query_as::<_, EventException>(
+            "SELECT * FROM event_exceptions ORDER BY occurrence_date",
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(export_error)?;
+
+        let mut calendar = Vec::with_capacity(events.len());
+
+        for event in &events {
+            let uid = uids.get(&event.id).cloned().unwrap_or_default();
+            let date = parse_event_date(&event.event_date).ok();
+            let recurring = event.recurrence.is_some();
+            let own_exceptions = exceptions
+                .iter()
+                .filter(|e| recurring && e.event_id == event.id);
+
+            let mut excluded = Vec::new();
+            let mut overrides = Vec::new();
+            for exception in own_exceptions {
+                let Ok(occurrence) = parse_event_date(&exception.occurrence_date) else {
+                    continue;
+                };
+                if exception.cancelled {
+                    excluded.push(occurrence);
+                } else {
+                    overrides.push(IcsEvent {
+                        uid: uid.clone(),
+                        date: Some(occurrence),
+                        start: exception.event_start,
+                        end: exception.event_end,
+                        summary: exception
+                            .event_name
+                            .clone()
+                            .unwrap_or_else(|| event.event_name.clone()),
+                        color: Some(event.color.clone()),
+                        recurrence_id: Some(occurrence),
+                        ..IcsEvent::default()
+                    });
+                }
+            }
+
+            calendar.push(IcsEvent {
+                uid,
+                date,
+                start: event.event_start,
+                end: event.event_end,
+                summary: event.event_name.clone(),
+                color: Some(event.color.clone()),
+                recurrence: event.recurrence.clone(),
+                excluded,
+                recurrence_id: None,
+            });
+            calendar.extend(overrides);
+        }
+
+        fs::write(path, ics::write_calendar(&calendar)).map_err(|e| {
+            error!("Failed to write calendar to {:?}: {:#}", path, e);
+            AppError::io("Failed to write the calendar file.")
+        })?;
+
+        info!("Exported {} events to {:?}", events.len(), path);
+
+        Ok(IcsExportSummary {
+            path: path.to_string_lossy().into_owned(),
+            event_count: events.len(),
+        })
+    }
+
+    /// Imports the VEVENTs of the iCalendar file at `path`. Events whose UID is already in the
+    /// calendar are skipped as duplicates.
+    pub async fn import_ics(&self, path: &Path) -> Result<IcsImportSummary, AppError> {
+        let text = fs::read_to_string(path).map_err(|e| {
+            error!("Failed to read calendar file {:?}: {:#}", path, e);
+            AppError::io("Failed to read the calendar file.")
+        })?;
+
+        let (events, mut failed) = ics::parse_calendar(&text);
+        let (masters, overrides): (Vec<IcsEvent>, Vec<IcsEvent>) = events
+            .into_iter()
+            .partition(|event| event.recurrence_id.is_none());
+
+        let import_error = |e: sqlx::Error| {
+            error!("Failed to import events from {:?}: {:#}", path, e);
+            AppError::database("Failed to import events. Please try again")
+        };
+
+        let mut transaction = self.pool.begin().await.map_err(|e| {
+            error!("Failed to start transaction: {:#}", e);
+            AppError::database("Failed to start transaction")
+        })?;
+
+        let mut imported: HashMap<String, i64> = HashMap::new();
+        let mut duplicates = 0;
+
+        for event in masters {
+            let Some(date) = event.date else {
+                continue;
+            };
+
+            let exists: Option<(i64,)> = sqlx::query_as("SELECT id FROM events WHERE uid = ?")
+                .bind(&event.uid)
+                .fetch_optional(&mut *transaction)
+                .await
+                .map_err(import_error)?;
+
+            if exists.is_some() || imported.contains_key(&event.uid) {
+                duplicates += 1;
+                continue;
+            }
+
+            let (id,): (i64,) = sqlx::query_as(
+                r#"
+                INSERT INTO events (event_date, year_month, event_name, event_start, event_end, color, recurrence, uid)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+                RETURNING id
+                "#,
+            )
+            .bind(date.to_string())
+            .bind(event_year_month(date))
+            .bind(&event.summary)
+            .bind(event.start)
+            .bind(event.end)
+            .bind(event.color.as_deref().unwrap_or(DEFAULT_EVENT_COLOR))
+            .bind(event.recurrence.as_ref().map(|rule| rule.to_string()))
+            .bind(&event.uid)
+            .fetch_one(&mut *transaction)
+            .await
+            .map_err(import_error)?;
+
+            if event.recurrence.is_some() {
+                for excluded in &event.excluded {
+                    sqlx::query(
+                        r#"
+                        INSERT INTO event_exceptions (event_id, occurrence_date, cancelled)
+                        VALUES (?, ?, 1)
+                        ON CONFLICT (event_id, occurrence_date) DO UPDATE SET cancelled = 1
+                        "#,
+                    )
+                    .bind(id)
+                    .bind(excluded.to_string())
+                    .execute(&mut *transaction)
+                    .await
+                    .map_err(import_error)?;
+                }
+            }
+
+            imported.insert(event.uid, id);
+        }
+
+        for event in overrides {
+            let Some(occurrence) = event.recurrence_id else {
+                continue;
+            };
+
+            let Some(&id) = imported.get(&event.uid) else {
+                let exists: Option<(i64,)> = sqlx::query_as("SELECT id FROM events WHERE uid = ?")
+                    .bind(&event.uid)
+                    .fetch_optional(&mut *transaction)
+                    .await
+                    .map_err(import_error)?;
+
+                if exists.is_some() {
+                    duplicates += 1;
+                } else {
+                    failed.push(format!(
+                        "Event {:?}: the recurring event it changes is not in the file",
+                        event.summary
+                    ));
+                }
+                continue;
+            };
+
+            sqlx::query(
+                r#"
+                INSERT INTO event_exceptions (event_id, occurrence_date, event_name, event_start, event_end)
+                VALUES (?, ?, ?, ?, ?)
+                ON CONFLICT (event_id, occurrence_date) DO UPDATE
+                SET event_name = excluded.event_name,
+                    event_start = excluded.event_start,
+                    event_end = excluded.event_end
+                "#,
+            )
+            .bind(id)
+            .bind(occurrence.to_string())
+            .bind(&event.summary)
+            .bind(event.start)
+            .bind(event.end)
+            .execute(&mut *transaction)
+            .await
+            .map_err(import_error)?;
+        }
+
+        transaction.commit().await.map_err(|e| {
+            error!("Failed to commit transaction: {:#}", e);
+            AppError::database("Failed to commit transaction")
+        })?;
+
+        failed.sort();
+        info!(
+            "Imported {} events from {:?} ({} duplicates, {} failed)",
+            imported.len(),
+            path,
+            duplicates,
+            failed.len()
+        );
+
+        Ok(IcsImportSummary {
+            path: path.to_string_lossy().into_owned(),
+            imported: imported.len(),
+            duplicates,
+            failed,
+        })
+    }
+}
+
+#[derive(FromRow, Debug, Clone)]
+struct EventException {
+    event_id: i64,
+    occurrence_date: String,
+    cancelled: bool,
+    event_name: Option<String>,
+    event_start: Option<i32>,
+    event_end: Option<i32>,
+}
+
+fn parse_event_date(date: &str) -> Result<Date, AppError> {
+    Date::parse(date, &format_description!("[year]-[month]-[day]"))
+        .map_err(|_| AppError::validation(format!("Invalid date {:?}, expected YYYY-MM-DD", date)))
+}
+
+/// `year_month` is kept for the month queries but always derived from `event_date`.
+fn event_year_month(date: Date) -> String {
+    format!("{:04}-{:02}", date.year(), date.month() as u8)
+}
+
+/// Checks that `occurrence_date` is a real occurrence of the recurring `event`.
+fn check_occurrence(event: &CalendarEvent, occurrence_date: &str) -> Result<(), AppError> {
+    let date = parse_event_date(occurrence_date)?;
+    let first = parse_event_date(&event.event_date)?;
+
+    let is_occurrence = event
+        .recurrence
+        .as_ref()
+        .is_some_and(|rule| rule.occurrences(first, date, date).contains(&date));
+
+    if !is_occurrence {
+        return Err(AppError::validation(format!(
+            "Event {} does not occur on {}",
+            event.id, occurrence_date
+        )));
+    }
+
+    Ok(())
+}
+
+/// Colour given to imported events that don't carry one.
+const DEFAULT_EVENT_COLOR: &str = "#36A2EB";
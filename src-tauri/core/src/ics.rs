@@ -0,0 +1,428 @@
+use crate::recurrence::RecurrenceRule;
+use time::{
+    macros::format_description, Date, Duration, OffsetDateTime, PrimitiveDateTime, Time, UtcOffset,
+};
+
+const PRODID: &str = "-//Stenberg-N//FocusBoard//EN";
+const COLOR_PROPERTY: &str = "X-FOCUSBOARD-COLOR";
+const SECONDS_PER_DAY: i32 = 86_400;
+
+/// One VEVENT. Times are seconds since midnight like `events.event_start`/`event_end`;
+/// an event without a start time is an all-day event.
+#[derive(Debug, Clone, Default)]
+pub struct IcsEvent {
+    pub uid: String,
+    pub date: Option<Date>,
+    pub start: Option<i32>,
+    pub end: Option<i32>,
+    pub summary: String,
+    pub color: Option<String>,
+    pub recurrence: Option<RecurrenceRule>,
+    /// Occurrences removed from a recurring event (EXDATE).
+    pub excluded: Vec<Date>,
+    /// Set when this VEVENT overrides one occurrence of the recurring event with the same UID.
+    pub recurrence_id: Option<Date>,
+}
+
+pub fn write_calendar(events: &[IcsEvent]) -> String {
+    let stamp = OffsetDateTime::now_utc()
+        .format(&format_description!(
+            "[year][month][day]T[hour][minute][second]Z"
+        ))
+        .unwrap_or_default();
+
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!("PRODID:{}", PRODID),
+        "CALSCALE:GREGORIAN".to_string(),
+    ];
+
+    for event in events {
+        let Some(date) = event.date else {
+            continue;
+        };
+
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!("UID:{}", escape_text(&event.uid)));
+        lines.push(format!("DTSTAMP:{}", stamp));
+        if let Some(recurrence_id) = event.recurrence_id {
+            // Identifies the occurrence by its original start, which is the recurring event's.
+            let original_start = events
+                .iter()
+                .find(|master| master.uid == event.uid && master.recurrence_id.is_none())
+                .map_or(event.start, |master| master.start);
+            lines.push(date_property(
+                "RECURRENCE-ID",
+                recurrence_id,
+                original_start,
+            ));
+        }
+        lines.push(date_property("DTSTART", date, event.start));
+
+        match (event.start, event.end) {
+            (Some(_), Some(end)) => {
+                let end_date = date + Duration::days(i64::from(end / SECONDS_PER_DAY));
+                lines.push(date_property(
+                    "DTEND",
+                    end_date,
+                    Some(end % SECONDS_PER_DAY),
+                ));
+            }
+            (None, _) => lines.push(date_property("DTEND", date + Duration::days(1), None)),
+            (Some(_), None) => {}
+        }
+
+        lines.push(format!("SUMMARY:{}", escape_text(&event.summary)));
+        if let Some(rule) = &event.recurrence {
+            lines.push(format!("RRULE:{}", rule));
+        }
+        for excluded in &event.excluded {
+            lines.push(date_property("EXDATE", *excluded, event.start));
+        }
+        if let Some(color) = &event.color {
+            lines.push(format!("{}:{}", COLOR_PROPERTY, escape_text(color)));
+        }
+        lines.push("END:VEVENT".to_string());
+    }
+
+    lines.push("END:VCALENDAR".to_string());
+
+    let mut calendar = String::new();
+    for line in lines {
+        fold_line(&line, &mut calendar);
+    }
+    calendar
+}
+
+/// `NAME:20261017T090000` for timed events, `NAME;VALUE=DATE:20261017` for all-day ones.
+fn date_property(name: &str, date: Date, seconds: Option<i32>) -> String {
+    let day = format!(
+        "{:04}{:02}{:02}",
+        date.year(),
+        date.month() as u8,
+        date.day()
+    );
+
+    match seconds {
+        Some(seconds) => format!(
+            "{}:{}T{:02}{:02}{:02}",
+            name,
+            day,
+            seconds / 3600,
+            seconds % 3600 / 60,
+            seconds % 60
+        ),
+        None => format!("{};VALUE=DATE:{}", name, day),
+    }
+}
+
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+fn unescape_text(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => out.push('\n'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// Content lines are limited to 75 octets; longer ones continue on lines starting with a space.
+fn fold_line(line: &str, out: &mut String) {
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
+
+struct Property<'a> {
+    name: String,
+    params: Vec<(String, &'a str)>,
+    value: &'a str,
+}
+
+impl Property<'_> {
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| *value)
+    }
+}
+
+fn parse_property(line: &str) -> Option<Property<'_>> {
+    // The value starts at the first colon that isn't inside a quoted parameter value.
+    let mut in_quotes = false;
+    let colon = line.char_indices().find_map(|(i, c)| match c {
+        '"' => {
+            in_quotes = !in_quotes;
+            None
+        }
+        ':' if !in_quotes => Some(i),
+        _ => None,
+    })?;
+
+    let (head, value) = (&line[..colon], &line[colon + 1..]);
+    let mut parts = head.split(';');
+    let name = parts.next()?.trim().to_ascii_uppercase();
+    let params = parts
+        .filter_map(|param| param.split_once('='))
+        .map(|(key, value)| (key.trim().to_ascii_uppercase(), value.trim_matches('"')))
+        .collect();
+
+    Some(Property {
+        name,
+        params,
+        value,
+    })
+}
+
+/// Parses a DATE or DATE-TIME value into a date and seconds since midnight. UTC times are
+/// converted to local time; times with a TZID are taken as local wall-clock time.
+fn parse_date_value(property: &Property) -> Result<(Date, Option<i32>), String> {
+    let value = property.value.trim();
+    let invalid = || format!("Invalid {} value {:?}", property.name, value);
+
+    let date = Date::parse(
+        value.get(..8).ok_or_else(invalid)?,
+        &format_description!("[year][month][day]"),
+    )
+    .map_err(|_| invalid())?;
+
+    if property.param("VALUE") == Some("DATE") || value.len() == 8 {
+        return Ok((date, None));
+    }
+
+    let time = Time::parse(
+        value.get(9..15).ok_or_else(invalid)?,
+        &format_description!("[hour][minute][second]"),
+    )
+    .map_err(|_| invalid())?;
+
+    let mut local = PrimitiveDateTime::new(date, time);
+    if value.ends_with('Z') {
+        let offset = UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC);
+        let converted = local.assume_utc().to_offset(offset);
+        local = PrimitiveDateTime::new(converted.date(), converted.time());
+    }
+
+    Ok((local.date(), Some(seconds_of_day(local.time()))))
+}
+
+fn seconds_of_day(time: Time) -> i32 {
+    i32::from(time.hour()) * 3600 + i32::from(time.minute()) * 60 + i32::from(time.second())
+}
+
+/// Parses the seconds of a simple DURATION such as `PT1H30M` or `P1D`.
+fn parse_duration(value: &str) -> Option<i64> {
+    let value = value.trim().strip_prefix('+').unwrap_or(value.trim());
+    let value = value.strip_prefix('P')?;
+
+    let mut seconds = 0;
+    let mut number = String::new();
+    for c in value.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' => {}
+            'W' | 'D' | 'H' | 'M' | 'S' => {
+                let n: i64 = number.parse().ok()?;
+                number.clear();
+                seconds += n * match c {
+                    'W' => 7 * 86_400,
+                    'D' => 86_400,
+                    'H' => 3600,
+                    'M' => 60,
+                    _ => 1,
+                };
+            }
+            _ => return None,
+        }
+    }
+    Some(seconds)
+}
+
+/// Parses every VEVENT in `text`. Events that can't be understood are reported in the second
+/// list instead of aborting the whole file.
+pub fn parse_calendar(text: &str) -> (Vec<IcsEvent>, Vec<String>) {
+    let mut lines: Vec<String> = Vec::new();
+    for raw in text.lines() {
+        let raw = raw.trim_end_matches('\r');
+        match raw.strip_prefix(' ').or_else(|| raw.strip_prefix('\t')) {
+            Some(continuation) if !lines.is_empty() => {
+                lines.last_mut().unwrap().push_str(continuation)
+            }
+            _ => lines.push(raw.to_string()),
+        }
+    }
+
+    let mut events = Vec::new();
+    let mut errors = Vec::new();
+    let mut current: Option<PendingEvent> = None;
+    let mut depth = 0;
+
+    for line in &lines {
+        let Some(property) = parse_property(line) else {
+            continue;
+        };
+
+        match (
+            property.name.as_str(),
+            property.value.trim().to_ascii_uppercase().as_str(),
+        ) {
+            ("BEGIN", "VEVENT") if current.is_none() => {
+                current = Some(PendingEvent::default());
+                depth = 0;
+                continue;
+            }
+            ("END", "VEVENT") if depth == 0 => {
+                if let Some(pending) = current.take() {
+                    match pending.finish() {
+                        Ok(event) => events.push(event),
+                        Err(e) => errors.push(e),
+                    }
+                }
+                continue;
+            }
+            // Nested components such as VALARM have properties we must not mistake for the event's.
+            ("BEGIN", _) if current.is_some() => {
+                depth += 1;
+                continue;
+            }
+            ("END", _) if current.is_some() => {
+                depth -= 1;
+                continue;
+            }
+            _ => {}
+        }
+
+        let Some(PendingEvent {
+            event,
+            end,
+            duration,
+            problems,
+        }) = current.as_mut()
+        else {
+            continue;
+        };
+        if depth > 0 {
+            continue;
+        }
+
+        match property.name.as_str() {
+            "UID" => event.uid = unescape_text(property.value).trim().to_string(),
+            "SUMMARY" => event.summary = unescape_text(property.value),
+            "DTSTART" => match parse_date_value(&property) {
+                Ok((date, start)) => {
+                    event.date = Some(date);
+                    event.start = start;
+                }
+                Err(e) => problems.push(e),
+            },
+            "DTEND" => match parse_date_value(&property) {
+                Ok(value) => *end = Some(value),
+                Err(e) => problems.push(e),
+            },
+            "DURATION" => *duration = parse_duration(property.value),
+            "RRULE" => match property.value.parse::<RecurrenceRule>() {
+                Ok(rule) => event.recurrence = Some(rule),
+                Err(e) => problems.push(e),
+            },
+            "EXDATE" => {
+                for value in property.value.split(',') {
+                    let single = Property {
+                        name: property.name.clone(),
+                        params: property.params.clone(),
+                        value,
+                    };
+                    match parse_date_value(&single) {
+                        Ok((date, _)) => event.excluded.push(date),
+                        Err(e) => problems.push(e),
+                    }
+                }
+            }
+            "RECURRENCE-ID" => match parse_date_value(&property) {
+                Ok((date, _)) => event.recurrence_id = Some(date),
+                Err(e) => problems.push(e),
+            },
+            // Prefer our own colour property over the RFC 7986 one when both are present.
+            "COLOR" | COLOR_PROPERTY
+                if property.name == COLOR_PROPERTY || event.color.is_none() =>
+            {
+                event.color = Some(unescape_text(property.value).trim().to_string());
+            }
+            _ => {}
+        }
+    }
+
+    (events, errors)
+}
+
+#[derive(Default)]
+struct PendingEvent {
+    event: IcsEvent,
+    end: Option<(Date, Option<i32>)>,
+    duration: Option<i64>,
+    problems: Vec<String>,
+}
+
+impl PendingEvent {
+    fn finish(self) -> Result<IcsEvent, String> {
+        let PendingEvent {
+            mut event,
+            end,
+            duration,
+            problems,
+        } = self;
+
+        let label = if event.summary.is_empty() {
+            format!("Event {:?}", event.uid)
+        } else {
+            format!("Event {:?}", event.summary)
+        };
+
+        if !problems.is_empty() {
+            return Err(format!("{}: {}", label, problems.join("; ")));
+        }
+        if event.uid.is_empty() {
+            return Err(format!("{}: missing UID", label));
+        }
+        let Some(date) = event.date else {
+            return Err(format!("{}: missing DTSTART", label));
+        };
+
+        if let Some(start) = event.start {
+            let end_seconds = match (end, duration) {
+                (Some((end_date, Some(end))), _) => {
+                    Some((end_date - date).whole_seconds() + i64::from(end))
+                }
+                (_, Some(duration)) => Some(i64::from(start) + duration),
+                _ => None,
+            };
+            // FocusBoard events live within a single day.
+            event.end = end_seconds.map(|end| end.clamp(0, i64::from(SECONDS_PER_DAY)) as i32);
+        }
+
+        Ok(event)
+    }
+}
@@ -0,0 +1,31 @@
+//! Everything FocusBoard does with its data, independent of the desktop app: the schema and
+//! its migrations, the services that read and write notes, tabs, tags, the trash, timers and
+//! events, and backups. Each service wraps a `SqlitePool`; open one with [`db::init_db`], or
+//! [`db::open_in_memory`] for a throwaway database.
+
+pub mod backup;
+pub mod db;
+pub mod diff;
+pub mod error;
+pub mod events;
+pub mod html;
+pub mod ics;
+pub mod markdown;
+pub mod models;
+pub mod notes;
+pub mod recurrence;
+pub mod storage;
+pub mod tabs;
+pub mod tags;
+pub mod timers;
+pub mod transfer;
+pub mod trash;
+
+pub use error::AppError;
+pub use events::EventService;
+pub use notes::NoteService;
+pub use tabs::TabService;
+pub use tags::TagService;
+pub use timers::TimerService;
+pub use transfer::TransferService;
+pub use trash::TrashService;
@@ -0,0 +1,576 @@
+use crate::html::{self, Node};
+
+/// Converts TipTap HTML to Markdown. Underline has no Markdown syntax, so it is kept as `<u>`.
+pub fn from_html(source: &str) -> String {
+    let nodes = html::parse(source);
+    let mut blocks = Vec::new();
+    render_blocks(&nodes, &mut blocks);
+    blocks.join("\n\n")
+}
+
+fn is_block(name: &str) -> bool {
+    matches!(
+        name,
+        "p" | "div"
+            | "h1"
+            | "h2"
+            | "h3"
+            | "h4"
+            | "h5"
+            | "h6"
+            | "ul"
+            | "ol"
+            | "li"
+            | "blockquote"
+            | "pre"
+            | "hr"
+    )
+}
+
+fn render_blocks(nodes: &[Node], blocks: &mut Vec<String>) {
+    let mut inline_run: Vec<Node> = Vec::new();
+
+    let flush = |run: &mut Vec<Node>, blocks: &mut Vec<String>| {
+        push_paragraph(&render_inline(run), blocks);
+        run.clear();
+    };
+
+    for node in nodes {
+        let Node::Element { name, children, .. } = node else {
+            inline_run.push(node.clone());
+            continue;
+        };
+        if !is_block(name) {
+            inline_run.push(node.clone());
+            continue;
+        }
+        flush(&mut inline_run, blocks);
+
+        match name.as_str() {
+            "p" | "div" | "li" => {
+                if children
+                    .iter()
+                    .any(|child| matches!(child, Node::Element { name, .. } if is_block(name)))
+                {
+                    render_blocks(children, blocks);
+                } else {
+                    push_paragraph(&render_inline(children), blocks);
+                }
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let level = name[1..].parse().unwrap_or(1);
+                blocks.push(format!(
+                    "{} {}",
+                    "#".repeat(level),
+                    render_inline(children).trim()
+                ));
+            }
+            "ul" | "ol" => blocks.push(render_list(node, "")),
+            "blockquote" => {
+                let mut inner = Vec::new();
+                render_blocks(children, &mut inner);
+                let quoted: Vec<String> = inner
+                    .join("\n\n")
+                    .lines()
+                    .map(|line| {
+                        if line.is_empty() {
+                            ">".to_string()
+                        } else {
+                            format!("> {}", line)
+                        }
+                    })
+                    .collect();
+                blocks.push(quoted.join("\n"));
+            }
+            "pre" => {
+                let code = text_content(children);
+                blocks.push(format!("```\n{}\n```", code.trim_end_matches('\n')));
+            }
+            "hr" => blocks.push("---".to_string()),
+            _ => {}
+        }
+    }
+
+    flush(&mut inline_run, blocks);
+}
+
+/// Adds a paragraph, escaping a leading character that would otherwise start a heading,
+/// quote or list.
+fn push_paragraph(text: &str, blocks: &mut Vec<String>) {
+    let text = text.trim();
+    if text.is_empty() {
+        return;
+    }
+
+    let ordered_marker = text
+        .split_once(". ")
+        .filter(|(number, _)| !number.is_empty() && number.bytes().all(|b| b.is_ascii_digit()));
+
+    if let Some((number, rest)) = ordered_marker {
+        blocks.push(format!("{}\\. {}", number, rest));
+    } else if text.starts_with(['#', '>', '-', '+']) {
+        blocks.push(format!("\\{}", text));
+    } else {
+        blocks.push(text.to_string());
+    }
+}
+
+fn render_list(list: &Node, indent: &str) -> String {
+    let Node::Element { name, children, .. } = list else {
+        return String::new();
+    };
+    let ordered = name == "ol";
+    let first: usize = list
+        .attr("start")
+        .and_then(|start| start.parse().ok())
+        .unwrap_or(1);
+
+    let mut lines = Vec::new();
+
+    let items = children
+        .iter()
+        .filter(|child| matches!(child, Node::Element { name, .. } if name == "li"));
+
+    for (index, item) in items.enumerate() {
+        let Node::Element { children, .. } = item else {
+            continue;
+        };
+        let marker = if ordered {
+            format!("{}.", first + index)
+        } else {
+            "-".to_string()
+        };
+
+        let mut text_parts = Vec::new();
+        let mut nested = Vec::new();
+        for child in children {
+            match child {
+                Node::Element { name, .. } if name == "ul" || name == "ol" => {
+                    // Nested lists line up with the text of the item they belong to.
+                    let indent = format!("{}{}", indent, " ".repeat(marker.len() + 1));
+                    nested.push(render_list(child, &indent))
+                }
+                Node::Element { name, children, .. } if name == "p" => {
+                    text_parts.push(render_inline(children).trim().to_string())
+                }
+                _ => text_parts.push(render_inline(std::slice::from_ref(child))),
+            }
+        }
+
+        let text = text_parts
+            .iter()
+            .map(|part| part.trim())
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        lines.push(format!("{}{} {}", indent, marker, text));
+        lines.extend(nested);
+    }
+
+    lines.join("\n")
+}
+
+fn render_inline(nodes: &[Node]) -> String {
+    let mut out = String::new();
+
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(&escape(&collapse_whitespace(text))),
+            Node::Element { name, children, .. } => match name.as_str() {
+                "strong" | "b" => out.push_str(&wrap(&render_inline(children), "**", "**")),
+                "em" | "i" => out.push_str(&wrap(&render_inline(children), "*", "*")),
+                "u" => out.push_str(&wrap(&render_inline(children), "<u>", "</u>")),
+                "s" | "strike" | "del" => out.push_str(&wrap(&render_inline(children), "~~", "~~")),
+                "code" => out.push_str(&wrap(&text_content(children), "`", "`")),
+                "br" => out.push_str("\\\n"),
+                "a" => {
+                    let text = render_inline(children);
+                    match node.attr("href") {
+                        Some(href) => out.push_str(&format!("[{}]({})", text, href)),
+                        None => out.push_str(&text),
+                    }
+                }
+                "img" => out.push_str(&format!(
+                    "![{}]({})",
+                    escape(node.attr("alt").unwrap_or("")),
+                    node.attr("src").unwrap_or("")
+                )),
+                _ => out.push_str(&render_inline(children)),
+            },
+        }
+    }
+
+    out
+}
+
+/// Puts markers around the text but outside its surrounding spaces, since `** bold**` isn't
+/// bold in Markdown.
+fn wrap(text: &str, open: &str, close: &str) -> String {
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        return text.to_string();
+    }
+    let leading = &text[..text.len() - text.trim_start().len()];
+    let trailing = &text[text.trim_end().len()..];
+    format!("{}{}{}{}{}", leading, open, trimmed, close, trailing)
+}
+
+fn text_content(nodes: &[Node]) -> String {
+    let mut out = String::new();
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Element { name, .. } if name == "br" => out.push('\n'),
+            Node::Element { children, .. } => out.push_str(&text_content(children)),
+        }
+    }
+    out
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut last_space = false;
+    for c in text.chars() {
+        if c.is_whitespace() && c != '\u{a0}' {
+            if !last_space {
+                out.push(' ');
+            }
+            last_space = true;
+        } else {
+            out.push(c);
+            last_space = false;
+        }
+    }
+    out
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '*' | '_' | '`' | '[' | ']' | '<' | '~') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Converts Markdown to HTML limited to what the TipTap editor understands. Raw HTML in the
+/// source is escaped (apart from `<u>`, which `from_html` produces), so the result is safe to
+/// store as note content.
+pub fn to_html(source: &str) -> String {
+    let lines: Vec<&str> = source.lines().collect();
+    render_markdown_blocks(&lines)
+}
+
+fn is_rule(line: &str) -> bool {
+    let compact: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+    compact.len() >= 3
+        && ["-", "*", "_"]
+            .iter()
+            .any(|marker| compact.chars().all(|c| c.to_string() == *marker))
+}
+
+/// Returns the indentation, whether the list is ordered, and the text after the marker.
+fn list_item(line: &str) -> Option<(usize, bool, &str)> {
+    let indent = line.len() - line.trim_start().len();
+    let rest = line.trim_start();
+
+    for marker in ["- ", "* ", "+ "] {
+        if let Some(text) = rest.strip_prefix(marker) {
+            return Some((indent, false, text));
+        }
+    }
+
+    let digits = rest.bytes().take_while(|b| b.is_ascii_digit()).count();
+    if digits > 0 && digits < 10 {
+        if let Some(text) = rest[digits..]
+            .strip_prefix(". ")
+            .or_else(|| rest[digits..].strip_prefix(") "))
+        {
+            return Some((indent, true, text));
+        }
+    }
+
+    None
+}
+
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.bytes().take_while(|&b| b == b'#').count();
+    if (1..=6).contains(&level) {
+        let rest = &line[level..];
+        if rest.is_empty() || rest.starts_with(' ') {
+            return Some((level, rest.trim().trim_end_matches('#').trim()));
+        }
+    }
+    None
+}
+
+fn render_markdown_blocks(lines: &[&str]) -> String {
+    let mut out = String::new();
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+        let trimmed = line.trim();
+
+        if trimmed.is_empty() {
+            i += 1;
+            continue;
+        }
+
+        if let Some(fence) = ["```", "~~~"].iter().find(|f| trimmed.starts_with(**f)) {
+            let mut code = Vec::new();
+            i += 1;
+            while i < lines.len() && !lines[i].trim().starts_with(fence) {
+                code.push(lines[i]);
+                i += 1;
+            }
+            i += 1;
+            out.push_str(&format!(
+                "<pre><code>{}</code></pre>",
+                html::escape(&code.join("\n"))
+            ));
+            continue;
+        }
+
+        if let Some((level, text)) = heading(trimmed) {
+            out.push_str(&format!(
+                "<h{level}>{}</h{level}>",
+                render_markdown_inline(text)
+            ));
+            i += 1;
+            continue;
+        }
+
+        if is_rule(trimmed) {
+            out.push_str("<hr>");
+            i += 1;
+            continue;
+        }
+
+        if trimmed.starts_with('>') {
+            let mut quoted = Vec::new();
+            while i < lines.len() && lines[i].trim_start().starts_with('>') {
+                let inner = lines[i].trim_start()[1..].strip_prefix(' ');
+                quoted.push(inner.unwrap_or(&lines[i].trim_start()[1..]));
+                i += 1;
+            }
+            out.push_str(&format!(
+                "<blockquote>{}</blockquote>",
+                render_markdown_blocks(&quoted)
+            ));
+            continue;
+        }
+
+        if list_item(line).is_some() {
+            let start = i;
+            while i < lines.len()
+                && !lines[i].trim().is_empty()
+                && (list_item(lines[i]).is_some() || lines[i].starts_with([' ', '\t']))
+            {
+                i += 1;
+            }
+            out.push_str(&render_markdown_list(&lines[start..i]));
+            continue;
+        }
+
+        let mut paragraph = vec![line];
+        i += 1;
+        while i < lines.len() {
+            let next = lines[i].trim();
+            let ends_paragraph = next.is_empty()
+                || heading(next).is_some()
+                || is_rule(next)
+                || next.starts_with('>')
+                || next.starts_with("```")
+                || next.starts_with("~~~")
+                || list_item(lines[i]).is_some();
+            if ends_paragraph {
+                break;
+            }
+            paragraph.push(lines[i]);
+            i += 1;
+        }
+        out.push_str(&format!("<p>{}</p>", render_paragraph(&paragraph)));
+    }
+
+    out
+}
+
+/// Joins paragraph lines, turning a trailing backslash or two trailing spaces into a line break.
+fn render_paragraph(lines: &[&str]) -> String {
+    let mut out = String::new();
+    for (index, line) in lines.iter().enumerate() {
+        let hard_break = line.ends_with('\\') || line.ends_with("  ");
+        let text = line.trim().trim_end_matches('\\');
+        out.push_str(&render_markdown_inline(text));
+        if index + 1 < lines.len() {
+            out.push_str(if hard_break { "<br>" } else { " " });
+        }
+    }
+    out
+}
+
+fn render_markdown_list(lines: &[&str]) -> String {
+    let Some((base_indent, ordered, _)) = lines.first().and_then(|line| list_item(line)) else {
+        return String::new();
+    };
+
+    let tag = if ordered { "ol" } else { "ul" };
+    let mut out = format!("<{}>", tag);
+    let mut i = 0;
+
+    while i < lines.len() {
+        let Some((_, _, text)) = list_item(lines[i]) else {
+            i += 1;
+            continue;
+        };
+        let mut item_text = vec![text];
+        i += 1;
+
+        // Continuation lines belong to the item; deeper list items form a nested list.
+        while i < lines.len() && list_item(lines[i]).is_none() {
+            item_text.push(lines[i].trim());
+            i += 1;
+        }
+        let nested_start = i;
+        while i < lines.len()
+            && list_item(lines[i]).is_none_or(|(indent, _, _)| indent > base_indent)
+        {
+            i += 1;
+        }
+
+        out.push_str(&format!("<li><p>{}</p>", render_paragraph(&item_text)));
+        if nested_start < i {
+            out.push_str(&render_markdown_list(&lines[nested_start..i]));
+        }
+        out.push_str("</li>");
+    }
+
+    out.push_str(&format!("</{}>", tag));
+    out
+}
+
+fn render_markdown_inline(text: &str) -> String {
+    const SPANS: &[(&str, &str, &str)] = &[
+        ("**", "<strong>", "</strong>"),
+        ("__", "<strong>", "</strong>"),
+        ("~~", "<s>", "</s>"),
+        ("*", "<em>", "</em>"),
+        ("_", "<em>", "</em>"),
+    ];
+
+    let mut out = String::new();
+    let mut rest = text;
+
+    'outer: while let Some(c) = rest.chars().next() {
+        if c == '\\' {
+            if let Some(escaped) = rest[1..]
+                .chars()
+                .next()
+                .filter(|c| c.is_ascii_punctuation())
+            {
+                out.push_str(&html::escape(&escaped.to_string()));
+                rest = &rest[1 + escaped.len_utf8()..];
+                continue;
+            }
+        }
+
+        if c == '`' {
+            if let Some(end) = rest[1..].find('`') {
+                out.push_str(&format!("<code>{}</code>", html::escape(&rest[1..1 + end])));
+                rest = &rest[end + 2..];
+                continue;
+            }
+        }
+
+        if let Some(inner) = rest.strip_prefix("<u>") {
+            if let Some(end) = inner.find("</u>") {
+                out.push_str(&format!("<u>{}</u>", render_markdown_inline(&inner[..end])));
+                rest = &inner[end + 4..];
+                continue;
+            }
+        }
+
+        if c == '[' || rest.starts_with("![") {
+            let image = c == '!';
+            let label_start = if image { 2 } else { 1 };
+            if let Some(label_end) = rest[label_start..].find("](") {
+                let label = &rest[label_start..label_start + label_end];
+                let target_start = label_start + label_end + 2;
+                if let Some(target_end) = closing_paren(&rest[target_start..]) {
+                    let href = rest[target_start..target_start + target_end].trim();
+                    let safe = ["http://", "https://", "mailto:"]
+                        .iter()
+                        .any(|scheme| href.to_ascii_lowercase().starts_with(scheme));
+                    if image || !safe {
+                        out.push_str(&render_markdown_inline(label));
+                    } else {
+                        out.push_str(&format!(
+                            "<a href=\"{}\">{}</a>",
+                            html::escape(href),
+                            render_markdown_inline(label)
+                        ));
+                    }
+                    rest = &rest[target_start + target_end + 1..];
+                    continue;
+                }
+            }
+        }
+
+        for (marker, open, close) in SPANS {
+            let Some(inner) = rest.strip_prefix(marker) else {
+                continue;
+            };
+            // Emphasis must hug its text: `* not emphasis *` stays literal.
+            if inner.starts_with(char::is_whitespace) {
+                continue;
+            }
+            if let Some(end) = inner.find(marker).filter(|&end| end > 0) {
+                if !inner[..end].ends_with(char::is_whitespace) {
+                    out.push_str(open);
+                    out.push_str(&render_markdown_inline(&inner[..end]));
+                    out.push_str(close);
+                    rest = &inner[end + marker.len()..];
+                    continue 'outer;
+                }
+            }
+        }
+
+        out.push_str(&html::escape(&c.to_string()));
+        rest = &rest[c.len_utf8()..];
+    }
+
+    out
+}
+
+/// Byte offset of the `)` closing a link target, allowing balanced parentheses inside it.
+fn closing_paren(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (index, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return Some(index),
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Converts plain text to paragraphs, one per blank-line separated block.
+pub fn text_to_html(source: &str) -> String {
+    source
+        .split("\n\n")
+        .map(|block| block.trim_matches(['\r', '\n']))
+        .filter(|block| !block.trim().is_empty())
+        .map(|block| {
+            let lines: Vec<String> = block
+                .lines()
+                .map(|line| html::escape(line.trim_end()))
+                .collect();
+            format!("<p>{}</p>", lines.join("<br>"))
+        })
+        .collect()
+}
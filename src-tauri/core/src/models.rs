@@ -0,0 +1,187 @@
+use crate::recurrence::RecurrenceRule;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use sqlx::FromRow;
+
+#[derive(FromRow, Serialize, Deserialize, Debug, Clone, Type)]
+pub struct Note {
+    pub id: i64,
+    pub title: String,
+    pub content: String,
+    pub tab_id: Option<i64>,
+    pub order_id: Option<i64>,
+    pub created_at: String,
+    pub updated_at: String,
+    pub deleted_at: Option<String>,
+    /// Bumped on every edit. Updates pass the version they started from and are rejected
+    /// with a conflict if the note has been changed since.
+    pub version: i64,
+}
+
+#[derive(FromRow, Serialize, Deserialize, Debug, Clone, Type)]
+pub struct Tab {
+    pub id: i64,
+    pub name: String,
+    /// The tab this one is a sub-tab of, or `None` at the top level.
+    pub parent_id: Option<i64>,
+    /// Position among the tabs with the same parent.
+    pub order_id: Option<i64>,
+    pub created_at: String,
+    pub updated_at: String,
+    pub deleted_at: Option<String>,
+}
+
+/// A tab with its sub-tabs, each level in order.
+#[derive(Serialize, Deserialize, Debug, Clone, Type)]
+pub struct TabNode {
+    #[serde(flatten)]
+    pub tab: Tab,
+    pub children: Vec<TabNode>,
+}
+
+/// What deleting a tab does with its sub-tabs.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum ChildTabs {
+    /// Into the trash along with the tab, notes and all, and back out when it is restored.
+    Trash,
+    /// Up to the tab's own parent, after its other sub-tabs. Only the tab's own notes go to
+    /// the trash.
+    MoveUp,
+}
+
+/// A label notes can carry any number of, across tabs. Names are unique, ignoring case.
+#[derive(FromRow, Serialize, Deserialize, Debug, Clone, Type)]
+pub struct Tag {
+    pub id: i64,
+    pub name: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(FromRow, Serialize, Deserialize, Debug, Clone, Type)]
+pub struct TagUsage {
+    pub id: i64,
+    pub name: String,
+    /// Notes outside the trash that carry the tag.
+    pub note_count: i64,
+}
+
+#[derive(FromRow, Serialize, Deserialize, Debug, Clone, Type)]
+pub struct NoteTag {
+    pub note_id: i64,
+    pub tag_id: i64,
+}
+
+/// Whether notes filtered by several tags need all of them or any one.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Type)]
+#[serde(rename_all = "lowercase")]
+pub enum TagMatch {
+    All,
+    Any,
+}
+
+#[derive(FromRow, Serialize, Deserialize, Debug, Clone, Type)]
+pub struct TrashedTab {
+    pub id: i64,
+    pub name: String,
+    pub deleted_at: String,
+    pub note_count: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Type)]
+pub struct Trash {
+    pub tabs: Vec<TrashedTab>,
+    pub notes: Vec<Note>,
+}
+
+#[derive(FromRow, Serialize, Deserialize, Debug, Clone, Type)]
+pub struct NoteRevision {
+    pub id: i64,
+    pub note_id: i64,
+    pub title: String,
+    pub content: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(FromRow, Serialize, Deserialize, Debug, Clone, Type)]
+pub struct NoteRevisionSummary {
+    pub id: i64,
+    pub note_id: i64,
+    pub title: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(FromRow, Serialize, Deserialize, Debug, Clone, Type)]
+pub struct Timer {
+    pub id: i64,
+    pub initial_duration: i32,
+    pub duration: i32,
+    pub message: String,
+}
+
+#[derive(FromRow, Serialize, Deserialize, Debug, Clone, Type)]
+pub struct CalendarEvent {
+    pub id: i64,
+    pub event_date: String,
+    pub year_month: String,
+    pub event_name: String,
+    pub event_start: Option<i32>,
+    pub event_end: Option<i32>,
+    pub color: String,
+    pub recurrence: Option<RecurrenceRule>,
+    /// Bumped on every edit, including edits to a single occurrence. See `Note::version`.
+    pub version: i64,
+    /// Set on the occurrences of a recurring event expanded by `EventService::range`: the
+    /// date that identifies the occurrence when editing or deleting just that one.
+    #[sqlx(default)]
+    pub occurrence_date: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Type)]
+pub struct IcsExportSummary {
+    pub path: String,
+    pub event_count: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Type)]
+pub struct IcsImportSummary {
+    pub path: String,
+    pub imported: usize,
+    pub duplicates: usize,
+    pub failed: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Type)]
+pub struct MarkdownExportSummary {
+    pub path: String,
+    pub tab_count: usize,
+    pub note_count: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Type)]
+pub struct ImportedFile {
+    pub path: String,
+    pub note_id: Option<i64>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Type)]
+pub struct FolderImportSummary {
+    pub path: String,
+    pub tabs_created: usize,
+    pub notes_imported: usize,
+    pub files: Vec<ImportedFile>,
+}
+
+#[derive(FromRow, Serialize, Deserialize, Debug, Clone, Type)]
+pub struct SearchHit {
+    pub id: i64,
+    pub tab_id: Option<i64>,
+    pub title: String,
+    pub title_highlight: String,
+    pub snippet: String,
+    pub rank: f64,
+}
@@ -0,0 +1,724 @@
+use crate::diff::{self, DiffLine};
+use crate::error::AppError;
+use crate::html;
+use crate::models::{Note, NoteRevision, NoteRevisionSummary, SearchHit};
+use log::error;
+use sqlx::{query_as, SqliteConnection, SqlitePool};
+
+/// Notes, their revision history and full-text search.
+#[derive(Clone)]
+pub struct NoteService {
+    pool: SqlitePool,
+}
+
+impl NoteService {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn list(&self) -> Result<Vec<Note>, AppError> {
+        let notes = query_as::<_, Note>(
+            r#"
+            SELECT * FROM notes
+            WHERE deleted_at IS NULL
+            ORDER BY order_id ASC
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| {
+            error!("Failed to fetch notes: {:#}", e);
+            AppError::database("Failed to load notes. Please try again.")
+        })?;
+
+        Ok(notes)
+    }
+
+    /// A note that is not in the trash.
+    pub async fn get(&self, id: i64) -> Result<Note, AppError> {
+        query_as::<_, Note>("SELECT * FROM notes WHERE id = ? AND deleted_at IS NULL")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| {
+                error!("Failed to fetch note {}: {:#}", id, e);
+                AppError::database("Failed to load note. Please try again")
+            })?
+            .ok_or_else(|| AppError::not_found(format!("Note with id {id} not found")))
+    }
+
+    pub async fn create(
+        &self,
+        title: String,
+        content: String,
+        tab_id: Option<i64>,
+    ) -> Result<Note, AppError> {
+        let mut conn = self.pool.acquire().await.map_err(|e| {
+            error!("Database error: {:#}", e);
+            AppError::database("Database error")
+        })?;
+
+        let new_order = next_note_order(&mut conn, tab_id).await.map_err(|e| {
+            error!("Database error: {:#}", e);
+            AppError::database("Database error")
+        })?;
+
+        let note = query_as::<_, Note>(
+            r#"
+            INSERT INTO notes (title, content, tab_id, order_id, title_text, content_text)
+            VALUES (?, ?, ?, ?, ?, ?)
+            RETURNING id, title, content, tab_id, order_id, created_at, updated_at, deleted_at, version
+            "#,
+        )
+        .bind(&title)
+        .bind(&content)
+        .bind(tab_id)
+        .bind(new_order)
+        .bind(html::to_plain_text(&title))
+        .bind(html::to_plain_text(&content))
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(|e| {
+            error!("Failed to create note {}: {:#}", title, e);
+            AppError::database("Failed to create note. Please try again")
+        })?;
+
+        Ok(note)
+    }
+
+    /// Saves the note if it is still at `version`. Otherwise nothing is written and a conflict
+    /// is returned with the note as it is now in its details, so the caller can merge or
+    /// overwrite by saving again with the newer version. Notes in the trash are not found.
+    pub async fn update(
+        &self,
+        id: i64,
+        version: i64,
+        title: String,
+        content: String,
+    ) -> Result<Note, AppError> {
+        let mut transaction = self.pool.begin().await.map_err(|e| {
+            error!("Failed to start transaction: {:#}", e);
+            AppError::database("Failed to start transaction")
+        })?;
+
+        current_note(
+            &mut transaction,
+            id,
+            version,
+            "Failed to update note. Please try again",
+        )
+        .await?;
+
+        save_note_with_revision(&mut transaction, id, &title, &content, true)
+            .await
+            .map_err(|e| {
+                error!("Failed to update note {}: {:#}", id, e);
+                AppError::database("Failed to update note. Please try again")
+            })?;
+
+        let note = query_as::<_, Note>("SELECT * FROM notes WHERE id = ? AND deleted_at IS NULL")
+            .bind(id)
+            .fetch_one(&mut *transaction)
+            .await
+            .map_err(|e| {
+                error!("Failed to fetch note {}: {:#}", id, e);
+                AppError::database("Failed to update note. Please try again")
+            })?;
+
+        transaction.commit().await.map_err(|e| {
+            error!("Failed to commit transaction: {:#}", e);
+            AppError::database("Failed to commit transaction")
+        })?;
+
+        Ok(note)
+    }
+
+    pub async fn revisions(&self, note_id: i64) -> Result<Vec<NoteRevisionSummary>, AppError> {
+        let revisions = query_as::<_, NoteRevisionSummary>(
+            r#"
+            SELECT id, note_id, title, created_at, updated_at FROM note_revisions
+            WHERE note_id = ?
+            ORDER BY id DESC
+            "#,
+        )
+        .bind(note_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| {
+            error!("Failed to fetch revisions of note {}: {:#}", note_id, e);
+            AppError::database("Failed to load note history. Please try again")
+        })?;
+
+        Ok(revisions)
+    }
+
+    pub async fn revision(&self, id: i64) -> Result<NoteRevision, AppError> {
+        query_as::<_, NoteRevision>(
+            r#"
+            SELECT id, note_id, title, COALESCE(content, '') AS content, created_at, updated_at
+            FROM note_revisions
+            WHERE id = ?
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| {
+            error!("Failed to fetch note revision {}: {:#}", id, e);
+            AppError::database("Failed to load note revision. Please try again")
+        })?
+        .ok_or_else(|| AppError::not_found(format!("Note revision with id {id} not found")))
+    }
+
+    /// Diffs the plain text of two revisions. Without `to_id` the revision is compared against the
+    /// note as it is now.
+    pub async fn diff_revisions(
+        &self,
+        from_id: i64,
+        to_id: Option<i64>,
+    ) -> Result<Vec<DiffLine>, AppError> {
+        let from = self.revision(from_id).await?;
+
+        let (to_title, to_content) = match to_id {
+            Some(to_id) => {
+                let to = self.revision(to_id).await?;
+                if to.note_id != from.note_id {
+                    return Err(AppError::validation("Revisions belong to different notes"));
+                }
+                (to.title, to.content)
+            }
+            None => {
+                let note: (String, Option<String>) = sqlx::query_as(
+                    "SELECT title, content FROM notes WHERE id = ? AND deleted_at IS NULL",
+                )
+                .bind(from.note_id)
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(|e| {
+                    error!("Failed to fetch note {}: {:#}", from.note_id, e);
+                    AppError::database("Failed to load note. Please try again")
+                })?
+                .ok_or_else(|| {
+                    AppError::not_found(format!("Note with id {} not found", from.note_id))
+                })?;
+                (note.0, note.1.unwrap_or_default())
+            }
+        };
+
+        Ok(diff::diff_lines(
+            &revision_text(&from.title, &from.content),
+            &revision_text(&to_title, &to_content),
+        ))
+    }
+
+    /// Restores a revision into its note if the note is still at `version`, with the same
+    /// conflict as [`NoteService::update`] otherwise. Notes in the trash are not found.
+    pub async fn restore_revision(&self, id: i64, version: i64) -> Result<Note, AppError> {
+        let revision = self.revision(id).await?;
+
+        let mut transaction = self.pool.begin().await.map_err(|e| {
+            error!("Failed to start transaction: {:#}", e);
+            AppError::database("Failed to start transaction")
+        })?;
+
+        current_note(
+            &mut transaction,
+            revision.note_id,
+            version,
+            "Failed to restore note revision. Please try again",
+        )
+        .await?;
+
+        // Never coalesce here, so the restore itself can be undone from the history.
+        save_note_with_revision(
+            &mut transaction,
+            revision.note_id,
+            &revision.title,
+            &revision.content,
+            false,
+        )
+        .await
+        .map_err(|e| {
+            error!("Failed to restore note revision {}: {:#}", id, e);
+            AppError::database("Failed to restore note revision. Please try again")
+        })?;
+
+        let note = query_as::<_, Note>("SELECT * FROM notes WHERE id = ? AND deleted_at IS NULL")
+            .bind(revision.note_id)
+            .fetch_one(&mut *transaction)
+            .await
+            .map_err(|e| {
+                error!("Failed to fetch note {}: {:#}", revision.note_id, e);
+                AppError::database("Failed to restore note revision. Please try again")
+            })?;
+
+        transaction.commit().await.map_err(|e| {
+            error!("Failed to commit transaction: {:#}", e);
+            AppError::database("Failed to commit transaction")
+        })?;
+
+        Ok(note)
+    }
+
+    pub async fn delete(&self, id: i64) -> Result<(), AppError> {
+        let result = sqlx::query(
+            "UPDATE notes SET deleted_at = datetime('now') WHERE id = ? AND deleted_at IS NULL",
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| {
+            error!("Failed to delete note {}: {:#}", id, e);
+            AppError::database("Failed to delete note. Please try again")
+        })?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::not_found(format!("Note with id {id} not found")));
+        }
+
+        Ok(())
+    }
+
+    /// Up to `limit` notes matching `query`, best first. `limit` defaults to
+    /// [`DEFAULT_SEARCH_LIMIT`] and can't exceed [`MAX_SEARCH_LIMIT`].
+    pub async fn search(
+        &self,
+        query: String,
+        tab_id: Option<i64>,
+        limit: Option<i64>,
+    ) -> Result<Vec<SearchHit>, AppError> {
+        let limit = limit.unwrap_or(DEFAULT_SEARCH_LIMIT);
+        if !(1..=MAX_SEARCH_LIMIT).contains(&limit) {
+            return Err(AppError::validation(format!(
+                "Search results are limited to between 1 and {MAX_SEARCH_LIMIT}"
+            )));
+        }
+
+        let Some(match_query) = build_match_query(&query) else {
+            return Ok(Vec::new());
+        };
+
+        let hits = query_as::<_, SearchHit>(
+            r#"
+            SELECT notes.id,
+                notes.tab_id,
+                notes.title,
+                highlight(notes_fts, 0, ?1, ?2) AS title_highlight,
+                snippet(notes_fts, 1, ?1, ?2, '…', 16) AS snippet,
+                bm25(notes_fts, 10.0, 1.0) AS rank
+            FROM notes_fts
+            JOIN notes ON notes.id = notes_fts.rowid
+            WHERE notes_fts MATCH ?3
+                AND notes.deleted_at IS NULL
+                AND (?4 IS NULL OR notes.tab_id = ?4)
+            ORDER BY rank
+            LIMIT ?5
+            "#,
+        )
+        .bind(MATCH_START)
+        .bind(MATCH_END)
+        .bind(&match_query)
+        .bind(tab_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| {
+            error!("Failed to search notes for {:?}: {:#}", match_query, e);
+            AppError::database("Failed to search notes. Please try again")
+        })?;
+
+        Ok(hits
+            .into_iter()
+            .map(|hit| SearchHit {
+                title_highlight: mark_matches(&hit.title_highlight),
+                snippet: mark_matches(&hit.snippet),
+                ..hit
+            })
+            .collect())
+    }
+
+    pub async fn reorder(&self, tab_id: Option<i64>, note_ids: Vec<i64>) -> Result<(), AppError> {
+        let len = note_ids.len() as i64;
+        if len == 0 {
+            return Ok(());
+        }
+
+        let mut transaction = self.pool.begin().await.map_err(|e| {
+            error!("Failed to start transaction: {:#}", e);
+            AppError::database("Failed to start transaction")
+        })?;
+
+        for (index, &id) in note_ids.iter().enumerate() {
+            let order_id = (index + 1) as i64;
+
+            sqlx::query(
+                "UPDATE notes SET order_id = ? WHERE id = ? AND tab_id IS NOT DISTINCT FROM ?",
+            )
+            .bind(order_id)
+            .bind(id)
+            .bind(tab_id)
+            .execute(&mut *transaction)
+            .await
+            .map_err(|e| {
+                error!("Failed to reorder note {}: {:#}", id, e);
+                AppError::database("Failed to reorder notes")
+            })?;
+        }
+
+        transaction.commit().await.map_err(|e| {
+            error!("Failed to commit transaction: {:#}", e);
+            AppError::database("Failed to commit transaction")
+        })?;
+
+        Ok(())
+    }
+
+    /// Moves `note_ids` into `tab_id`, in the given order, starting at `index` among the notes
+    /// already there (past the end appends them). The tabs they came from are renumbered so
+    /// their order has no gaps. Returns every note whose tab or position changed, in order.
+    pub async fn move_notes(
+        &self,
+        note_ids: Vec<i64>,
+        tab_id: Option<i64>,
+        index: usize,
+    ) -> Result<Vec<Note>, AppError> {
+        let mut moved: Vec<i64> = Vec::with_capacity(note_ids.len());
+        for id in note_ids {
+            if !moved.contains(&id) {
+                moved.push(id);
+            }
+        }
+        if moved.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut transaction = self.pool.begin().await.map_err(|e| {
+            error!("Failed to start transaction: {:#}", e);
+            AppError::database("Failed to start transaction")
+        })?;
+
+        let move_error = |e: sqlx::Error| {
+            error!(
+                "Failed to move notes {:?} to tab {:?}: {:#}",
+                moved, tab_id, e
+            );
+            AppError::database("Failed to move notes. Please try again")
+        };
+
+        if let Some(tab_id) = tab_id {
+            let tab: Option<(i64,)> =
+                sqlx::query_as("SELECT id FROM tabs WHERE id = ? AND deleted_at IS NULL")
+                    .bind(tab_id)
+                    .fetch_optional(&mut *transaction)
+                    .await
+                    .map_err(move_error)?;
+            if tab.is_none() {
+                return Err(AppError::not_found(format!(
+                    "Tab with id {tab_id} not found"
+                )));
+            }
+        }
+
+        let mut source_tabs: Vec<Option<i64>> = Vec::new();
+        for &id in &moved {
+            let note: Option<(Option<i64>,)> =
+                sqlx::query_as("SELECT tab_id FROM notes WHERE id = ? AND deleted_at IS NULL")
+                    .bind(id)
+                    .fetch_optional(&mut *transaction)
+                    .await
+                    .map_err(move_error)?;
+            let Some((source,)) = note else {
+                return Err(AppError::not_found(format!("Note with id {id} not found")));
+            };
+            if source != tab_id && !source_tabs.contains(&source) {
+                source_tabs.push(source);
+            }
+        }
+
+        let mut order = live_note_ids(&mut transaction, tab_id)
+            .await
+            .map_err(move_error)?;
+        order.retain(|id| !moved.contains(id));
+        let index = index.min(order.len());
+        order.splice(index..index, moved.iter().copied());
+
+        // Notes that change tabs count as edited, so a window holding one at its old version
+        // sees the conflict.
+        for (position, &id) in order.iter().enumerate() {
+            sqlx::query(
+                r#"
+                UPDATE notes
+                SET version = CASE WHEN tab_id IS NOT DISTINCT FROM ?1
+                        THEN version ELSE version + 1 END,
+                    updated_at = CASE WHEN tab_id IS NOT DISTINCT FROM ?1
+                        THEN updated_at ELSE datetime('now') END,
+                    tab_id = ?1,
+                    order_id = ?2
+                WHERE id = ?3
+                "#,
+            )
+            .bind(tab_id)
+            .bind((position + 1) as i64)
+            .bind(id)
+            .execute(&mut *transaction)
+            .await
+            .map_err(move_error)?;
+        }
+
+        let mut changed = order;
+        for source in source_tabs {
+            let remaining = live_note_ids(&mut transaction, source)
+                .await
+                .map_err(move_error)?;
+            for (position, &id) in remaining.iter().enumerate() {
+                sqlx::query("UPDATE notes SET order_id = ? WHERE id = ?")
+                    .bind((position + 1) as i64)
+                    .bind(id)
+                    .execute(&mut *transaction)
+                    .await
+                    .map_err(move_error)?;
+            }
+            changed.extend(remaining);
+        }
+
+        let mut notes = Vec::with_capacity(changed.len());
+        for id in changed {
+            let note = query_as::<_, Note>("SELECT * FROM notes WHERE id = ?")
+                .bind(id)
+                .fetch_one(&mut *transaction)
+                .await
+                .map_err(move_error)?;
+            notes.push(note);
+        }
+
+        transaction.commit().await.map_err(|e| {
+            error!("Failed to commit transaction: {:#}", e);
+            AppError::database("Failed to commit transaction")
+        })?;
+
+        Ok(notes)
+    }
+}
+
+/// The note `id` outside the trash, if it is still at `version`. Otherwise a conflict is
+/// returned with the note as it is now in its details.
+async fn current_note(
+    conn: &mut SqliteConnection,
+    id: i64,
+    version: i64,
+    failure: &str,
+) -> Result<Note, AppError> {
+    let current = query_as::<_, Note>("SELECT * FROM notes WHERE id = ? AND deleted_at IS NULL")
+        .bind(id)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| {
+            error!("Failed to fetch note {}: {:#}", id, e);
+            AppError::database(failure)
+        })?
+        .ok_or_else(|| AppError::not_found(format!("Note with id {id} not found")))?;
+
+    if current.version != version {
+        return Err(
+            AppError::conflict("This note was changed somewhere else since you opened it")
+                .with_details(&current),
+        );
+    }
+
+    Ok(current)
+}
+
+/// The notes of `tab_id` outside the trash, in order.
+async fn live_note_ids(
+    conn: &mut SqliteConnection,
+    tab_id: Option<i64>,
+) -> Result<Vec<i64>, sqlx::Error> {
+    let rows: Vec<(i64,)> = sqlx::query_as(
+        r#"
+        SELECT id FROM notes
+        WHERE tab_id IS NOT DISTINCT FROM ? AND deleted_at IS NULL
+        ORDER BY order_id ASC, id ASC
+        "#,
+    )
+    .bind(tab_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(rows.into_iter().map(|(id,)| id).collect())
+}
+
+/// The order_id that appends a note to the end of `tab_id`.
+pub(crate) async fn next_note_order(
+    conn: &mut SqliteConnection,
+    tab_id: Option<i64>,
+) -> Result<i64, sqlx::Error> {
+    let row: Option<(Option<i64>,)> = sqlx::query_as(
+        r#"
+        SELECT MAX(order_id) FROM notes WHERE tab_id IS NOT DISTINCT FROM ?
+        "#,
+    )
+    .bind(tab_id)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    Ok(row.and_then(|r| r.0).unwrap_or(0) + 1)
+}
+
+/// Saves within this many seconds of the newest revision being started are folded into it,
+/// so the click-outside autosave doesn't produce a revision per click.
+const REVISION_COALESCE_SECONDS: i64 = 60;
+
+/// Writes `title` and `content` to the note and records them in its revision history.
+/// The first time a note is saved its previous text is kept as the oldest revision.
+async fn save_note_with_revision(
+    conn: &mut SqliteConnection,
+    id: i64,
+    title: &str,
+    content: &str,
+    coalesce: bool,
+) -> Result<(), sqlx::Error> {
+    let current: Option<(String, Option<String>, String)> =
+        sqlx::query_as("SELECT title, content, updated_at FROM notes WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&mut *conn)
+            .await?;
+
+    let Some((old_title, old_content, old_updated_at)) = current else {
+        return Ok(());
+    };
+    let old_content = old_content.unwrap_or_default();
+
+    if old_title == title && old_content == content {
+        return Ok(());
+    }
+
+    let latest: Option<(i64, bool)> = sqlx::query_as(
+        r#"
+        SELECT id, created_at >= datetime('now', ?) FROM note_revisions
+        WHERE note_id = ?
+        ORDER BY id DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(format!("-{} seconds", REVISION_COALESCE_SECONDS))
+    .bind(id)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    if latest.is_none() {
+        sqlx::query(
+            r#"
+            INSERT INTO note_revisions (note_id, title, content, created_at, updated_at)
+            VALUES (?1, ?2, ?3, ?4, ?4)
+            "#,
+        )
+        .bind(id)
+        .bind(&old_title)
+        .bind(&old_content)
+        .bind(&old_updated_at)
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    sqlx::query(
+        r#"
+        UPDATE notes
+        SET title = ?,
+            content = ?,
+            title_text = ?,
+            content_text = ?,
+            updated_at = datetime('now'),
+            version = version + 1
+        WHERE id = ?
+        "#,
+    )
+    .bind(title)
+    .bind(content)
+    .bind(html::to_plain_text(title))
+    .bind(html::to_plain_text(content))
+    .bind(id)
+    .execute(&mut *conn)
+    .await?;
+
+    match latest {
+        Some((revision_id, true)) if coalesce => {
+            sqlx::query(
+                r#"
+                UPDATE note_revisions
+                SET title = ?, content = ?, updated_at = datetime('now')
+                WHERE id = ?
+                "#,
+            )
+            .bind(title)
+            .bind(content)
+            .bind(revision_id)
+            .execute(&mut *conn)
+            .await?;
+        }
+        _ => {
+            sqlx::query("INSERT INTO note_revisions (note_id, title, content) VALUES (?, ?, ?)")
+                .bind(id)
+                .bind(title)
+                .bind(content)
+                .execute(&mut *conn)
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+fn revision_text(title: &str, content: &str) -> String {
+    format!(
+        "{}\n{}",
+        html::to_plain_text(title),
+        html::to_plain_text(content)
+    )
+}
+
+/// Turns what the user typed into an FTS5 query: words are ANDed, `"quoted text"` is a
+/// phrase and a trailing `*` makes a word a prefix match. Everything else is taken literally.
+fn build_match_query(input: &str) -> Option<String> {
+    let mut terms = Vec::new();
+
+    for (i, part) in input.split('"').enumerate() {
+        if i % 2 == 1 {
+            let phrase = part.trim();
+            if !phrase.is_empty() {
+                terms.push(format!("\"{}\"", phrase));
+            }
+            continue;
+        }
+
+        for word in part.split_whitespace() {
+            let (word, prefix) = match word.strip_suffix('*') {
+                Some(stem) => (stem.trim_end_matches('*'), true),
+                None => (word, false),
+            };
+            if word.is_empty() {
+                continue;
+            }
+            terms.push(format!("\"{}\"{}", word, if prefix { "*" } else { "" }));
+        }
+    }
+
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" "))
+    }
+}
+
+pub const DEFAULT_SEARCH_LIMIT: i64 = 50;
+/// SQLite treats a negative `LIMIT` as none, so the limit is always checked against this.
+pub const MAX_SEARCH_LIMIT: i64 = 200;
+
+/// FTS5 marks matches with these, then the text is escaped and they become `<mark>` tags.
+const MATCH_START: &str = "\u{2}";
+const MATCH_END: &str = "\u{3}";
+
+fn mark_matches(text: &str) -> String {
+    html::escape(text)
+        .replace(MATCH_START, "<mark>")
+        .replace(MATCH_END, "</mark>")
+}
@@ -0,0 +1,336 @@
+use log::warn;
+use serde::{Deserialize, Serialize};
+use sqlx::error::BoxDynError;
+use sqlx::sqlite::{SqliteTypeInfo, SqliteValueRef};
+use sqlx::{Decode, Sqlite, Type};
+use std::fmt;
+use std::str::FromStr;
+use time::{macros::format_description, Date, Duration, Month};
+
+/// Upper bound on periods walked for a COUNT rule, which has to be counted from its first
+/// occurrence. A rule whose weekdays never match would otherwise be walked to the end of time.
+const MAX_PERIODS: i64 = 20_000;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, specta::Type)]
+#[serde(rename_all = "lowercase")]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, specta::Type)]
+#[serde(rename_all = "lowercase")]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl Weekday {
+    const CODES: [(&'static str, Weekday); 7] = [
+        ("MO", Weekday::Monday),
+        ("TU", Weekday::Tuesday),
+        ("WE", Weekday::Wednesday),
+        ("TH", Weekday::Thursday),
+        ("FR", Weekday::Friday),
+        ("SA", Weekday::Saturday),
+        ("SU", Weekday::Sunday),
+    ];
+
+    fn code(self) -> &'static str {
+        Self::CODES[self.days_from_monday() as usize].0
+    }
+
+    fn days_from_monday(self) -> i64 {
+        self as i64
+    }
+
+    fn of(date: Date) -> Weekday {
+        Self::CODES[date.weekday().number_days_from_monday() as usize].1
+    }
+}
+
+/// A subset of RFC 5545 RRULE: FREQ, INTERVAL, BYDAY (daily and weekly rules only),
+/// COUNT and UNTIL. Stored in `events.recurrence` in its RRULE text form.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, specta::Type)]
+pub struct RecurrenceRule {
+    pub frequency: Frequency,
+    #[serde(default = "default_interval")]
+    pub interval: u32,
+    #[serde(default)]
+    pub by_weekday: Vec<Weekday>,
+    pub count: Option<u32>,
+    /// Last possible occurrence date, inclusive, as YYYY-MM-DD.
+    pub until: Option<String>,
+}
+
+fn default_interval() -> u32 {
+    1
+}
+
+fn parse_date(date: &str) -> Result<Date, String> {
+    Date::parse(date, &format_description!("[year]-[month]-[day]"))
+        .map_err(|_| format!("Invalid date {:?}, expected YYYY-MM-DD", date))
+}
+
+impl RecurrenceRule {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.interval == 0 {
+            return Err("A recurrence interval must be at least 1".to_string());
+        }
+        if self.count == Some(0) {
+            return Err("A recurrence count must be at least 1".to_string());
+        }
+        if !self.by_weekday.is_empty()
+            && matches!(self.frequency, Frequency::Monthly | Frequency::Yearly)
+        {
+            return Err("Weekdays can only be chosen for daily or weekly recurrence".to_string());
+        }
+        if let Some(until) = &self.until {
+            parse_date(until)?;
+        }
+        Ok(())
+    }
+
+    /// Occurrence dates of an event starting on `start` that fall within `from..=to`.
+    pub fn occurrences(&self, start: Date, from: Date, to: Date) -> Vec<Date> {
+        let until = self
+            .until
+            .as_deref()
+            .and_then(|until| parse_date(until).ok())
+            .map_or(to, |until| until.min(to));
+
+        let mut weekdays = self.by_weekday.clone();
+        weekdays.sort();
+        weekdays.dedup();
+
+        let interval = i64::from(self.interval.max(1));
+        let week_start = start - Duration::days(Weekday::of(start).days_from_monday());
+
+        // Without a COUNT, earlier periods can't affect the range, so skip straight to it.
+        let (first_period, end_period) = match self.count {
+            Some(_) => (0, MAX_PERIODS),
+            None => (
+                periods_between(self.frequency, start, week_start, from) / interval,
+                i64::MAX,
+            ),
+        };
+
+        let mut dates = Vec::new();
+        let mut seen: u32 = 0;
+
+        for period in first_period..end_period {
+            let candidates: Vec<Date> = match self.frequency {
+                Frequency::Daily => start
+                    .checked_add(Duration::days(period * interval))
+                    .filter(|date| weekdays.is_empty() || weekdays.contains(&Weekday::of(*date)))
+                    .into_iter()
+                    .collect(),
+                Frequency::Weekly => {
+                    let Some(monday) = week_start.checked_add(Duration::weeks(period * interval))
+                    else {
+                        return dates;
+                    };
+                    if weekdays.is_empty() {
+                        vec![monday + Duration::days(Weekday::of(start).days_from_monday())]
+                    } else {
+                        weekdays
+                            .iter()
+                            .map(|day| monday + Duration::days(day.days_from_monday()))
+                            .collect()
+                    }
+                }
+                Frequency::Monthly => add_months(start, period * interval).into_iter().collect(),
+                Frequency::Yearly => add_months(start, period * interval * 12)
+                    .into_iter()
+                    .collect(),
+            };
+
+            if period_start(self.frequency, start, week_start, period * interval) > until {
+                return dates;
+            }
+
+            for date in candidates {
+                if date < start {
+                    continue;
+                }
+                if date > until {
+                    return dates;
+                }
+                seen += 1;
+                if self.count.is_some_and(|count| seen > count) {
+                    return dates;
+                }
+                if date >= from {
+                    dates.push(date);
+                }
+            }
+        }
+
+        warn!("Stopped expanding recurrence {self} from {start} after {MAX_PERIODS} periods");
+        dates
+    }
+}
+
+/// Whole periods from the one containing `start` to the one containing `date`, or 0 if
+/// `date` comes first.
+fn periods_between(frequency: Frequency, start: Date, week_start: Date, date: Date) -> i64 {
+    let periods = match frequency {
+        Frequency::Daily => (date - start).whole_days(),
+        Frequency::Weekly => (date - week_start).whole_days().div_euclid(7),
+        Frequency::Monthly => month_index(date) - month_index(start),
+        Frequency::Yearly => i64::from(date.year()) - i64::from(start.year()),
+    };
+    periods.max(0)
+}
+
+fn month_index(date: Date) -> i64 {
+    i64::from(date.year()) * 12 + i64::from(date.month() as u8 - 1)
+}
+
+/// The first day a period could produce an occurrence on, used to stop walking.
+fn period_start(frequency: Frequency, start: Date, week_start: Date, offset: i64) -> Date {
+    match frequency {
+        Frequency::Daily => start.checked_add(Duration::days(offset)),
+        Frequency::Weekly => week_start.checked_add(Duration::weeks(offset)),
+        Frequency::Monthly => first_of_month(start, offset),
+        Frequency::Yearly => first_of_month(start, offset * 12),
+    }
+    .unwrap_or(Date::MAX)
+}
+
+fn first_of_month(date: Date, months: i64) -> Option<Date> {
+    let total = month_index(date) + months;
+    let year = i32::try_from(total.div_euclid(12)).ok()?;
+    let month = Month::try_from((total.rem_euclid(12) + 1) as u8).ok()?;
+    Date::from_calendar_date(year, month, 1).ok()
+}
+
+/// Same day of month `months` later. Months without that day (e.g. the 31st) are skipped,
+/// as RFC 5545 does.
+fn add_months(date: Date, months: i64) -> Option<Date> {
+    let first = first_of_month(date, months)?;
+    Date::from_calendar_date(first.year(), first.month(), date.day()).ok()
+}
+
+impl fmt::Display for RecurrenceRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let frequency = match self.frequency {
+            Frequency::Daily => "DAILY",
+            Frequency::Weekly => "WEEKLY",
+            Frequency::Monthly => "MONTHLY",
+            Frequency::Yearly => "YEARLY",
+        };
+        write!(f, "FREQ={}", frequency)?;
+
+        if self.interval > 1 {
+            write!(f, ";INTERVAL={}", self.interval)?;
+        }
+        if !self.by_weekday.is_empty() {
+            let days: Vec<&str> = self.by_weekday.iter().map(|day| day.code()).collect();
+            write!(f, ";BYDAY={}", days.join(","))?;
+        }
+        if let Some(count) = self.count {
+            write!(f, ";COUNT={}", count)?;
+        }
+        if let Some(until) = &self.until {
+            write!(f, ";UNTIL={}", until.replace('-', ""))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for RecurrenceRule {
+    type Err = String;
+
+    fn from_str(rule: &str) -> Result<Self, Self::Err> {
+        let rule = rule.trim().trim_start_matches("RRULE:");
+
+        let mut frequency = None;
+        let mut parsed = RecurrenceRule {
+            frequency: Frequency::Daily,
+            interval: 1,
+            by_weekday: Vec::new(),
+            count: None,
+            until: None,
+        };
+
+        for part in rule.split(';').filter(|part| !part.is_empty()) {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| format!("Invalid recurrence rule part {:?}", part))?;
+
+            match key.to_ascii_uppercase().as_str() {
+                "FREQ" => {
+                    frequency = Some(match value.to_ascii_uppercase().as_str() {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        _ => return Err(format!("Unsupported recurrence frequency {:?}", value)),
+                    })
+                }
+                "INTERVAL" => {
+                    parsed.interval = value
+                        .parse()
+                        .map_err(|_| format!("Invalid recurrence interval {:?}", value))?
+                }
+                "COUNT" => {
+                    parsed.count = Some(
+                        value
+                            .parse()
+                            .map_err(|_| format!("Invalid recurrence count {:?}", value))?,
+                    )
+                }
+                "UNTIL" => {
+                    // Accepts both DATE (20261231) and DATE-TIME (20261231T235959Z) forms.
+                    let date = value
+                        .get(..8)
+                        .filter(|date| date.bytes().all(|b| b.is_ascii_digit()));
+                    let date = date.ok_or_else(|| format!("Invalid recurrence end {:?}", value))?;
+                    parsed.until = Some(format!("{}-{}-{}", &date[..4], &date[4..6], &date[6..]));
+                }
+                "BYDAY" => {
+                    for code in value.split(',') {
+                        let day = Weekday::CODES
+                            .iter()
+                            .find(|(c, _)| c.eq_ignore_ascii_case(code.trim()))
+                            .map(|(_, day)| *day)
+                            .ok_or_else(|| format!("Unsupported recurrence weekday {:?}", code))?;
+                        parsed.by_weekday.push(day);
+                    }
+                }
+                "WKST" => {}
+                _ => return Err(format!("Unsupported recurrence rule part {:?}", key)),
+            }
+        }
+
+        parsed.frequency = frequency.ok_or("A recurrence rule needs a FREQ")?;
+        parsed.validate()?;
+
+        Ok(parsed)
+    }
+}
+
+impl Type<Sqlite> for RecurrenceRule {
+    fn type_info() -> SqliteTypeInfo {
+        <&str as Type<Sqlite>>::type_info()
+    }
+
+    fn compatible(ty: &SqliteTypeInfo) -> bool {
+        <&str as Type<Sqlite>>::compatible(ty)
+    }
+}
+
+impl<'r> Decode<'r, Sqlite> for RecurrenceRule {
+    fn decode(value: SqliteValueRef<'r>) -> Result<Self, BoxDynError> {
+        let rule = <&str as Decode<Sqlite>>::decode(value)?;
+        Ok(rule.parse()?)
+    }
+}
@@ -0,0 +1,68 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Where FocusBoard keeps its files. Everything lives under one root folder, by default
+/// the platform's local data directory for the app:
+///
+/// ```text
+/// <root>/database/data.db
+/// <root>/database_backups/
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Storage {
+    root: PathBuf,
+}
+
+impl Storage {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn database_dir(&self) -> PathBuf {
+        self.root.join("database")
+    }
+
+    pub fn database_path(&self) -> PathBuf {
+        self.database_dir().join("data.db")
+    }
+
+    pub fn database_url(&self) -> String {
+        let path = self.database_path().to_string_lossy().replace("\\", "/");
+        format!("sqlite://{}?mode=rwc", path)
+    }
+
+    pub fn backup_dir(&self) -> PathBuf {
+        self.root.join("database_backups")
+    }
+}
+
+/// Copies the files and folders in `from` into `to`, skipping any that already exist there.
+/// Returns how many entries were copied.
+pub fn copy_dir_contents(from: &Path, to: &Path) -> io::Result<usize> {
+    if !from.is_dir() {
+        return Ok(0);
+    }
+    fs::create_dir_all(to)?;
+
+    let mut copied = 0;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if target.exists() {
+            continue;
+        }
+        if entry.file_type()?.is_dir() {
+            copy_dir_contents(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), &target)?;
+        }
+        copied += 1;
+    }
+
+    Ok(copied)
+}
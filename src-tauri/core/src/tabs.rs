@@ -0,0 +1,455 @@
+use crate::error::AppError;
+use crate::models::{ChildTabs, Tab, TabNode};
+use log::error;
+use sqlx::{query_as, SqliteConnection, SqlitePool};
+
+/// Selects, as `subtree`, tab `?1` and the sub-tabs that went to the trash along with it.
+pub(crate) const TRASHED_SUBTREE: &str = r#"
+    WITH RECURSIVE subtree(id) AS (
+        SELECT ?1
+        UNION
+        SELECT tabs.id FROM tabs
+        JOIN subtree ON tabs.parent_id = subtree.id
+        WHERE tabs.trashed_with_parent = 1
+    )
+"#;
+
+/// Selects, as `tab_paths(id, path)`, every tab outside the trash with a path that sorts it
+/// the way [`TabService::tree`] lays it out: after its parent, among its siblings by `order_id`.
+pub(crate) const TAB_PATHS: &str = r#"
+    WITH RECURSIVE tab_paths(id, path) AS (
+        SELECT id, printf('%010d.%010d', COALESCE(order_id, 0), id) FROM tabs
+        WHERE parent_id IS NULL AND deleted_at IS NULL
+        UNION ALL
+        SELECT tabs.id,
+            tab_paths.path || '/' || printf('%010d.%010d', COALESCE(tabs.order_id, 0), tabs.id)
+        FROM tabs
+        JOIN tab_paths ON tabs.parent_id = tab_paths.id
+        WHERE tabs.deleted_at IS NULL
+    )
+"#;
+
+/// The tabs notes are grouped into. Tabs nest: each has an optional parent, and `order_id`
+/// orders it among its siblings.
+#[derive(Clone)]
+pub struct TabService {
+    pool: SqlitePool,
+}
+
+impl TabService {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn list(&self) -> Result<Vec<Tab>, AppError> {
+        let tabs = query_as::<_, Tab>(
+            r#"
+            SELECT * FROM tabs
+            WHERE deleted_at IS NULL
+            ORDER BY order_id ASC
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| {
+            error!("Failed to fetch tabs: {:#}", e);
+            AppError::database("Failed to fetch tabs. Please try again")
+        })?;
+
+        Ok(tabs)
+    }
+
+    /// The tabs outside the trash as a tree, from the top level down.
+    pub async fn tree(&self) -> Result<Vec<TabNode>, AppError> {
+        let tabs = self.list().await?;
+        Ok(subtree(&tabs, None))
+    }
+
+    /// A tab that is not in the trash.
+    pub async fn get(&self, id: i64) -> Result<Tab, AppError> {
+        query_as::<_, Tab>("SELECT * FROM tabs WHERE id = ? AND deleted_at IS NULL")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| {
+                error!("Failed to fetch tab {}: {:#}", id, e);
+                AppError::database("Failed to load tab. Please try again")
+            })?
+            .ok_or_else(|| AppError::not_found(format!("Tab with id {id} not found")))
+    }
+
+    /// A new tab at the end of the top level.
+    pub async fn create(&self, name: String) -> Result<Tab, AppError> {
+        self.insert(name, None).await
+    }
+
+    /// A new sub-tab at the end of `parent_id`'s sub-tabs.
+    pub async fn create_child(&self, parent_id: i64, name: String) -> Result<Tab, AppError> {
+        self.insert(name, Some(parent_id)).await
+    }
+
+    async fn insert(&self, name: String, parent_id: Option<i64>) -> Result<Tab, AppError> {
+        let mut transaction = self.begin().await?;
+
+        let insert_error = |e: sqlx::Error| {
+            error!("Failed to create tab {}: {:#}", name, e);
+            AppError::database("Failed to create tab. Please try again")
+        };
+
+        if let Some(parent_id) = parent_id {
+            live_tab(&mut transaction, parent_id)
+                .await
+                .map_err(insert_error)?
+                .ok_or_else(|| AppError::not_found(format!("Tab with id {parent_id} not found")))?;
+        }
+
+        let new_order = next_tab_order(&mut transaction, parent_id)
+            .await
+            .map_err(insert_error)?;
+
+        let tab = query_as::<_, Tab>(
+            r#"
+            INSERT INTO tabs (name, parent_id, order_id)
+            VALUES (?, ?, ?)
+            RETURNING *
+            "#,
+        )
+        .bind(&name)
+        .bind(parent_id)
+        .bind(new_order)
+        .fetch_one(&mut *transaction)
+        .await
+        .map_err(insert_error)?;
+
+        commit(transaction).await?;
+        Ok(tab)
+    }
+
+    pub async fn rename(&self, id: i64, name: String) -> Result<Tab, AppError> {
+        query_as::<_, Tab>(
+            r#"
+            UPDATE tabs
+            SET name = ?,
+                updated_at = datetime('now')
+            WHERE id = ? AND deleted_at IS NULL
+            RETURNING *
+            "#,
+        )
+        .bind(name)
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| {
+            error!("Failed to update tab {}: {:#}", id, e);
+            AppError::database("Failed to update tab. Please try again")
+        })?
+        .ok_or_else(|| AppError::not_found(format!("Tab with id {id} not found")))
+    }
+
+    /// Moves the tab and its notes to the trash. `children` says whether its sub-tabs go along
+    /// or move up a level. Returns the tabs left on its level, sub-tabs that moved up included,
+    /// renumbered to close the gap.
+    pub async fn delete(&self, id: i64, children: ChildTabs) -> Result<Vec<Tab>, AppError> {
+        let mut transaction = self.begin().await?;
+
+        let delete_error = |e: sqlx::Error| {
+            error!("Failed to delete tab {}: {:#}", id, e);
+            AppError::database("Failed to delete tab. Please try again")
+        };
+
+        let tab = live_tab(&mut transaction, id)
+            .await
+            .map_err(delete_error)?
+            .ok_or_else(|| AppError::not_found(format!("Tab with id {id} not found")))?;
+
+        if children == ChildTabs::MoveUp {
+            let first_order = next_tab_order(&mut transaction, tab.parent_id)
+                .await
+                .map_err(delete_error)?;
+            let child_ids = live_child_ids(&mut transaction, Some(id))
+                .await
+                .map_err(delete_error)?;
+
+            for (offset, child_id) in child_ids.into_iter().enumerate() {
+                sqlx::query("UPDATE tabs SET parent_id = ?, order_id = ? WHERE id = ?")
+                    .bind(tab.parent_id)
+                    .bind(first_order + offset as i64)
+                    .bind(child_id)
+                    .execute(&mut *transaction)
+                    .await
+                    .map_err(delete_error)?;
+            }
+        }
+
+        sqlx::query("UPDATE tabs SET deleted_at = datetime('now') WHERE id = ?")
+            .bind(id)
+            .execute(&mut *transaction)
+            .await
+            .map_err(delete_error)?;
+
+        // Whatever sub-tabs are left go along. Flagged, so they're restored with the tab.
+        sqlx::query(
+            r#"
+            WITH RECURSIVE descendants(id) AS (
+                SELECT id FROM tabs WHERE parent_id = ?1 AND deleted_at IS NULL
+                UNION
+                SELECT tabs.id FROM tabs
+                JOIN descendants ON tabs.parent_id = descendants.id
+                WHERE tabs.deleted_at IS NULL
+            )
+            UPDATE tabs
+            SET deleted_at = (SELECT deleted_at FROM tabs WHERE id = ?1),
+                trashed_with_parent = 1
+            WHERE id IN descendants
+            "#,
+        )
+        .bind(id)
+        .execute(&mut *transaction)
+        .await
+        .map_err(delete_error)?;
+
+        sqlx::query(&format!(
+            r#"
+            {TRASHED_SUBTREE}
+            UPDATE notes
+            SET deleted_at = (SELECT deleted_at FROM tabs WHERE id = ?1),
+                trashed_with_tab = 1
+            WHERE tab_id IN subtree AND deleted_at IS NULL
+            "#
+        ))
+        .bind(id)
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| {
+            error!("Failed to move notes of tab {} to trash: {:#}", id, e);
+            AppError::database("Failed to delete tab. Please try again")
+        })?;
+
+        let siblings = live_child_ids(&mut transaction, tab.parent_id)
+            .await
+            .map_err(delete_error)?;
+        let mut renumbered = Vec::with_capacity(siblings.len());
+        for (position, sibling) in siblings.into_iter().enumerate() {
+            let tab = query_as::<_, Tab>("UPDATE tabs SET order_id = ? WHERE id = ? RETURNING *")
+                .bind((position + 1) as i64)
+                .bind(sibling)
+                .fetch_one(&mut *transaction)
+                .await
+                .map_err(delete_error)?;
+            renumbered.push(tab);
+        }
+
+        commit(transaction).await?;
+        Ok(renumbered)
+    }
+
+    /// Moves the tab under `parent_id`, or to the top level, at `index` among its new siblings,
+    /// and closes the gap it leaves. Returns every tab whose parent or position changed.
+    pub async fn move_to(
+        &self,
+        id: i64,
+        parent_id: Option<i64>,
+        index: usize,
+    ) -> Result<Vec<Tab>, AppError> {
+        let mut transaction = self.begin().await?;
+
+        let move_error = |e: sqlx::Error| {
+            error!("Failed to move tab {} under {:?}: {:#}", id, parent_id, e);
+            AppError::database("Failed to move tab. Please try again")
+        };
+
+        let tab = live_tab(&mut transaction, id)
+            .await
+            .map_err(move_error)?
+            .ok_or_else(|| AppError::not_found(format!("Tab with id {id} not found")))?;
+
+        if let Some(parent_id) = parent_id {
+            live_tab(&mut transaction, parent_id)
+                .await
+                .map_err(move_error)?
+                .ok_or_else(|| AppError::not_found(format!("Tab with id {parent_id} not found")))?;
+
+            let ancestors: Vec<(i64,)> = sqlx::query_as(
+                r#"
+                WITH RECURSIVE ancestors(id, parent_id) AS (
+                    SELECT id, parent_id FROM tabs WHERE id = ?
+                    UNION
+                    SELECT tabs.id, tabs.parent_id FROM tabs
+                    JOIN ancestors ON tabs.id = ancestors.parent_id
+                )
+                SELECT id FROM ancestors
+                "#,
+            )
+            .bind(parent_id)
+            .fetch_all(&mut *transaction)
+            .await
+            .map_err(move_error)?;
+
+            if ancestors.iter().any(|&(ancestor,)| ancestor == id) {
+                return Err(AppError::validation(
+                    "A tab can't be moved into itself or one of its sub-tabs",
+                ));
+            }
+        }
+
+        let mut order = live_child_ids(&mut transaction, parent_id)
+            .await
+            .map_err(move_error)?;
+        order.retain(|&sibling| sibling != id);
+        let index = index.min(order.len());
+        order.insert(index, id);
+
+        for (position, &sibling) in order.iter().enumerate() {
+            sqlx::query("UPDATE tabs SET parent_id = ?, order_id = ? WHERE id = ?")
+                .bind(parent_id)
+                .bind((position + 1) as i64)
+                .bind(sibling)
+                .execute(&mut *transaction)
+                .await
+                .map_err(move_error)?;
+        }
+
+        let mut changed = order;
+        if tab.parent_id != parent_id {
+            let remaining = live_child_ids(&mut transaction, tab.parent_id)
+                .await
+                .map_err(move_error)?;
+            for (position, &sibling) in remaining.iter().enumerate() {
+                sqlx::query("UPDATE tabs SET order_id = ? WHERE id = ?")
+                    .bind((position + 1) as i64)
+                    .bind(sibling)
+                    .execute(&mut *transaction)
+                    .await
+                    .map_err(move_error)?;
+            }
+            changed.extend(remaining);
+        }
+
+        let mut tabs = Vec::with_capacity(changed.len());
+        for id in changed {
+            let tab = query_as::<_, Tab>("SELECT * FROM tabs WHERE id = ?")
+                .bind(id)
+                .fetch_one(&mut *transaction)
+                .await
+                .map_err(move_error)?;
+            tabs.push(tab);
+        }
+
+        commit(transaction).await?;
+        Ok(tabs)
+    }
+
+    /// Orders sibling tabs as given.
+    pub async fn reorder(&self, tab_ids: Vec<i64>) -> Result<(), AppError> {
+        let len = tab_ids.len() as i64;
+        if len == 0 {
+            return Ok(());
+        }
+
+        let mut transaction = self.begin().await?;
+
+        let ids = serde_json::to_string(&tab_ids).unwrap_or_else(|_| "[]".to_string());
+        let (parents,): (i64,) = sqlx::query_as(
+            r#"
+            SELECT COUNT(DISTINCT COALESCE(parent_id, 0)) FROM tabs
+            WHERE id IN (SELECT value FROM json_each(?))
+            "#,
+        )
+        .bind(ids)
+        .fetch_one(&mut *transaction)
+        .await
+        .map_err(|e| {
+            error!("Failed to look up parents of tabs {:?}: {:#}", tab_ids, e);
+            AppError::database("Failed to reorder tabs")
+        })?;
+        if parents > 1 {
+            return Err(AppError::validation(
+                "Only tabs with the same parent can be reordered together",
+            ));
+        }
+
+        for (index, &id) in tab_ids.iter().enumerate() {
+            let order_id = (index + 1) as i64;
+
+            sqlx::query("UPDATE tabs SET order_id = ? WHERE id = ?")
+                .bind(order_id)
+                .bind(id)
+                .execute(&mut *transaction)
+                .await
+                .map_err(|e| {
+                    error!("Failed to reorder tab {}: {:#}", id, e);
+                    AppError::database("Failed to reorder tabs")
+                })?;
+        }
+
+        commit(transaction).await
+    }
+
+    async fn begin(&self) -> Result<sqlx::Transaction<'static, sqlx::Sqlite>, AppError> {
+        self.pool.begin().await.map_err(|e| {
+            error!("Failed to start transaction: {:#}", e);
+            AppError::database("Failed to start transaction")
+        })
+    }
+}
+
+/// The tabs of `tabs` under `parent_id`, each with its own sub-tabs. `tabs` is in order.
+fn subtree(tabs: &[Tab], parent_id: Option<i64>) -> Vec<TabNode> {
+    tabs.iter()
+        .filter(|tab| tab.parent_id == parent_id)
+        .map(|tab| TabNode {
+            tab: tab.clone(),
+            children: subtree(tabs, Some(tab.id)),
+        })
+        .collect()
+}
+
+async fn live_tab(conn: &mut SqliteConnection, id: i64) -> Result<Option<Tab>, sqlx::Error> {
+    query_as::<_, Tab>("SELECT * FROM tabs WHERE id = ? AND deleted_at IS NULL")
+        .bind(id)
+        .fetch_optional(&mut *conn)
+        .await
+}
+
+/// The tabs directly under `parent_id` outside the trash, in order.
+async fn live_child_ids(
+    conn: &mut SqliteConnection,
+    parent_id: Option<i64>,
+) -> Result<Vec<i64>, sqlx::Error> {
+    let rows: Vec<(i64,)> = sqlx::query_as(
+        r#"
+        SELECT id FROM tabs
+        WHERE parent_id IS ? AND deleted_at IS NULL
+        ORDER BY order_id ASC, id ASC
+        "#,
+    )
+    .bind(parent_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(rows.into_iter().map(|(id,)| id).collect())
+}
+
+async fn commit(transaction: sqlx::Transaction<'_, sqlx::Sqlite>) -> Result<(), AppError> {
+    transaction.commit().await.map_err(|e| {
+        error!("Failed to commit transaction: {:#}", e);
+        AppError::database("Failed to commit transaction")
+    })
+}
+
+/// The order_id that appends a tab after all others under `parent_id`.
+pub(crate) async fn next_tab_order(
+    conn: &mut SqliteConnection,
+    parent_id: Option<i64>,
+) -> Result<i64, sqlx::Error> {
+    let row: Option<(Option<i64>,)> = sqlx::query_as(
+        r#"
+        SELECT MAX(order_id) FROM tabs WHERE parent_id IS ?
+        "#,
+    )
+    .bind(parent_id)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    Ok(row.and_then(|r| r.0).unwrap_or(0) + 1)
+}
@@ -0,0 +1,351 @@
+use crate::error::AppError;
+use crate::models::{Note, NoteTag, Tag, TagMatch, TagUsage};
+use crate::tabs::TAB_PATHS;
+use log::error;
+use sqlx::{query_as, SqliteConnection, SqlitePool};
+
+/// Tags, which cut across tabs: a note can carry any number of them.
+#[derive(Clone)]
+pub struct TagService {
+    pool: SqlitePool,
+}
+
+impl TagService {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Every tag with the number of notes carrying it, by name.
+    pub async fn list(&self) -> Result<Vec<TagUsage>, AppError> {
+        query_as::<_, TagUsage>(
+            r#"
+            SELECT t.id, t.name, COUNT(n.id) AS note_count
+            FROM tags t
+            LEFT JOIN note_tags nt ON nt.tag_id = t.id
+            LEFT JOIN notes n ON n.id = nt.note_id AND n.deleted_at IS NULL
+            GROUP BY t.id
+            ORDER BY t.name COLLATE NOCASE ASC
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| {
+            error!("Failed to fetch tags: {:#}", e);
+            AppError::database("Failed to load tags. Please try again")
+        })
+    }
+
+    pub async fn get(&self, id: i64) -> Result<Tag, AppError> {
+        let mut conn = self.acquire().await?;
+        fetch_tag(&mut conn, id).await
+    }
+
+    /// Fails with a conflict carrying the existing tag if the name is taken.
+    pub async fn create(&self, name: String) -> Result<Tag, AppError> {
+        let name = tag_name(&name)?;
+        let mut transaction = self.begin().await?;
+
+        ensure_name_free(&mut transaction, &name, None).await?;
+
+        let tag = query_as::<_, Tag>("INSERT INTO tags (name) VALUES (?) RETURNING *")
+            .bind(&name)
+            .fetch_one(&mut *transaction)
+            .await
+            .map_err(|e| {
+                error!("Failed to create tag {}: {:#}", name, e);
+                AppError::database("Failed to create tag. Please try again")
+            })?;
+
+        commit(transaction).await?;
+        Ok(tag)
+    }
+
+    /// Fails with a conflict carrying the other tag if the name is taken; merge the two instead.
+    pub async fn rename(&self, id: i64, name: String) -> Result<Tag, AppError> {
+        let name = tag_name(&name)?;
+        let mut transaction = self.begin().await?;
+
+        ensure_name_free(&mut transaction, &name, Some(id)).await?;
+
+        let tag = query_as::<_, Tag>(
+            r#"
+            UPDATE tags
+            SET name = ?,
+                updated_at = datetime('now')
+            WHERE id = ?
+            RETURNING *
+            "#,
+        )
+        .bind(&name)
+        .bind(id)
+        .fetch_optional(&mut *transaction)
+        .await
+        .map_err(|e| {
+            error!("Failed to rename tag {}: {:#}", id, e);
+            AppError::database("Failed to rename tag. Please try again")
+        })?
+        .ok_or_else(|| AppError::not_found(format!("Tag with id {id} not found")))?;
+
+        commit(transaction).await?;
+        Ok(tag)
+    }
+
+    /// Moves every note tagged `from_id` over to `into_id` and deletes `from_id`.
+    pub async fn merge(&self, from_id: i64, into_id: i64) -> Result<Tag, AppError> {
+        if from_id == into_id {
+            return Err(AppError::validation("A tag can't be merged into itself"));
+        }
+
+        let mut transaction = self.begin().await?;
+        fetch_tag(&mut transaction, from_id).await?;
+        fetch_tag(&mut transaction, into_id).await?;
+
+        let merge_error = |e: sqlx::Error| {
+            error!("Failed to merge tag {} into {}: {:#}", from_id, into_id, e);
+            AppError::database("Failed to merge tags. Please try again")
+        };
+
+        sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO note_tags (note_id, tag_id)
+            SELECT note_id, ?1 FROM note_tags WHERE tag_id = ?2
+            "#,
+        )
+        .bind(into_id)
+        .bind(from_id)
+        .execute(&mut *transaction)
+        .await
+        .map_err(merge_error)?;
+
+        sqlx::query("DELETE FROM tags WHERE id = ?")
+            .bind(from_id)
+            .execute(&mut *transaction)
+            .await
+            .map_err(merge_error)?;
+
+        let tag = query_as::<_, Tag>(
+            "UPDATE tags SET updated_at = datetime('now') WHERE id = ? RETURNING *",
+        )
+        .bind(into_id)
+        .fetch_one(&mut *transaction)
+        .await
+        .map_err(merge_error)?;
+
+        commit(transaction).await?;
+        Ok(tag)
+    }
+
+    /// Deletes the tag and takes it off every note. The notes themselves stay.
+    pub async fn delete(&self, id: i64) -> Result<(), AppError> {
+        let result = sqlx::query("DELETE FROM tags WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| {
+                error!("Failed to delete tag {}: {:#}", id, e);
+                AppError::database("Failed to delete tag. Please try again")
+            })?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::not_found(format!("Tag with id {id} not found")));
+        }
+
+        Ok(())
+    }
+
+    /// Tags the note, unless it already is, and returns all of its tags.
+    pub async fn attach(&self, note_id: i64, tag_id: i64) -> Result<Vec<Tag>, AppError> {
+        let mut transaction = self.begin().await?;
+
+        let note: Option<(i64,)> =
+            sqlx::query_as("SELECT id FROM notes WHERE id = ? AND deleted_at IS NULL")
+                .bind(note_id)
+                .fetch_optional(&mut *transaction)
+                .await
+                .map_err(|e| {
+                    error!("Failed to fetch note {}: {:#}", note_id, e);
+                    AppError::database("Failed to tag note. Please try again")
+                })?;
+        if note.is_none() {
+            return Err(AppError::not_found(format!(
+                "Note with id {note_id} not found"
+            )));
+        }
+        fetch_tag(&mut transaction, tag_id).await?;
+
+        sqlx::query("INSERT OR IGNORE INTO note_tags (note_id, tag_id) VALUES (?, ?)")
+            .bind(note_id)
+            .bind(tag_id)
+            .execute(&mut *transaction)
+            .await
+            .map_err(|e| {
+                error!("Failed to tag note {} with {}: {:#}", note_id, tag_id, e);
+                AppError::database("Failed to tag note. Please try again")
+            })?;
+
+        let tags = note_tags(&mut transaction, note_id).await?;
+        commit(transaction).await?;
+        Ok(tags)
+    }
+
+    /// Takes the tag off the note and returns the tags it has left.
+    pub async fn detach(&self, note_id: i64, tag_id: i64) -> Result<Vec<Tag>, AppError> {
+        let mut conn = self.acquire().await?;
+
+        sqlx::query("DELETE FROM note_tags WHERE note_id = ? AND tag_id = ?")
+            .bind(note_id)
+            .bind(tag_id)
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| {
+                error!("Failed to untag note {} from {}: {:#}", note_id, tag_id, e);
+                AppError::database("Failed to remove tag from note. Please try again")
+            })?;
+
+        note_tags(&mut conn, note_id).await
+    }
+
+    /// The tags of one note, by name.
+    pub async fn for_note(&self, note_id: i64) -> Result<Vec<Tag>, AppError> {
+        let mut conn = self.acquire().await?;
+        note_tags(&mut conn, note_id).await
+    }
+
+    /// Which note carries which tag. Notes in the trash are included, since they keep their
+    /// tags when restored.
+    pub async fn assignments(&self) -> Result<Vec<NoteTag>, AppError> {
+        query_as::<_, NoteTag>("SELECT note_id, tag_id FROM note_tags ORDER BY note_id, tag_id")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| {
+                error!("Failed to fetch note tags: {:#}", e);
+                AppError::database("Failed to load tags. Please try again")
+            })
+    }
+
+    /// Notes outside the trash carrying all of `tag_ids`, or any of them, in the order of the
+    /// tab tree.
+    pub async fn notes(&self, tag_ids: Vec<i64>, mode: TagMatch) -> Result<Vec<Note>, AppError> {
+        let mut tag_ids = tag_ids;
+        tag_ids.sort_unstable();
+        tag_ids.dedup();
+        if tag_ids.is_empty() {
+            return Err(AppError::validation("Pick at least one tag to filter by"));
+        }
+
+        let required = match mode {
+            TagMatch::All => tag_ids.len() as i64,
+            TagMatch::Any => 1,
+        };
+        let ids = serde_json::to_string(&tag_ids).unwrap_or_else(|_| "[]".to_string());
+
+        query_as::<_, Note>(&format!(
+            r#"
+            {TAB_PATHS}
+            SELECT n.* FROM notes n
+            LEFT JOIN tab_paths t ON t.id = n.tab_id
+            WHERE n.deleted_at IS NULL
+              AND n.id IN (
+                SELECT note_id FROM note_tags
+                WHERE tag_id IN (SELECT value FROM json_each(?))
+                GROUP BY note_id
+                HAVING COUNT(*) >= ?
+              )
+            ORDER BY t.path ASC, n.order_id ASC
+            "#
+        ))
+        .bind(ids)
+        .bind(required)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| {
+            error!("Failed to fetch notes tagged {:?}: {:#}", tag_ids, e);
+            AppError::database("Failed to load notes. Please try again")
+        })
+    }
+
+    async fn acquire(&self) -> Result<sqlx::pool::PoolConnection<sqlx::Sqlite>, AppError> {
+        self.pool.acquire().await.map_err(|e| {
+            error!("Database error: {:#}", e);
+            AppError::database("Database error")
+        })
+    }
+
+    async fn begin(&self) -> Result<sqlx::Transaction<'static, sqlx::Sqlite>, AppError> {
+        self.pool.begin().await.map_err(|e| {
+            error!("Failed to start transaction: {:#}", e);
+            AppError::database("Failed to start transaction")
+        })
+    }
+}
+
+/// `name` trimmed, or a validation error if nothing is left.
+fn tag_name(name: &str) -> Result<String, AppError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(AppError::validation("A tag needs a name"));
+    }
+    Ok(name.to_string())
+}
+
+async fn ensure_name_free(
+    conn: &mut SqliteConnection,
+    name: &str,
+    except_id: Option<i64>,
+) -> Result<(), AppError> {
+    let existing = query_as::<_, Tag>("SELECT * FROM tags WHERE name = ? AND id IS NOT ?")
+        .bind(name)
+        .bind(except_id)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| {
+            error!("Failed to look up tag {}: {:#}", name, e);
+            AppError::database("Failed to save tag. Please try again")
+        })?;
+
+    match existing {
+        Some(existing) => Err(AppError::conflict(format!(
+            "A tag named {} already exists",
+            existing.name
+        ))
+        .with_details(&existing)),
+        None => Ok(()),
+    }
+}
+
+async fn fetch_tag(conn: &mut SqliteConnection, id: i64) -> Result<Tag, AppError> {
+    query_as::<_, Tag>("SELECT * FROM tags WHERE id = ?")
+        .bind(id)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| {
+            error!("Failed to fetch tag {}: {:#}", id, e);
+            AppError::database("Failed to load tag. Please try again")
+        })?
+        .ok_or_else(|| AppError::not_found(format!("Tag with id {id} not found")))
+}
+
+async fn note_tags(conn: &mut SqliteConnection, note_id: i64) -> Result<Vec<Tag>, AppError> {
+    query_as::<_, Tag>(
+        r#"
+        SELECT t.* FROM tags t
+        JOIN note_tags nt ON nt.tag_id = t.id
+        WHERE nt.note_id = ?
+        ORDER BY t.name COLLATE NOCASE ASC
+        "#,
+    )
+    .bind(note_id)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch tags of note {}: {:#}", note_id, e);
+        AppError::database("Failed to load tags. Please try again")
+    })
+}
+
+async fn commit(transaction: sqlx::Transaction<'_, sqlx::Sqlite>) -> Result<(), AppError> {
+    transaction.commit().await.map_err(|e| {
+        error!("Failed to commit transaction: {:#}", e);
+        AppError::database("Failed to commit transaction")
+    })
+}
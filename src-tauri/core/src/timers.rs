@@ -0,0 +1,72 @@
+use crate::error::AppError;
+use crate::models::Timer;
+use log::error;
+use sqlx::{query_as, SqlitePool};
+
+/// The single saved timer.
+#[derive(Clone)]
+pub struct TimerService {
+    pool: SqlitePool,
+}
+
+impl TimerService {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn save(
+        &self,
+        initial_duration: i32,
+        duration: i32,
+        message: String,
+    ) -> Result<Timer, AppError> {
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO timers (id, initial_duration, duration, message)
+            VALUES (1, ?, ?, ?)
+            "#,
+        )
+        .bind(initial_duration)
+        .bind(duration)
+        .bind(message)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| {
+            error!("Failed to create/upsert timer: {:#}", e);
+            AppError::database("Failed to save timer. Please try again")
+        })?;
+
+        let timer = sqlx::query_as::<_, Timer>(
+            r#"
+            SELECT * FROM timers WHERE id = 1
+            "#,
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| {
+            error!("Failed to fetch timer after create: {:#}", e);
+            AppError::database("Failed to load timer. Please try again")
+        })?;
+
+        Ok(timer)
+    }
+
+    pub async fn get(&self) -> Result<Timer, AppError> {
+        let timer = query_as::<_, Timer>(
+            r#"
+            SELECT * FROM timers WHERE id = 1
+            "#,
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| {
+            error!("Failed to get timer: {:#}", e);
+            AppError::database("Failed to load timer. Please try again")
+        })?;
+
+        match timer {
+            Some(t) => Ok(t),
+            None => Err(AppError::not_found("No timer found")),
+        }
+    }
+}
@@ -0,0 +1,433 @@
+use crate::error::AppError;
+use crate::html;
+use crate::markdown;
+use crate::models::{FolderImportSummary, ImportedFile, MarkdownExportSummary, Note, Tab};
+use crate::notes::next_note_order;
+use crate::tabs::next_tab_order;
+use log::{error, info};
+use sqlx::{query_as, SqlitePool};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, read_dir};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use time::{macros::format_description, OffsetDateTime};
+
+/// Moves notes in and out of the app as folders of Markdown and text files.
+#[derive(Clone)]
+pub struct TransferService {
+    pool: SqlitePool,
+}
+
+impl TransferService {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Exports every tab as a folder of Markdown notes, inside a new timestamped folder under
+    /// `parent`.
+    pub async fn export_markdown(&self, parent: &Path) -> Result<MarkdownExportSummary, AppError> {
+        let load_error = |e: sqlx::Error| {
+            error!("Failed to read notes for export: {:#}", e);
+            AppError::database("Failed to export notes. Please try again")
+        };
+
+        let tabs =
+            query_as::<_, Tab>("SELECT * FROM tabs WHERE deleted_at IS NULL ORDER BY order_id")
+                .fetch_all(&self.pool)
+                .await
+                .map_err(load_error)?;
+
+        let notes = query_as::<_, Note>(
+            "SELECT * FROM notes WHERE deleted_at IS NULL ORDER BY tab_id, order_id",
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(load_error)?;
+
+        let timestamp = OffsetDateTime::now_local()
+            .unwrap_or_else(|_| OffsetDateTime::now_utc())
+            .format(&format_description!(
+                "[year]-[month]-[day]_T[hour]H-[minute]M-[second]S"
+            ))
+            .map_err(|e| {
+                error!("Failed to format local time: {:#}", e);
+                AppError::io("Failed to name the export folder.")
+            })?;
+
+        let root = parent.join(format!("focusboard-notes_{}", timestamp));
+        fs::create_dir_all(&root).map_err(|e| {
+            error!("Failed to create export directory {:?}: {:#}", root, e);
+            AppError::io("Failed to create the export folder.")
+        })?;
+
+        let mut root_names = HashSet::new();
+        let mut note_count = 0;
+
+        for tab in &tabs {
+            let dir = root.join(unique_file_name(&tab.name, "Untitled tab", &mut root_names));
+            fs::create_dir_all(&dir).map_err(|e| {
+                error!("Failed to create tab directory {:?}: {:#}", dir, e);
+                AppError::io("Failed to create the export folder.")
+            })?;
+
+            let mut names = HashSet::new();
+            for note in notes.iter().filter(|note| note.tab_id == Some(tab.id)) {
+                write_markdown_note(&dir, note, &mut names)?;
+                note_count += 1;
+            }
+        }
+
+        // Notes outside any (live) tab go directly in the export folder.
+        let tab_ids: HashSet<i64> = tabs.iter().map(|tab| tab.id).collect();
+        for note in notes
+            .iter()
+            .filter(|note| note.tab_id.is_none_or(|tab_id| !tab_ids.contains(&tab_id)))
+        {
+            write_markdown_note(&root, note, &mut root_names)?;
+            note_count += 1;
+        }
+
+        info!(
+            "Exported {} notes in {} tabs to {:?}",
+            note_count,
+            tabs.len(),
+            root
+        );
+
+        Ok(MarkdownExportSummary {
+            path: root.to_string_lossy().into_owned(),
+            tab_count: tabs.len(),
+            note_count,
+        })
+    }
+
+    /// Imports a folder of Markdown (`.md`) and text (`.txt`) files. Each subfolder becomes a new
+    /// tab holding the files inside it; files directly in the folder go into a tab named after it.
+    pub async fn import_folder(&self, root: &Path) -> Result<FolderImportSummary, AppError> {
+        let read_error = |e: std::io::Error| {
+            error!("Failed to read import folder {:?}: {:#}", root, e);
+            AppError::io("Failed to read the import folder.")
+        };
+
+        let entries = visible_entries(root).map_err(read_error)?;
+
+        let folder_name = |path: &Path| {
+            path.file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "Imported notes".to_string())
+        };
+
+        // (tab name, files), loose files in the root first.
+        let mut groups: Vec<(String, Vec<PathBuf>)> = Vec::new();
+        let loose = entries
+            .iter()
+            .filter(|path| !path.is_dir() && is_importable(path))
+            .cloned()
+            .collect();
+        groups.push((folder_name(root), loose));
+        for dir in entries.iter().filter(|path| path.is_dir()) {
+            let mut files = Vec::new();
+            collect_import_files(dir, &mut files).map_err(read_error)?;
+            groups.push((folder_name(dir), files));
+        }
+
+        let import_error = |e: sqlx::Error| {
+            error!("Failed to import notes from {:?}: {:#}", root, e);
+            AppError::database("Failed to import notes. Please try again")
+        };
+
+        let mut transaction = self.pool.begin().await.map_err(|e| {
+            error!("Failed to start transaction: {:#}", e);
+            AppError::database("Failed to start transaction")
+        })?;
+
+        let mut files = Vec::new();
+        let mut tabs_created = 0;
+        let mut notes_imported = 0;
+
+        for (tab_name, paths) in groups {
+            if paths.is_empty() {
+                continue;
+            }
+
+            let mut notes = Vec::new();
+            for path in paths {
+                match read_import_file(&path) {
+                    Ok(note) => notes.push((path, note)),
+                    Err(error) => files.push(ImportedFile {
+                        path: path.to_string_lossy().into_owned(),
+                        note_id: None,
+                        error: Some(error),
+                    }),
+                }
+            }
+            if notes.is_empty() {
+                continue;
+            }
+            // Notes exported by FocusBoard keep their order; the rest follow by file name.
+            notes.sort_by_key(|(_, note)| (note.order_id.is_none(), note.order_id));
+
+            let tab_order = next_tab_order(&mut transaction, None)
+                .await
+                .map_err(import_error)?;
+            let (tab_id,): (i64,) = sqlx::query_as(
+                r#"
+                INSERT INTO tabs (name, order_id)
+                VALUES (?, ?)
+                RETURNING id
+                "#,
+            )
+            .bind(&tab_name)
+            .bind(tab_order)
+            .fetch_one(&mut *transaction)
+            .await
+            .map_err(import_error)?;
+            tabs_created += 1;
+
+            for (path, note) in notes {
+                let order_id = next_note_order(&mut transaction, Some(tab_id))
+                    .await
+                    .map_err(import_error)?;
+                let (id,): (i64,) = sqlx::query_as(
+                    r#"
+                    INSERT INTO notes (title, content, tab_id, order_id, created_at, updated_at, title_text, content_text)
+                    VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+                    RETURNING id
+                    "#,
+                )
+                .bind(&note.title)
+                .bind(&note.content)
+                .bind(tab_id)
+                .bind(order_id)
+                .bind(&note.created_at)
+                .bind(&note.updated_at)
+                .bind(html::to_plain_text(&note.title))
+                .bind(html::to_plain_text(&note.content))
+                .fetch_one(&mut *transaction)
+                .await
+                .map_err(import_error)?;
+
+                notes_imported += 1;
+                files.push(ImportedFile {
+                    path: path.to_string_lossy().into_owned(),
+                    note_id: Some(id),
+                    error: None,
+                });
+            }
+        }
+
+        transaction.commit().await.map_err(|e| {
+            error!("Failed to commit transaction: {:#}", e);
+            AppError::database("Failed to commit transaction")
+        })?;
+
+        info!(
+            "Imported {} notes into {} tabs from {:?}",
+            notes_imported, tabs_created, root
+        );
+
+        Ok(FolderImportSummary {
+            path: root.to_string_lossy().into_owned(),
+            tabs_created,
+            notes_imported,
+            files,
+        })
+    }
+}
+
+/// Turns a tab or note title into a file name that is valid on every platform and not yet
+/// taken in `used` (compared case-insensitively).
+fn unique_file_name(name: &str, fallback: &str, used: &mut HashSet<String>) -> String {
+    const RESERVED: &[&str] = &[
+        "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "LPT1", "LPT2", "LPT3",
+    ];
+
+    let cleaned: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .take(80)
+        .collect();
+    let mut base = cleaned.trim().trim_matches('.').trim().to_string();
+    if base.is_empty() {
+        base = fallback.to_string();
+    }
+    if RESERVED.contains(&base.to_ascii_uppercase().as_str()) {
+        base.push('_');
+    }
+
+    let mut candidate = base.clone();
+    let mut counter = 2;
+    while !used.insert(candidate.to_lowercase()) {
+        candidate = format!("{} ({})", base, counter);
+        counter += 1;
+    }
+    candidate
+}
+
+fn write_markdown_note(
+    dir: &Path,
+    note: &Note,
+    used: &mut HashSet<String>,
+) -> Result<(), AppError> {
+    let title = html::to_plain_text(&note.title);
+    let file_name = unique_file_name(&title, "Untitled", used);
+    let path = dir.join(format!("{}.md", file_name));
+
+    // A JSON string is also a valid double-quoted YAML scalar.
+    let front_matter = format!(
+        "---\ntitle: {}\ncreated_at: {}\nupdated_at: {}\norder_id: {}\n---\n\n",
+        serde_json::to_string(&title).unwrap_or_default(),
+        note.created_at,
+        note.updated_at,
+        note.order_id
+            .map_or("null".to_string(), |order| order.to_string())
+    );
+    let body = markdown::from_html(&note.content);
+
+    fs::write(&path, format!("{}{}\n", front_matter, body)).map_err(|e| {
+        error!("Failed to write note {} to {:?}: {:#}", note.id, path, e);
+        AppError::io(format!("Failed to write {:?}.", path))
+    })
+}
+
+/// A Markdown or text file read from disk, ready to become a note.
+struct ImportedNote {
+    title: String,
+    content: String,
+    created_at: String,
+    updated_at: String,
+    order_id: Option<i64>,
+}
+
+const IMPORT_EXTENSIONS: [&str; 3] = ["md", "markdown", "txt"];
+
+/// Collects importable files under `dir`, recursively and sorted by path.
+fn collect_import_files(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for path in visible_entries(dir)? {
+        if path.is_dir() {
+            collect_import_files(&path, files)?;
+        } else if is_importable(&path) {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Entries of `dir` sorted by path, without hidden files and folders.
+fn visible_entries(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut entries: Vec<PathBuf> = read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.file_name()
+                .is_some_and(|name| !name.to_string_lossy().starts_with('.'))
+        })
+        .collect();
+    entries.sort();
+    Ok(entries)
+}
+
+fn is_importable(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| {
+        IMPORT_EXTENSIONS
+            .iter()
+            .any(|known| ext.eq_ignore_ascii_case(known))
+    })
+}
+
+/// Splits the front-matter block written by `export_markdown` off the top of `text`.
+fn split_front_matter(text: &str) -> (HashMap<String, String>, &str) {
+    let mut fields = HashMap::new();
+
+    let Some(rest) = text
+        .strip_prefix("---\r\n")
+        .or_else(|| text.strip_prefix("---\n"))
+    else {
+        return (fields, text);
+    };
+
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        offset += line.len();
+        let line = line.trim_end();
+        if line == "---" {
+            return (fields, &rest[offset..]);
+        }
+        if let Some((key, value)) = line.split_once(':') {
+            fields.insert(key.trim().to_string(), value.trim().to_string());
+        }
+    }
+
+    // No closing delimiter: not front-matter after all.
+    (HashMap::new(), text)
+}
+
+fn read_import_file(path: &Path) -> Result<ImportedNote, String> {
+    let text = fs::read_to_string(path).map_err(|e| {
+        error!("Failed to read {:?} for import: {:#}", path, e);
+        match e.kind() {
+            ErrorKind::InvalidData => "The file is not valid UTF-8 text.".to_string(),
+            _ => "The file could not be read.".to_string(),
+        }
+    })?;
+
+    let modified = fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .map(OffsetDateTime::from)
+        .unwrap_or_else(|_| OffsetDateTime::now_utc());
+    let modified = modified
+        .format(&format_description!(
+            "[year]-[month]-[day] [hour]:[minute]:[second]"
+        ))
+        .map_err(|e| e.to_string())?;
+
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let is_text = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("txt"));
+    if is_text {
+        return Ok(ImportedNote {
+            title: html::escape(&stem),
+            content: markdown::text_to_html(&text),
+            created_at: modified.clone(),
+            updated_at: modified,
+            order_id: None,
+        });
+    }
+
+    let (fields, body) = split_front_matter(&text);
+    let title = fields
+        .get("title")
+        .map(|title| serde_json::from_str::<String>(title).unwrap_or_else(|_| title.clone()))
+        .filter(|title| !title.trim().is_empty())
+        .unwrap_or(stem);
+    let timestamp = |key: &str| {
+        fields
+            .get(key)
+            .filter(|value| {
+                time::PrimitiveDateTime::parse(
+                    value,
+                    &format_description!("[year]-[month]-[day] [hour]:[minute]:[second]"),
+                )
+                .is_ok()
+            })
+            .cloned()
+            .unwrap_or_else(|| modified.clone())
+    };
+
+    Ok(ImportedNote {
+        title: html::escape(&title),
+        content: markdown::to_html(body),
+        created_at: timestamp("created_at"),
+        updated_at: timestamp("updated_at"),
+        order_id: fields.get("order_id").and_then(|order| order.parse().ok()),
+    })
+}
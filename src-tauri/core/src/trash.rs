@@ -0,0 +1,250 @@
+use crate::error::AppError;
+use crate::models::{Note, Tab, Trash, TrashedTab};
+use crate::tabs::TRASHED_SUBTREE;
+use log::error;
+use sqlx::{query_as, SqlitePool};
+
+/// Deleted notes and tabs, until they are restored or purged.
+#[derive(Clone)]
+pub struct TrashService {
+    pool: SqlitePool,
+}
+
+impl TrashService {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn list(&self) -> Result<Trash, AppError> {
+        // Sub-tabs trashed along with their parent are listed, and counted, as part of it.
+        let tabs = query_as::<_, TrashedTab>(
+            r#"
+            WITH RECURSIVE trashed(id, root_id) AS (
+                SELECT id, id FROM tabs
+                WHERE deleted_at IS NOT NULL AND trashed_with_parent = 0
+                UNION ALL
+                SELECT tabs.id, trashed.root_id FROM tabs
+                JOIN trashed ON tabs.parent_id = trashed.id
+                WHERE tabs.trashed_with_parent = 1
+            )
+            SELECT tabs.id, tabs.name, tabs.deleted_at,
+                (SELECT COUNT(*) FROM notes
+                 JOIN trashed ON notes.tab_id = trashed.id
+                 WHERE trashed.root_id = tabs.id AND notes.trashed_with_tab = 1) AS note_count
+            FROM tabs
+            WHERE tabs.deleted_at IS NOT NULL AND tabs.trashed_with_parent = 0
+            ORDER BY tabs.deleted_at DESC
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| {
+            error!("Failed to fetch trashed tabs: {:#}", e);
+            AppError::database("Failed to load trash. Please try again")
+        })?;
+
+        let notes = query_as::<_, Note>(
+            r#"
+            SELECT * FROM notes
+            WHERE deleted_at IS NOT NULL AND trashed_with_tab = 0
+            ORDER BY deleted_at DESC
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| {
+            error!("Failed to fetch trashed notes: {:#}", e);
+            AppError::database("Failed to load trash. Please try again")
+        })?;
+
+        Ok(Trash { tabs, notes })
+    }
+
+    pub async fn restore_note(&self, id: i64) -> Result<Note, AppError> {
+        let tab_deleted: Option<(Option<String>,)> = sqlx::query_as(
+            r#"
+            SELECT tabs.deleted_at FROM notes
+            LEFT JOIN tabs ON tabs.id = notes.tab_id
+            WHERE notes.id = ? AND notes.deleted_at IS NOT NULL AND notes.trashed_with_tab = 0
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| {
+            error!("Database error: {:#}", e);
+            AppError::database("Database error")
+        })?;
+
+        match tab_deleted {
+            None => {
+                return Err(AppError::not_found(format!(
+                    "Note with id {id} not found in trash"
+                )))
+            }
+            Some((Some(_),)) => {
+                return Err(AppError::conflict(
+                    "This note's tab is in the trash. Restore the tab first",
+                ))
+            }
+            Some((None,)) => {}
+        }
+
+        let note = query_as::<_, Note>(
+            r#"
+            UPDATE notes
+            SET deleted_at = NULL,
+                order_id = (
+                    SELECT COALESCE(MAX(order_id), 0) + 1 FROM notes AS live
+                    WHERE live.tab_id IS NOT DISTINCT FROM notes.tab_id AND live.deleted_at IS NULL
+                )
+            WHERE id = ?
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| {
+            error!("Failed to restore note {}: {:#}", id, e);
+            AppError::database("Failed to restore note. Please try again")
+        })?;
+
+        Ok(note)
+    }
+
+    /// Restores the tab with the sub-tabs and notes that went to the trash along with it. Returns
+    /// the tab, then its sub-tabs.
+    pub async fn restore_tab(&self, id: i64) -> Result<Vec<Tab>, AppError> {
+        let mut transaction = self.pool.begin().await.map_err(|e| {
+            error!("Failed to start transaction: {:#}", e);
+            AppError::database("Failed to start transaction")
+        })?;
+
+        let restore_error = |e: sqlx::Error| {
+            error!("Failed to restore tab {}: {:#}", id, e);
+            AppError::database("Failed to restore tab. Please try again")
+        };
+
+        let parent_deleted: Option<(Option<String>,)> = sqlx::query_as(
+            r#"
+            SELECT parent.deleted_at FROM tabs
+            LEFT JOIN tabs AS parent ON parent.id = tabs.parent_id
+            WHERE tabs.id = ? AND tabs.deleted_at IS NOT NULL
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&mut *transaction)
+        .await
+        .map_err(restore_error)?;
+
+        match parent_deleted {
+            None => {
+                return Err(AppError::not_found(format!(
+                    "Tab with id {id} not found in trash"
+                )))
+            }
+            Some((Some(_),)) => {
+                return Err(AppError::conflict(
+                    "This tab's parent is in the trash. Restore the parent first",
+                ))
+            }
+            Some((None,)) => {}
+        }
+
+        let subtree: Vec<(i64,)> =
+            sqlx::query_as(&format!("{TRASHED_SUBTREE} SELECT id FROM subtree"))
+                .bind(id)
+                .fetch_all(&mut *transaction)
+                .await
+                .map_err(restore_error)?;
+
+        // The notes keep their order_id while in the trash, so they come back in their original order.
+        sqlx::query(&format!(
+            r#"
+            {TRASHED_SUBTREE}
+            UPDATE notes
+            SET deleted_at = NULL,
+                trashed_with_tab = 0
+            WHERE tab_id IN subtree AND trashed_with_tab = 1
+            "#
+        ))
+        .bind(id)
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| {
+            error!("Failed to restore notes of tab {}: {:#}", id, e);
+            AppError::database("Failed to restore tab. Please try again")
+        })?;
+
+        let mut tabs = Vec::with_capacity(subtree.len());
+        for (tab_id,) in subtree {
+            // Sub-tabs keep their place under the tab; the tab itself goes last among its siblings.
+            let tab = query_as::<_, Tab>(
+                r#"
+                UPDATE tabs
+                SET deleted_at = NULL,
+                    trashed_with_parent = 0,
+                    order_id = CASE WHEN id = ?1 THEN (
+                        SELECT COALESCE(MAX(order_id), 0) + 1 FROM tabs AS live
+                        WHERE live.parent_id IS tabs.parent_id AND live.deleted_at IS NULL
+                    ) ELSE order_id END
+                WHERE id = ?2
+                RETURNING *
+                "#,
+            )
+            .bind(id)
+            .bind(tab_id)
+            .fetch_one(&mut *transaction)
+            .await
+            .map_err(restore_error)?;
+            tabs.push(tab);
+        }
+
+        transaction.commit().await.map_err(|e| {
+            error!("Failed to commit transaction: {:#}", e);
+            AppError::database("Failed to commit transaction")
+        })?;
+
+        Ok(tabs)
+    }
+
+    pub async fn empty(&self) -> Result<(), AppError> {
+        self.purge(None).await.map_err(|e| {
+            error!("Failed to empty trash: {:#}", e);
+            AppError::database("Failed to empty trash. Please try again")
+        })?;
+
+        Ok(())
+    }
+
+    /// Permanently deletes trashed tabs and notes. With `older_than_days` only items that have been
+    /// in the trash for at least that long are removed; without it the whole trash is emptied.
+    pub async fn purge(&self, older_than_days: Option<i64>) -> Result<u64, sqlx::Error> {
+        let cutoff = match older_than_days {
+            Some(days) => format!("-{} days", days),
+            None => "+0 days".to_string(),
+        };
+
+        let mut transaction = self.pool.begin().await?;
+
+        // Trashed tabs take their notes with them through ON DELETE CASCADE.
+        let tabs = sqlx::query(
+            "DELETE FROM tabs WHERE deleted_at IS NOT NULL AND deleted_at <= datetime('now', ?)",
+        )
+        .bind(&cutoff)
+        .execute(&mut *transaction)
+        .await?;
+
+        let notes = sqlx::query(
+            "DELETE FROM notes WHERE deleted_at IS NOT NULL AND deleted_at <= datetime('now', ?)",
+        )
+        .bind(&cutoff)
+        .execute(&mut *transaction)
+        .await?;
+
+        transaction.commit().await?;
+
+        Ok(tabs.rows_affected() + notes.rows_affected())
+    }
+}
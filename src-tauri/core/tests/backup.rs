@@ -0,0 +1,46 @@
+use focusboard_core::backup;
+use focusboard_core::db::{init_db, latest_version};
+use focusboard_core::storage::Storage;
+use focusboard_core::{AppError, NoteService};
+use std::fs;
+
+#[tokio::test]
+async fn backups_can_be_staged_for_restore() {
+    let dir = tempfile::tempdir().unwrap();
+    let storage = Storage::new(dir.path().to_path_buf());
+    fs::create_dir_all(storage.database_dir()).unwrap();
+
+    let pool = init_db(&storage.database_url(), &storage.backup_dir())
+        .await
+        .unwrap();
+    NoteService::new(pool.clone())
+        .create("Keep me".into(), "".into(), None)
+        .await
+        .unwrap();
+
+    let archive = backup::timestamped_path(&storage.backup_dir(), backup::MANUAL_PREFIX);
+    let summary = backup::write_backup(&pool, &archive).await.unwrap();
+    assert!(summary.integrity_ok);
+
+    let info = backup::describe(&archive).await;
+    let stats = info.stats.unwrap();
+    assert_eq!(stats.schema_version, latest_version());
+    assert_eq!(stats.note_count, 1);
+
+    let staged = dir.path().join("staged.db");
+    backup::stage_backup(&archive, &staged).await.unwrap();
+    assert_eq!(backup::read_stats(&staged).await.unwrap().note_count, 1);
+}
+
+#[tokio::test]
+async fn damaged_backups_are_refused() {
+    let dir = tempfile::tempdir().unwrap();
+    let archive = dir.path().join("broken.tar.gz");
+    fs::write(&archive, b"not a backup").unwrap();
+
+    let result = backup::stage_backup(&archive, &dir.path().join("staged.db")).await;
+    assert!(matches!(
+        result,
+        Err(AppError::Validation { .. }) | Err(AppError::Io { .. })
+    ));
+}
@@ -0,0 +1,295 @@
+use focusboard_core::db::open_in_memory;
+use focusboard_core::{AppError, EventService};
+
+const COLOR: &str = "#36A2EB";
+
+#[tokio::test]
+async fn events_are_listed_by_month_and_range() {
+    let events = EventService::new(open_in_memory().await.unwrap());
+
+    let late = events
+        .create(
+            "2026-03-10".into(),
+            "Late".into(),
+            Some(900),
+            Some(1000),
+            COLOR.into(),
+            None,
+        )
+        .await
+        .unwrap();
+    let early = events
+        .create(
+            "2026-03-10".into(),
+            "Early".into(),
+            Some(480),
+            Some(540),
+            COLOR.into(),
+            None,
+        )
+        .await
+        .unwrap();
+    events
+        .create(
+            "2026-04-01".into(),
+            "April".into(),
+            None,
+            None,
+            COLOR.into(),
+            None,
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(late.year_month, "2026-03");
+
+    let march: Vec<_> = events
+        .month("2026-03".into(), None)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|event| event.id)
+        .collect();
+    assert_eq!(march, vec![early.id, late.id]);
+    assert_eq!(
+        events
+            .month("2026-03".into(), Some(true))
+            .await
+            .unwrap()
+            .len(),
+        3
+    );
+
+    let range = events
+        .range("2026-03-01".into(), "2026-03-31".into())
+        .await
+        .unwrap();
+    assert_eq!(range.len(), 2);
+}
+
+#[tokio::test]
+async fn invalid_dates_and_ranges_are_rejected() {
+    let events = EventService::new(open_in_memory().await.unwrap());
+
+    assert!(matches!(
+        events
+            .create(
+                "10/03/2026".into(),
+                "Bad".into(),
+                None,
+                None,
+                COLOR.into(),
+                None
+            )
+            .await,
+        Err(AppError::Validation { .. })
+    ));
+    assert!(matches!(
+        events.range("2026-03-31".into(), "2026-03-01".into()).await,
+        Err(AppError::Validation { .. })
+    ));
+}
+
+#[tokio::test]
+async fn recurring_events_expand_with_their_exceptions() {
+    let events = EventService::new(open_in_memory().await.unwrap());
+    let rule = "FREQ=WEEKLY;COUNT=4".parse().unwrap();
+
+    // 2026-03-02 is a Monday.
+    let standup = events
+        .create(
+            "2026-03-02".into(),
+            "Standup".into(),
+            Some(540),
+            Some(555),
+            COLOR.into(),
+            Some(rule),
+        )
+        .await
+        .unwrap();
+
+    events
+        .update(
+            standup.id,
+            standup.version,
+            "Long standup".into(),
+            Some(540),
+            Some(600),
+            Some("2026-03-09".into()),
+        )
+        .await
+        .unwrap();
+    // Editing the occurrence bumped the series, so a delete from before it is stale.
+    assert!(matches!(
+        events
+            .delete(standup.id, standup.version, Some("2026-03-16".into()))
+            .await,
+        Err(AppError::Conflict { .. })
+    ));
+    let standup = events.get(standup.id).await.unwrap();
+    events
+        .delete(standup.id, standup.version, Some("2026-03-16".into()))
+        .await
+        .unwrap();
+
+    let occurrences = events
+        .range("2026-03-01".into(), "2026-04-30".into())
+        .await
+        .unwrap();
+    let dates: Vec<_> = occurrences.iter().map(|e| e.event_date.as_str()).collect();
+    assert_eq!(dates, vec!["2026-03-02", "2026-03-09", "2026-03-23"]);
+    assert_eq!(occurrences[1].event_name, "Long standup");
+    assert_eq!(occurrences[1].event_end, Some(600));
+    assert_eq!(occurrences[0].event_name, "Standup");
+
+    // Tuesdays are not occurrences of a Monday series.
+    assert!(matches!(
+        events
+            .delete(standup.id, standup.version + 1, Some("2026-03-03".into()))
+            .await,
+        Err(AppError::Validation { .. })
+    ));
+}
+
+#[tokio::test]
+async fn recurring_events_expand_far_from_their_start() {
+    let events = EventService::new(open_in_memory().await.unwrap());
+    for (date, rule) in [
+        ("2026-01-01", "FREQ=DAILY;INTERVAL=2"),
+        ("2026-01-31", "FREQ=MONTHLY"),
+        ("2026-01-05", "FREQ=WEEKLY;INTERVAL=3;BYDAY=MO,FR"),
+    ] {
+        events
+            .create(
+                date.into(),
+                rule.into(),
+                None,
+                None,
+                COLOR.into(),
+                Some(rule.parse().unwrap()),
+            )
+            .await
+            .unwrap();
+    }
+
+    let occurrences = events
+        .range("2100-01-01".into(), "2100-01-31".into())
+        .await
+        .unwrap();
+    let dates = |rule: &str| -> Vec<&str> {
+        occurrences
+            .iter()
+            .filter(|e| e.event_name == rule)
+            .map(|e| e.event_date.as_str())
+            .collect()
+    };
+    // 2100-01-01 is 27,028 days after 2026-01-01.
+    assert_eq!(dates("FREQ=DAILY;INTERVAL=2").len(), 16);
+    assert_eq!(dates("FREQ=DAILY;INTERVAL=2")[0], "2100-01-01");
+    assert_eq!(dates("FREQ=MONTHLY"), vec!["2100-01-31"]);
+    // 2100-01-04 is a Monday, 3,861 weeks after 2026-01-05.
+    assert_eq!(
+        dates("FREQ=WEEKLY;INTERVAL=3;BYDAY=MO,FR"),
+        vec!["2100-01-04", "2100-01-08", "2100-01-25", "2100-01-29"]
+    );
+}
+
+#[tokio::test]
+async fn ics_export_round_trips_and_skips_duplicates() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("calendar.ics");
+
+    let source = EventService::new(open_in_memory().await.unwrap());
+    source
+        .create(
+            "2026-05-01".into(),
+            "Holiday".into(),
+            None,
+            None,
+            COLOR.into(),
+            None,
+        )
+        .await
+        .unwrap();
+    let weekly = source
+        .create(
+            "2026-05-04".into(),
+            "Gym".into(),
+            Some(1080),
+            Some(1140),
+            "#FF6384".into(),
+            Some("FREQ=WEEKLY;COUNT=3".parse().unwrap()),
+        )
+        .await
+        .unwrap();
+    source
+        .delete(weekly.id, weekly.version, Some("2026-05-11".into()))
+        .await
+        .unwrap();
+
+    let exported = source.export_ics(&path).await.unwrap();
+    assert_eq!(exported.event_count, 2);
+
+    let target = EventService::new(open_in_memory().await.unwrap());
+    let imported = target.import_ics(&path).await.unwrap();
+    assert_eq!(imported.imported, 2);
+    assert!(imported.failed.is_empty());
+
+    let dates: Vec<_> = target
+        .range("2026-05-01".into(), "2026-05-31".into())
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|event| event.event_date)
+        .collect();
+    assert_eq!(dates, vec!["2026-05-01", "2026-05-04", "2026-05-18"]);
+
+    let again = target.import_ics(&path).await.unwrap();
+    assert_eq!(again.imported, 0);
+    assert_eq!(again.duplicates, 2);
+}
+
+#[tokio::test]
+async fn editing_an_occurrence_makes_older_copies_of_the_series_stale() {
+    let events = EventService::new(open_in_memory().await.unwrap());
+    let series = events
+        .create(
+            "2026-03-02".into(),
+            "Standup".into(),
+            Some(540),
+            Some(555),
+            COLOR.into(),
+            Some("FREQ=WEEKLY".parse().unwrap()),
+        )
+        .await
+        .unwrap();
+
+    let updated = events
+        .update(
+            series.id,
+            series.version,
+            "Long standup".into(),
+            Some(540),
+            Some(600),
+            Some("2026-03-09".into()),
+        )
+        .await
+        .unwrap();
+    assert_eq!(updated.event_name, "Standup");
+    assert_eq!(updated.version, series.version + 1);
+
+    assert!(matches!(
+        events
+            .update(
+                series.id,
+                series.version,
+                "Daily".into(),
+                Some(540),
+                Some(555),
+                None,
+            )
+            .await,
+        Err(AppError::Conflict { .. })
+    ));
+    assert_eq!(events.get(series.id).await.unwrap().event_name, "Standup");
+}
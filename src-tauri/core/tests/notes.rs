@@ -0,0 +1,318 @@
+use focusboard_core::db::open_in_memory;
+use focusboard_core::diff::DiffKind;
+use focusboard_core::models::Note;
+use focusboard_core::{AppError, NoteService, TabService};
+
+#[tokio::test]
+async fn created_notes_are_appended_to_their_tab() {
+    let pool = open_in_memory().await.unwrap();
+    let tabs = TabService::new(pool.clone());
+    let notes = NoteService::new(pool);
+
+    let tab = tabs.create("Work".into()).await.unwrap();
+    let first = notes
+        .create("First".into(), "<p>one</p>".into(), Some(tab.id))
+        .await
+        .unwrap();
+    let second = notes
+        .create("Second".into(), "<p>two</p>".into(), Some(tab.id))
+        .await
+        .unwrap();
+    let loose = notes.create("Loose".into(), "".into(), None).await.unwrap();
+
+    assert_eq!(first.order_id, Some(1));
+    assert_eq!(second.order_id, Some(2));
+    assert_eq!(loose.order_id, Some(1));
+
+    notes
+        .reorder(Some(tab.id), vec![second.id, first.id])
+        .await
+        .unwrap();
+    let listed: Vec<_> = notes
+        .list()
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|note| note.tab_id == Some(tab.id))
+        .map(|note| note.id)
+        .collect();
+    assert_eq!(listed, vec![second.id, first.id]);
+}
+
+#[tokio::test]
+async fn quick_saves_share_one_revision() {
+    let notes = NoteService::new(open_in_memory().await.unwrap());
+    let note = notes
+        .create("Draft".into(), "<p>v1</p>".into(), None)
+        .await
+        .unwrap();
+
+    let note = notes
+        .update(note.id, note.version, "Draft".into(), "<p>v2</p>".into())
+        .await
+        .unwrap();
+    notes
+        .update(note.id, note.version, "Draft".into(), "<p>v3</p>".into())
+        .await
+        .unwrap();
+
+    // The original text, plus one revision the two saves were folded into.
+    let revisions = notes.revisions(note.id).await.unwrap();
+    assert_eq!(revisions.len(), 2);
+    let latest = notes.revision(revisions[0].id).await.unwrap();
+    assert_eq!(latest.content, "<p>v3</p>");
+
+    let diff = notes
+        .diff_revisions(revisions[1].id, Some(revisions[0].id))
+        .await
+        .unwrap();
+    assert!(diff
+        .iter()
+        .any(|line| line.kind == DiffKind::Delete && line.text == "v1"));
+    assert!(diff
+        .iter()
+        .any(|line| line.kind == DiffKind::Insert && line.text == "v3"));
+}
+
+#[tokio::test]
+async fn restoring_a_revision_records_a_new_one() {
+    let notes = NoteService::new(open_in_memory().await.unwrap());
+    let note = notes
+        .create("Draft".into(), "<p>v1</p>".into(), None)
+        .await
+        .unwrap();
+    let saved = notes
+        .update(note.id, note.version, "Draft".into(), "<p>v2</p>".into())
+        .await
+        .unwrap();
+
+    let original = notes.revisions(note.id).await.unwrap().pop().unwrap();
+    assert!(matches!(
+        notes.restore_revision(original.id, note.version).await,
+        Err(AppError::Conflict { .. })
+    ));
+    let restored = notes
+        .restore_revision(original.id, saved.version)
+        .await
+        .unwrap();
+
+    assert_eq!(restored.content, "<p>v1</p>");
+    assert_eq!(restored.version, saved.version + 1);
+    assert_eq!(notes.revisions(note.id).await.unwrap().len(), 3);
+
+    notes.delete(note.id).await.unwrap();
+    assert!(matches!(
+        notes.restore_revision(original.id, restored.version).await,
+        Err(AppError::NotFound { .. })
+    ));
+    assert!(matches!(
+        notes.diff_revisions(original.id, None).await,
+        Err(AppError::NotFound { .. })
+    ));
+}
+
+#[tokio::test]
+async fn stale_updates_are_rejected_with_the_current_note() {
+    let notes = NoteService::new(open_in_memory().await.unwrap());
+    let opened = notes
+        .create("Draft".into(), "<p>v1</p>".into(), None)
+        .await
+        .unwrap();
+
+    let saved = notes
+        .update(
+            opened.id,
+            opened.version,
+            "Draft".into(),
+            "<p>mine</p>".into(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(saved.version, opened.version + 1);
+
+    let Err(AppError::Conflict { details, .. }) = notes
+        .update(
+            opened.id,
+            opened.version,
+            "Draft".into(),
+            "<p>theirs</p>".into(),
+        )
+        .await
+    else {
+        panic!("expected a conflict");
+    };
+    let current: Note = serde_json::from_value(details.unwrap()).unwrap();
+    assert_eq!(current.content, "<p>mine</p>");
+    assert_eq!(current.version, saved.version);
+
+    // Overwriting means saving again on top of the version the conflict reported.
+    let overwritten = notes
+        .update(
+            opened.id,
+            current.version,
+            "Draft".into(),
+            "<p>theirs</p>".into(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(overwritten.content, "<p>theirs</p>");
+}
+
+#[tokio::test]
+async fn search_matches_words_and_prefixes() {
+    let notes = NoteService::new(open_in_memory().await.unwrap());
+    let groceries = notes
+        .create(
+            "Groceries".into(),
+            "<p>Buy <b>apples</b> and pears</p>".into(),
+            None,
+        )
+        .await
+        .unwrap();
+    notes
+        .create("Meeting".into(), "<p>Quarterly planning</p>".into(), None)
+        .await
+        .unwrap();
+
+    let hits = notes.search("apple*".into(), None, None).await.unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].id, groceries.id);
+    assert!(hits[0].snippet.contains("<mark>apples</mark>"));
+
+    assert!(notes
+        .search("\"pears and\"".into(), None, None)
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(notes
+        .search("   ".into(), None, None)
+        .await
+        .unwrap()
+        .is_empty());
+
+    for limit in [-1, 0, 201] {
+        assert!(matches!(
+            notes.search("apples".into(), None, Some(limit)).await,
+            Err(AppError::Validation { .. })
+        ));
+    }
+
+    notes.delete(groceries.id).await.unwrap();
+    assert!(notes
+        .search("apples".into(), None, None)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn missing_notes_are_not_found() {
+    let notes = NoteService::new(open_in_memory().await.unwrap());
+
+    assert!(matches!(
+        notes.delete(42).await,
+        Err(AppError::NotFound { .. })
+    ));
+    assert!(matches!(
+        notes.revision(42).await,
+        Err(AppError::NotFound { .. })
+    ));
+
+    let trashed = notes.create("Gone".into(), "".into(), None).await.unwrap();
+    assert_eq!(notes.get(trashed.id).await.unwrap().title, "Gone");
+    notes.delete(trashed.id).await.unwrap();
+    assert!(matches!(
+        notes.get(trashed.id).await,
+        Err(AppError::NotFound { .. })
+    ));
+    assert!(matches!(
+        notes
+            .update(trashed.id, trashed.version, "Edited".into(), "".into())
+            .await,
+        Err(AppError::NotFound { .. })
+    ));
+}
+
+#[tokio::test]
+async fn moved_notes_are_renumbered_in_both_tabs() {
+    let pool = open_in_memory().await.unwrap();
+    let tabs = TabService::new(pool.clone());
+    let notes = NoteService::new(pool);
+
+    let work = tabs.create("Work".into()).await.unwrap();
+    let home = tabs.create("Home".into()).await.unwrap();
+    let mut work_notes = Vec::new();
+    for title in ["A", "B", "C"] {
+        let note = notes
+            .create(title.into(), "".into(), Some(work.id))
+            .await
+            .unwrap();
+        work_notes.push(note.id);
+    }
+    let x = notes
+        .create("X".into(), "".into(), Some(home.id))
+        .await
+        .unwrap();
+    let y = notes
+        .create("Y".into(), "".into(), Some(home.id))
+        .await
+        .unwrap();
+
+    let a = notes.get(work_notes[0]).await.unwrap();
+
+    // C then A, between X and Y.
+    let changed = notes
+        .move_notes(vec![work_notes[2], work_notes[0]], Some(home.id), 1)
+        .await
+        .unwrap();
+    assert_eq!(changed.len(), 5);
+
+    let in_tab = |all: &[Note], tab_id: i64| -> Vec<(String, Option<i64>)> {
+        let mut notes: Vec<_> = all.iter().filter(|n| n.tab_id == Some(tab_id)).collect();
+        notes.sort_by_key(|n| n.order_id);
+        notes
+            .into_iter()
+            .map(|n| (n.title.clone(), n.order_id))
+            .collect()
+    };
+    let all = notes.list().await.unwrap();
+    assert_eq!(
+        in_tab(&all, home.id),
+        vec![
+            ("X".into(), Some(1)),
+            ("C".into(), Some(2)),
+            ("A".into(), Some(3)),
+            ("Y".into(), Some(4)),
+        ]
+    );
+    assert_eq!(in_tab(&all, work.id), vec![("B".into(), Some(1))]);
+    assert_eq!(
+        notes.get(a.id).await.unwrap().version,
+        a.version + 1,
+        "changing tabs is an edit"
+    );
+
+    // Past the end appends, and moving within the same tab just reorders it.
+    notes
+        .move_notes(vec![x.id], Some(home.id), 99)
+        .await
+        .unwrap();
+    let all = notes.list().await.unwrap();
+    let titles: Vec<_> = in_tab(&all, home.id).into_iter().map(|(t, _)| t).collect();
+    assert_eq!(titles, vec!["C", "A", "Y", "X"]);
+    assert_eq!(
+        notes.get(y.id).await.unwrap().version,
+        y.version,
+        "moving is not an edit"
+    );
+
+    assert!(matches!(
+        notes.move_notes(vec![x.id], Some(9999), 0).await,
+        Err(AppError::NotFound { .. })
+    ));
+    notes.delete(x.id).await.unwrap();
+    assert!(matches!(
+        notes.move_notes(vec![x.id], Some(work.id), 0).await,
+        Err(AppError::NotFound { .. })
+    ));
+}
@@ -0,0 +1,142 @@
+use focusboard_core::db::open_in_memory;
+use focusboard_core::models::{ChildTabs, TabNode};
+use focusboard_core::{AppError, NoteService, TabService, TrashService};
+
+fn names(nodes: &[TabNode]) -> Vec<String> {
+    nodes
+        .iter()
+        .map(|node| match node.children.as_slice() {
+            [] => node.tab.name.clone(),
+            children => format!("{} [{}]", node.tab.name, names(children).join(", ")),
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn sub_tabs_move_between_parents_without_cycles() {
+    let tabs = TabService::new(open_in_memory().await.unwrap());
+
+    let work = tabs.create("Work".into()).await.unwrap();
+    let home = tabs.create("Home".into()).await.unwrap();
+    let alpha = tabs.create_child(work.id, "Alpha".into()).await.unwrap();
+    let beta = tabs.create_child(work.id, "Beta".into()).await.unwrap();
+    let specs = tabs.create_child(alpha.id, "Specs".into()).await.unwrap();
+    assert_eq!((alpha.order_id, beta.order_id), (Some(1), Some(2)));
+
+    assert_eq!(
+        names(&tabs.tree().await.unwrap()),
+        vec!["Work [Alpha [Specs], Beta]", "Home"]
+    );
+
+    // A tab can't end up inside itself.
+    for parent in [alpha.id, specs.id] {
+        assert!(matches!(
+            tabs.move_to(alpha.id, Some(parent), 0).await,
+            Err(AppError::Validation { .. })
+        ));
+    }
+
+    let moved = tabs.move_to(alpha.id, Some(home.id), 0).await.unwrap();
+    let orders: Vec<_> = moved
+        .iter()
+        .map(|t| (t.id, t.parent_id, t.order_id))
+        .collect();
+    assert_eq!(
+        orders,
+        vec![
+            (alpha.id, Some(home.id), Some(1)),
+            (beta.id, Some(work.id), Some(1))
+        ]
+    );
+
+    tabs.move_to(home.id, None, 0).await.unwrap();
+    assert_eq!(
+        names(&tabs.tree().await.unwrap()),
+        vec!["Home [Alpha [Specs]]", "Work [Beta]"]
+    );
+
+    assert!(matches!(
+        tabs.reorder(vec![beta.id, home.id]).await,
+        Err(AppError::Validation { .. })
+    ));
+    assert!(matches!(
+        tabs.create_child(9999, "Orphan".into()).await,
+        Err(AppError::NotFound { .. })
+    ));
+}
+
+#[tokio::test]
+async fn deleting_a_tab_trashes_or_keeps_its_sub_tabs() {
+    let pool = open_in_memory().await.unwrap();
+    let tabs = TabService::new(pool.clone());
+    let notes = NoteService::new(pool.clone());
+    let trash = TrashService::new(pool);
+
+    let work = tabs.create("Work".into()).await.unwrap();
+    let alpha = tabs.create_child(work.id, "Alpha".into()).await.unwrap();
+    let specs = tabs.create_child(alpha.id, "Specs".into()).await.unwrap();
+    notes
+        .create("Plan".into(), "".into(), Some(alpha.id))
+        .await
+        .unwrap();
+    notes
+        .create("Spec".into(), "".into(), Some(specs.id))
+        .await
+        .unwrap();
+
+    // Trashed with its sub-tabs, it is listed once, counting every note that went with it.
+    let renumbered = tabs.delete(alpha.id, ChildTabs::Trash).await.unwrap();
+    assert!(renumbered.is_empty());
+    assert_eq!(names(&tabs.tree().await.unwrap()), vec!["Work"]);
+    assert!(notes.list().await.unwrap().is_empty());
+
+    let listed = trash.list().await.unwrap();
+    assert_eq!(listed.tabs.len(), 1);
+    assert_eq!(listed.tabs[0].note_count, 2);
+    assert!(matches!(
+        trash.restore_tab(specs.id).await,
+        Err(AppError::Conflict { .. })
+    ));
+
+    let restored = trash.restore_tab(alpha.id).await.unwrap();
+    let ids: Vec<_> = restored.iter().map(|t| t.id).collect();
+    assert_eq!(ids, vec![alpha.id, specs.id]);
+    assert_eq!(
+        names(&tabs.tree().await.unwrap()),
+        vec!["Work [Alpha [Specs]]"]
+    );
+    assert_eq!(notes.list().await.unwrap().len(), 2);
+
+    // Kept, the sub-tabs move up a level with their notes.
+    let renumbered = tabs.delete(alpha.id, ChildTabs::MoveUp).await.unwrap();
+    assert_eq!(renumbered.len(), 1);
+    assert_eq!(renumbered[0].parent_id, Some(work.id));
+    assert_eq!(names(&tabs.tree().await.unwrap()), vec!["Work [Specs]"]);
+    let left: Vec<_> = notes
+        .list()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|n| n.title)
+        .collect();
+    assert_eq!(left, vec!["Spec"]);
+
+    // The level the tab leaves is renumbered without a gap.
+    tabs.create("Inbox".into()).await.unwrap();
+    tabs.create_child(work.id, "Notes".into()).await.unwrap();
+    let renumbered = tabs.delete(work.id, ChildTabs::MoveUp).await.unwrap();
+    let orders: Vec<_> = renumbered
+        .iter()
+        .map(|tab| (tab.name.as_str(), tab.order_id))
+        .collect();
+    assert_eq!(
+        orders,
+        vec![("Inbox", Some(1)), ("Specs", Some(2)), ("Notes", Some(3))]
+    );
+
+    // Tabs in the trash can't be renamed.
+    assert!(matches!(
+        tabs.rename(work.id, "Job".into()).await,
+        Err(AppError::NotFound { .. })
+    ));
+}
@@ -0,0 +1,175 @@
+use focusboard_core::db::open_in_memory;
+use focusboard_core::models::{Tag, TagMatch};
+use focusboard_core::{AppError, NoteService, TabService, TagService};
+
+fn names(tags: &[Tag]) -> Vec<&str> {
+    tags.iter().map(|tag| tag.name.as_str()).collect()
+}
+
+#[tokio::test]
+async fn notes_are_filtered_by_all_or_any_of_their_tags() {
+    let pool = open_in_memory().await.unwrap();
+    let tabs = TabService::new(pool.clone());
+    let notes = NoteService::new(pool.clone());
+    let tags = TagService::new(pool);
+
+    let work = tabs.create("Work".into()).await.unwrap();
+    let home = tabs.create("Home".into()).await.unwrap();
+    let both = notes
+        .create("Both".into(), "".into(), Some(home.id))
+        .await
+        .unwrap();
+    let urgent_only = notes
+        .create("Urgent only".into(), "".into(), Some(work.id))
+        .await
+        .unwrap();
+    notes
+        .create("Untagged".into(), "".into(), Some(work.id))
+        .await
+        .unwrap();
+
+    let urgent = tags.create("urgent".into()).await.unwrap();
+    let later = tags.create("Later".into()).await.unwrap();
+    tags.attach(both.id, urgent.id).await.unwrap();
+    let both_tags = tags.attach(both.id, later.id).await.unwrap();
+    tags.attach(urgent_only.id, urgent.id).await.unwrap();
+    // Tagging twice is a no-op.
+    tags.attach(urgent_only.id, urgent.id).await.unwrap();
+
+    assert_eq!(names(&both_tags), vec!["Later", "urgent"]);
+
+    let all: Vec<_> = tags
+        .notes(vec![urgent.id, later.id], TagMatch::All)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|note| note.id)
+        .collect();
+    assert_eq!(all, vec![both.id]);
+
+    // In tab order: Work comes before Home.
+    let any: Vec<_> = tags
+        .notes(vec![urgent.id, later.id], TagMatch::Any)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|note| note.id)
+        .collect();
+    assert_eq!(any, vec![urgent_only.id, both.id]);
+
+    let usage: Vec<_> = tags
+        .list()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|tag| (tag.name, tag.note_count))
+        .collect();
+    assert_eq!(usage, vec![("Later".into(), 1), ("urgent".into(), 2)]);
+
+    // Trashed notes neither match nor count.
+    notes.delete(urgent_only.id).await.unwrap();
+    let any = tags.notes(vec![urgent.id], TagMatch::Any).await.unwrap();
+    assert_eq!(any.len(), 1);
+    let urgent_usage = tags.list().await.unwrap();
+    assert_eq!(urgent_usage[1].note_count, 1);
+
+    let left = tags.detach(both.id, urgent.id).await.unwrap();
+    assert_eq!(names(&left), vec!["Later"]);
+}
+
+#[tokio::test]
+async fn tagged_notes_follow_the_tab_tree() {
+    let pool = open_in_memory().await.unwrap();
+    let tabs = TabService::new(pool.clone());
+    let notes = NoteService::new(pool.clone());
+    let tags = TagService::new(pool);
+
+    let work = tabs.create("Work".into()).await.unwrap();
+    let home = tabs.create("Home".into()).await.unwrap();
+    let alpha = tabs.create_child(work.id, "Alpha".into()).await.unwrap();
+    let urgent = tags.create("urgent".into()).await.unwrap();
+
+    // Every note is first in its tab, and Alpha is first among Work's sub-tabs.
+    for tab in [&home, &alpha, &work] {
+        let note = notes
+            .create(tab.name.clone(), "".into(), Some(tab.id))
+            .await
+            .unwrap();
+        tags.attach(note.id, urgent.id).await.unwrap();
+    }
+
+    let titles: Vec<_> = tags
+        .notes(vec![urgent.id], TagMatch::Any)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|note| note.title)
+        .collect();
+    assert_eq!(titles, vec!["Work", "Alpha", "Home"]);
+}
+
+#[tokio::test]
+async fn names_are_unique_ignoring_case() {
+    let tags = TagService::new(open_in_memory().await.unwrap());
+
+    let work = tags.create("  Work ".into()).await.unwrap();
+    assert_eq!(work.name, "Work");
+
+    let taken = tags.create("work".into()).await.unwrap_err();
+    let AppError::Conflict { details, .. } = taken else {
+        panic!("expected a conflict, got {taken:?}");
+    };
+    assert_eq!(details.unwrap()["id"], work.id);
+
+    let home = tags.create("Home".into()).await.unwrap();
+    assert!(matches!(
+        tags.rename(home.id, "WORK".into()).await,
+        Err(AppError::Conflict { .. })
+    ));
+    // Changing only the case of its own name is fine.
+    let work = tags.rename(work.id, "WORK".into()).await.unwrap();
+    assert_eq!(work.name, "WORK");
+
+    assert!(matches!(
+        tags.create(" ".into()).await,
+        Err(AppError::Validation { .. })
+    ));
+}
+
+#[tokio::test]
+async fn merging_moves_notes_onto_the_surviving_tag() {
+    let pool = open_in_memory().await.unwrap();
+    let notes = NoteService::new(pool.clone());
+    let tags = TagService::new(pool);
+
+    let first = notes.create("First".into(), "".into(), None).await.unwrap();
+    let second = notes
+        .create("Second".into(), "".into(), None)
+        .await
+        .unwrap();
+    let todo = tags.create("todo".into()).await.unwrap();
+    let tasks = tags.create("tasks".into()).await.unwrap();
+    tags.attach(first.id, todo.id).await.unwrap();
+    tags.attach(first.id, tasks.id).await.unwrap();
+    tags.attach(second.id, todo.id).await.unwrap();
+
+    let merged = tags.merge(todo.id, tasks.id).await.unwrap();
+    assert_eq!(merged.id, tasks.id);
+
+    let usage = tags.list().await.unwrap();
+    assert_eq!(usage.len(), 1);
+    assert_eq!(usage[0].note_count, 2);
+    assert!(matches!(
+        tags.get(todo.id).await,
+        Err(AppError::NotFound { .. })
+    ));
+    assert!(matches!(
+        tags.merge(tasks.id, tasks.id).await,
+        Err(AppError::Validation { .. })
+    ));
+
+    // Deleting a tag leaves its notes alone.
+    tags.delete(tasks.id).await.unwrap();
+    assert!(tags.for_note(first.id).await.unwrap().is_empty());
+    assert_eq!(notes.list().await.unwrap().len(), 2);
+}
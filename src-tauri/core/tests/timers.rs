@@ -0,0 +1,18 @@
+use focusboard_core::db::open_in_memory;
+use focusboard_core::{AppError, TimerService};
+
+#[tokio::test]
+async fn there_is_only_ever_one_timer() {
+    let timers = TimerService::new(open_in_memory().await.unwrap());
+
+    assert!(matches!(timers.get().await, Err(AppError::NotFound { .. })));
+
+    timers.save(1500, 1500, "Focus".into()).await.unwrap();
+    let saved = timers.save(300, 120, "Break".into()).await.unwrap();
+
+    assert_eq!(saved.id, 1);
+    let timer = timers.get().await.unwrap();
+    assert_eq!(timer.initial_duration, 300);
+    assert_eq!(timer.duration, 120);
+    assert_eq!(timer.message, "Break");
+}
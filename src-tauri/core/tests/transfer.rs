@@ -0,0 +1,60 @@
+use focusboard_core::db::open_in_memory;
+use focusboard_core::{NoteService, TabService, TransferService};
+use std::fs;
+
+#[tokio::test]
+async fn markdown_export_imports_back_into_new_tabs() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let pool = open_in_memory().await.unwrap();
+    let tabs = TabService::new(pool.clone());
+    let notes = NoteService::new(pool.clone());
+    let tab = tabs.create("Recipes".into()).await.unwrap();
+    notes
+        .create(
+            "Pancakes".into(),
+            "<p>Mix <strong>well</strong></p>".into(),
+            Some(tab.id),
+        )
+        .await
+        .unwrap();
+    notes
+        .create("Waffles".into(), "<p>Crispy</p>".into(), Some(tab.id))
+        .await
+        .unwrap();
+
+    let exported = TransferService::new(pool)
+        .export_markdown(dir.path())
+        .await
+        .unwrap();
+    assert_eq!(exported.tab_count, 1);
+    assert_eq!(exported.note_count, 2);
+    let pancakes = fs::read_to_string(
+        std::path::Path::new(&exported.path)
+            .join("Recipes")
+            .join("Pancakes.md"),
+    )
+    .unwrap();
+    assert!(pancakes.contains("**well**"));
+
+    let target = open_in_memory().await.unwrap();
+    let imported = TransferService::new(target.clone())
+        .import_folder(std::path::Path::new(&exported.path))
+        .await
+        .unwrap();
+    assert_eq!(imported.tabs_created, 1);
+    assert_eq!(imported.notes_imported, 2);
+
+    let titles: Vec<_> = NoteService::new(target.clone())
+        .list()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|note| note.title)
+        .collect();
+    assert_eq!(titles, vec!["Pancakes", "Waffles"]);
+    assert_eq!(
+        TabService::new(target).list().await.unwrap()[0].name,
+        "Recipes"
+    );
+}
@@ -0,0 +1,86 @@
+use focusboard_core::db::open_in_memory;
+use focusboard_core::models::ChildTabs;
+use focusboard_core::{AppError, NoteService, TabService, TrashService};
+
+#[tokio::test]
+async fn deleting_a_tab_trashes_its_notes_with_it() {
+    let pool = open_in_memory().await.unwrap();
+    let tabs = TabService::new(pool.clone());
+    let notes = NoteService::new(pool.clone());
+    let trash = TrashService::new(pool);
+
+    let tab = tabs.create("Ideas".into()).await.unwrap();
+    let note = notes
+        .create("Idea".into(), "".into(), Some(tab.id))
+        .await
+        .unwrap();
+
+    tabs.delete(tab.id, ChildTabs::Trash).await.unwrap();
+    assert!(tabs.list().await.unwrap().is_empty());
+    assert!(notes.list().await.unwrap().is_empty());
+
+    let listed = trash.list().await.unwrap();
+    assert_eq!(listed.tabs.len(), 1);
+    assert_eq!(listed.tabs[0].note_count, 1);
+    // Notes trashed along with their tab are listed under it, not on their own.
+    assert!(listed.notes.is_empty());
+
+    // The note can't come back without its tab.
+    assert!(matches!(
+        trash.restore_note(note.id).await,
+        Err(AppError::NotFound { .. })
+    ));
+
+    trash.restore_tab(tab.id).await.unwrap();
+    assert_eq!(notes.list().await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn a_note_in_a_trashed_tab_cannot_be_restored_alone() {
+    let pool = open_in_memory().await.unwrap();
+    let tabs = TabService::new(pool.clone());
+    let notes = NoteService::new(pool.clone());
+    let trash = TrashService::new(pool);
+
+    let tab = tabs.create("Ideas".into()).await.unwrap();
+    let note = notes
+        .create("Idea".into(), "".into(), Some(tab.id))
+        .await
+        .unwrap();
+    notes.delete(note.id).await.unwrap();
+    tabs.delete(tab.id, ChildTabs::Trash).await.unwrap();
+
+    assert!(matches!(
+        trash.restore_note(note.id).await,
+        Err(AppError::Conflict { .. })
+    ));
+}
+
+#[tokio::test]
+async fn emptying_the_trash_deletes_for_good() {
+    let pool = open_in_memory().await.unwrap();
+    let tabs = TabService::new(pool.clone());
+    let notes = NoteService::new(pool.clone());
+    let trash = TrashService::new(pool);
+
+    let tab = tabs.create("Old".into()).await.unwrap();
+    notes
+        .create("In tab".into(), "".into(), Some(tab.id))
+        .await
+        .unwrap();
+    let loose = notes.create("Loose".into(), "".into(), None).await.unwrap();
+    tabs.delete(tab.id, ChildTabs::Trash).await.unwrap();
+    notes.delete(loose.id).await.unwrap();
+
+    // Nothing has been in the trash for a day yet.
+    assert_eq!(trash.purge(Some(1)).await.unwrap(), 0);
+
+    trash.empty().await.unwrap();
+    let listed = trash.list().await.unwrap();
+    assert!(listed.tabs.is_empty());
+    assert!(listed.notes.is_empty());
+    assert!(matches!(
+        trash.restore_note(loose.id).await,
+        Err(AppError::NotFound { .. })
+    ));
+}
@@ -0,0 +1,89 @@
+use crate::changes::{EventChanged, NoteChanged, TabChanged, TagChanged};
+use crate::commands;
+use crate::shutdown::AppClosing;
+use tauri::Wry;
+use tauri_specta::{collect_commands, collect_events, Builder, ErrorHandlingMode};
+
+/// Generated TypeScript types, typed `invoke` wrappers for every command and typed listeners for
+/// every event, written on each debug run so the frontend fails to type-check when it drifts
+/// from the Rust side.
+#[cfg(debug_assertions)]
+const BINDINGS_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../src/types/bindings.ts");
+
+pub fn builder() -> Builder<Wry> {
+    Builder::<Wry>::new()
+        // Commands reject with an `AppError`, so callers keep using try/catch.
+        .error_handling(ErrorHandlingMode::Throw)
+        .commands(collect_commands![
+            commands::get_notes,
+            commands::create_note,
+            commands::update_note,
+            commands::delete_note,
+            commands::list_note_revisions,
+            commands::get_note_revision,
+            commands::diff_note_revisions,
+            commands::restore_note_revision,
+            commands::search_notes,
+            commands::get_tabs,
+            commands::get_tab_tree,
+            commands::create_tab,
+            commands::create_sub_tab,
+            commands::update_tab,
+            commands::delete_tab,
+            commands::get_tags,
+            commands::get_note_tags,
+            commands::get_notes_by_tags,
+            commands::create_tag,
+            commands::rename_tag,
+            commands::merge_tags,
+            commands::delete_tag,
+            commands::tag_note,
+            commands::untag_note,
+            commands::list_trash,
+            commands::restore_note,
+            commands::restore_tab,
+            commands::empty_trash,
+            commands::backup_database,
+            commands::list_backups,
+            commands::restore_backup,
+            commands::get_data_directory,
+            commands::move_data_directory,
+            commands::export_markdown,
+            commands::import_folder,
+            commands::reorder_notes,
+            commands::move_notes,
+            commands::reorder_tabs,
+            commands::move_tab,
+            commands::create_timer,
+            commands::get_timer,
+            commands::insert_event,
+            commands::get_events,
+            commands::get_events_in_range,
+            commands::update_event,
+            commands::set_event_recurrence,
+            commands::delete_event,
+            commands::export_events_ics,
+            commands::import_events_ics,
+            commands::open_note_window,
+            commands::open_tab_window,
+            commands::register_pending_work,
+            commands::complete_pending_work,
+        ])
+        .events(collect_events![
+            NoteChanged,
+            TabChanged,
+            TagChanged,
+            EventChanged,
+            AppClosing
+        ])
+}
+
+#[cfg(debug_assertions)]
+pub fn export(builder: &Builder<Wry>) -> Result<(), specta_typescript::ExportError> {
+    builder.export(
+        // IDs and counts stay well below 2^53, so they are plain numbers on the frontend.
+        specta_typescript::Typescript::default()
+            .bigint(specta_typescript::BigIntExportBehavior::Number),
+        BINDINGS_PATH,
+    )
+}
@@ -0,0 +1,109 @@
+use focusboard_core::models::{CalendarEvent, Note, Tab, Tag};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use tauri::AppHandle;
+use tauri_specta::Event;
+
+/// Sent as `note-changed` to every window after a command changes notes, so each view can
+/// patch its list instead of fetching all notes again.
+#[derive(Serialize, Deserialize, Debug, Clone, Type, Event)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum NoteChanged {
+    Created {
+        note: Note,
+    },
+    Updated {
+        note: Note,
+    },
+    /// Moved to the trash.
+    Deleted {
+        id: i64,
+    },
+    /// Brought back from the trash.
+    Restored {
+        note: Note,
+    },
+    /// The notes of `tab_id`, in their new order.
+    Reordered {
+        tab_id: Option<i64>,
+        note_ids: Vec<i64>,
+    },
+    /// Notes moved to another tab, with every note whose tab or position changed on the way.
+    Moved {
+        notes: Vec<Note>,
+    },
+    /// Too many notes changed to list, e.g. after an import or a backup restore. Load them again.
+    Reset,
+}
+
+/// Sent as `tab-changed` after a command changes tabs. Trashing or restoring a tab also moves
+/// its notes, which is sent as its own `note-changed`. `Deleted` takes along whatever sub-tabs
+/// the tab still has; sub-tabs restored with a tab are sent as `Restored` one by one. `Moved`
+/// lists every tab whose parent or position changed.
+#[derive(Serialize, Deserialize, Debug, Clone, Type, Event)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TabChanged {
+    Created { tab: Tab },
+    Updated { tab: Tab },
+    Deleted { id: i64 },
+    Restored { tab: Tab },
+    Reordered { tab_ids: Vec<i64> },
+    Moved { tabs: Vec<Tab> },
+    Reset,
+}
+
+/// Sent as `tag-changed` after a command changes tags or which notes carry them. Usage counts
+/// aren't sent; they follow from `NoteTagged` and the notes in the trash.
+#[derive(Serialize, Deserialize, Debug, Clone, Type, Event)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TagChanged {
+    Created {
+        tag: Tag,
+    },
+    Updated {
+        tag: Tag,
+    },
+    /// `from_id` is gone; its notes now carry `tag`.
+    Merged {
+        from_id: i64,
+        tag: Tag,
+    },
+    Deleted {
+        id: i64,
+    },
+    /// Every tag the note now carries.
+    NoteTagged {
+        note_id: i64,
+        tag_ids: Vec<i64>,
+    },
+    Reset,
+}
+
+/// Sent as `event-changed` after a command changes calendar events. `event` is always the
+/// stored event, so editing or deleting one occurrence of a recurring event sends the series as
+/// `Updated`.
+#[derive(Serialize, Deserialize, Debug, Clone, Type, Event)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum EventChanged {
+    Created { event: CalendarEvent },
+    Updated { event: CalendarEvent },
+    Deleted { id: i64 },
+    Reset,
+}
+
+/// Sends `change` to every window. The change itself is already saved, so a failed emit is only
+/// logged; the other windows catch up on their next load.
+pub fn notify<E: Event + Serialize + Clone>(app: &AppHandle, change: E) {
+    if let Err(e) = change.emit(app) {
+        warn!("Failed to emit {}: {:#}", E::NAME, e);
+    }
+}
+
+/// Tells every window to load everything again, after the whole database was swapped out.
+pub fn notify_reset(app: &AppHandle) {
+    notify(app, NoteChanged::Reset);
+    notify(app, TabChanged::Reset);
+    notify(app, TagChanged::Reset);
+    notify(app, EventChanged::Reset);
+}
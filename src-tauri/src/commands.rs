@@ -1,568 +1,955 @@
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 
-use dirs::data_local_dir;
+use crate::changes::{self, EventChanged, NoteChanged, TabChanged, TagChanged};
+use crate::db::Database;
+use crate::dialogs;
+use crate::settings;
+use crate::shutdown::Shutdown;
+use crate::storage::{self, DataDirectory};
+use crate::windows;
+use focusboard_core::backup::{self, BackupInfo, BackupSummary};
+use focusboard_core::db;
+use focusboard_core::diff::DiffLine;
+use focusboard_core::html;
+use focusboard_core::models::{
+    CalendarEvent, ChildTabs, FolderImportSummary, IcsExportSummary, IcsImportSummary,
+    MarkdownExportSummary, Note, NoteRevision, NoteRevisionSummary, NoteTag, SearchHit, Tab,
+    TabNode, Tag, TagMatch, TagUsage, Timer, Trash,
+};
+use focusboard_core::recurrence::RecurrenceRule;
+use focusboard_core::storage::{copy_dir_contents, Storage};
+use focusboard_core::AppError;
 use log::{error, info};
-use serde::{Deserialize, Serialize};
-use sqlx::{query_as, FromRow, SqlitePool};
-use std::fs::{copy, create_dir, read_dir};
-use std::io::ErrorKind;
-use std::path::PathBuf;
-use tauri::State;
-use time::{macros::format_description, OffsetDateTime};
-
-#[derive(FromRow, Serialize, Deserialize, Debug, Clone)]
-pub struct Note {
-    pub id: i64,
-    pub title: String,
-    pub content: String,
-    pub tab_id: Option<i64>,
-    pub order_id: Option<i64>,
-    pub created_at: String,
-    pub updated_at: String,
-}
-
-#[derive(FromRow, Serialize, Deserialize, Debug, Clone)]
-pub struct Tab {
-    pub id: i64,
-    pub name: String,
-    pub order_id: Option<i64>,
-    pub created_at: String,
-    pub updated_at: String,
-}
-
-#[derive(FromRow, Serialize, Deserialize, Debug, Clone)]
-pub struct Timer {
-    pub id: i64,
-    pub initial_duration: i32,
-    pub duration: i32,
-    pub message: String,
-}
-
-#[derive(FromRow, Serialize, Deserialize, Debug, Clone)]
-pub struct CalendarEvent {
-    pub id: i64,
-    pub event_date: String,
-    pub year_month: String,
-    pub event_name: String,
-    pub event_start: Option<i32>,
-    pub event_end: Option<i32>,
-    pub color: String,
-}
-
-#[tauri::command]
-pub async fn get_notes(
-    pool: State<'_, SqlitePool>,
-) -> Result<Vec<Note>, String> {
-    let notes = query_as::<_, Note>(
-        r#"
-        SELECT * FROM notes
-        ORDER BY order_id ASC
-        "#,
-    )
-    .fetch_all(&*pool)
-    .await
-    .map_err(|e| {
-        error!("Failed to fetch notes: {:#}", e);
-        "Failed to load notes. Please try again.".to_string()
-    })?;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, State, Window};
 
-    Ok(notes)
+#[tauri::command]
+#[specta::specta]
+pub async fn get_notes(db: State<'_, Database>) -> Result<Vec<Note>, AppError> {
+    db.notes().list().await
 }
 
 #[tauri::command]
+#[specta::specta]
 pub async fn create_note(
-    pool: State<'_, SqlitePool>,
+    app: AppHandle,
+    db: State<'_, Database>,
     title: String,
     content: String,
     tab_id: Option<i64>,
-) -> Result<Note, String> {
-    let row: Option<(Option<i64>,)> = sqlx::query_as(
-        r#"
-        SELECT MAX(order_id) FROM notes WHERE tab_id IS NOT DISTINCT FROM ?
-        "#,
-    )
-    .bind(tab_id)
-    .fetch_optional(&*pool)
-    .await
-    .map_err(|e| {
-        error!("Database error: {:#}", e);
-        e.to_string()
-    })?;
-
-    let max_order = row.and_then(|r| r.0).unwrap_or(0);
-    let new_order = max_order + 1;
-
-    let note = query_as::<_, Note>(
-        r#"
-        INSERT INTO notes (title, content, tab_id, order_id)
-        VALUES (?, ?, ?, ?)
-        RETURNING id, title, content, tab_id, order_id, created_at, updated_at
-        "#,
-    )
-    .bind(&title)
-    .bind(content)
-    .bind(tab_id)
-    .bind(new_order)
-    .fetch_one(&*pool)
-    .await
-    .map_err(|e| {
-        error!("Failed to create note {}: {:#}", title, e);
-        "Failed to create note. Please try again".to_string()
-    })?;
-
+) -> Result<Note, AppError> {
+    let note = db.notes().create(title, content, tab_id).await?;
+    changes::notify(&app, NoteChanged::Created { note: note.clone() });
     Ok(note)
 }
 
 #[tauri::command]
+#[specta::specta]
 pub async fn update_note(
-    pool: State<'_, SqlitePool>,
+    app: AppHandle,
+    db: State<'_, Database>,
     id: i64,
+    version: i64,
     title: String,
     content: String,
-) -> Result<(), String> {
-    sqlx::query(
-        r#"
-        UPDATE notes
-        SET title = ?,
-            content = ?,
-            updated_at = datetime('now')
-        WHERE id = ?
-        "#,
-    )
-    .bind(title)
-    .bind(content)
-    .bind(id)
-    .execute(&*pool)
-    .await
-    .map_err(|e| {
-        error!("Failed to update note {}: {:#}", id, e);
-        "Failed to update note. Please try again".to_string()
-    })?;
+) -> Result<Note, AppError> {
+    let note = db.notes().update(id, version, title, content).await?;
+    changes::notify(&app, NoteChanged::Updated { note: note.clone() });
+    Ok(note)
+}
 
-    Ok(())
+#[tauri::command]
+#[specta::specta]
+pub async fn list_note_revisions(
+    db: State<'_, Database>,
+    note_id: i64,
+) -> Result<Vec<NoteRevisionSummary>, AppError> {
+    db.notes().revisions(note_id).await
 }
 
 #[tauri::command]
-pub async fn delete_note(pool: State<'_, SqlitePool>, id: i64) -> Result<(), String> {
-    let result = sqlx::query("DELETE FROM notes WHERE id = ?")
-        .bind(id)
-        .execute(&*pool)
-        .await
-        .map_err(|e| {
-            error!("Failed to delete note {}: {:#}", id, e);
-            "Failed to delete note. Please try again".to_string()
-        })?;
+#[specta::specta]
+pub async fn get_note_revision(
+    db: State<'_, Database>,
+    id: i64,
+) -> Result<NoteRevision, AppError> {
+    db.notes().revision(id).await
+}
 
-    if result.rows_affected() == 0 {
-        return Err(format!("Note with id {id} not found"));
-    }
+/// Diffs the plain text of two revisions. Without `to_id` the revision is compared against the
+/// note as it is now.
+#[tauri::command]
+#[specta::specta]
+pub async fn diff_note_revisions(
+    db: State<'_, Database>,
+    from_id: i64,
+    to_id: Option<i64>,
+) -> Result<Vec<DiffLine>, AppError> {
+    db.notes().diff_revisions(from_id, to_id).await
+}
 
+#[tauri::command]
+#[specta::specta]
+pub async fn restore_note_revision(
+    app: AppHandle,
+    db: State<'_, Database>,
+    id: i64,
+    version: i64,
+) -> Result<Note, AppError> {
+    let note = db.notes().restore_revision(id, version).await?;
+    changes::notify(&app, NoteChanged::Updated { note: note.clone() });
+    Ok(note)
+}
+
+#[tauri::command]
+#[specta::specta]
+pub async fn delete_note(app: AppHandle, db: State<'_, Database>, id: i64) -> Result<(), AppError> {
+    db.notes().delete(id).await?;
+    changes::notify(&app, NoteChanged::Deleted { id });
     Ok(())
 }
 
 #[tauri::command]
-pub async fn get_tabs(pool: State<'_, SqlitePool>) -> Result<Vec<Tab>, String> {
-    let tabs = query_as::<_, Tab>(
-        r#"
-        SELECT * FROM tabs
-        ORDER BY order_id ASC
-        "#,
-    )
-    .fetch_all(&*pool)
-    .await
-    .map_err(|e| {
-        error!("Failed to fetch tabs: {:#}", e);
-        "Failed to fetch tabs. Please try again".to_string()
-    })?;
+#[specta::specta]
+pub async fn search_notes(
+    db: State<'_, Database>,
+    query: String,
+    tab_id: Option<i64>,
+    limit: Option<i64>,
+) -> Result<Vec<SearchHit>, AppError> {
+    db.notes().search(query, tab_id, limit).await
+}
 
-    Ok(tabs)
+#[tauri::command]
+#[specta::specta]
+pub async fn get_tabs(db: State<'_, Database>) -> Result<Vec<Tab>, AppError> {
+    db.tabs().list().await
 }
 
+/// Every tab outside the trash, nested under its parent.
 #[tauri::command]
-pub async fn create_tab(pool: State<'_, SqlitePool>, name: String) -> Result<Tab, String> {
-    let row: Option<(Option<i64>,)> = sqlx::query_as(
-        r#"
-        SELECT MAX(order_id) FROM tabs
-        "#,
-    )
-    .fetch_optional(&*pool)
-    .await
-    .map_err(|e| {
-        error!("Database error: {:#}", e);
-        "Database error".to_string()
-    })?;
+#[specta::specta]
+pub async fn get_tab_tree(db: State<'_, Database>) -> Result<Vec<TabNode>, AppError> {
+    db.tabs().tree().await
+}
 
-    let max_order = row.and_then(|r| r.0).unwrap_or(0);
-    let new_order = max_order + 1;
-
-    let tab = query_as::<_, Tab>(
-        r#"
-        INSERT INTO tabs (name, order_id)
-        VALUES (?, ?)
-        RETURNING id, name, order_id, created_at, updated_at
-        "#,
-    )
-    .bind(&name)
-    .bind(new_order)
-    .fetch_one(&*pool)
-    .await
-    .map_err(|e| {
-        error!("Failed to create tab {}: {:#}", name, e);
-        "Failed to create tab. Please try again".to_string()
-    })?;
+#[tauri::command]
+#[specta::specta]
+pub async fn create_tab(
+    app: AppHandle,
+    db: State<'_, Database>,
+    name: String,
+) -> Result<Tab, AppError> {
+    let tab = db.tabs().create(name).await?;
+    changes::notify(&app, TabChanged::Created { tab: tab.clone() });
+    Ok(tab)
+}
 
+#[tauri::command]
+#[specta::specta]
+pub async fn create_sub_tab(
+    app: AppHandle,
+    db: State<'_, Database>,
+    parent_id: i64,
+    name: String,
+) -> Result<Tab, AppError> {
+    let tab = db.tabs().create_child(parent_id, name).await?;
+    changes::notify(&app, TabChanged::Created { tab: tab.clone() });
     Ok(tab)
 }
 
 #[tauri::command]
-pub async fn update_tab(pool: State<'_, SqlitePool>, id: i64, name: String) -> Result<(), String> {
-    sqlx::query(
-        r#"
-        UPDATE tabs
-        SET name = ?,
-            updated_at = datetime('now')
-        WHERE id = ?
-        "#,
-    )
-    .bind(name)
-    .bind(id)
-    .execute(&*pool)
-    .await
-    .map_err(|e| {
-        error!("Failed to update tab {}: {:#}", id, e);
-        "Failed to update tab. Please try again".to_string()
-    })?;
+#[specta::specta]
+pub async fn update_tab(
+    app: AppHandle,
+    db: State<'_, Database>,
+    id: i64,
+    name: String,
+) -> Result<Tab, AppError> {
+    let tab = db.tabs().rename(id, name).await?;
+    changes::notify(&app, TabChanged::Updated { tab: tab.clone() });
+    Ok(tab)
+}
 
+/// Moves the tab and its notes to the trash. `children` says whether its sub-tabs go with it or
+/// move up a level.
+#[tauri::command]
+#[specta::specta]
+pub async fn delete_tab(
+    app: AppHandle,
+    db: State<'_, Database>,
+    id: i64,
+    children: ChildTabs,
+) -> Result<(), AppError> {
+    let renumbered = db.tabs().delete(id, children).await?;
+    if !renumbered.is_empty() {
+        changes::notify(&app, TabChanged::Moved { tabs: renumbered });
+    }
+    changes::notify(&app, TabChanged::Deleted { id });
+    changes::notify(&app, NoteChanged::Reset);
     Ok(())
 }
 
+/// Every tag with the number of notes outside the trash that carry it.
 #[tauri::command]
-pub async fn delete_tab(pool: State<'_, SqlitePool>, id: i64) -> Result<(), String> {
-    let result = sqlx::query("DELETE FROM tabs WHERE id = ?")
-        .bind(id)
-        .execute(&*pool)
-        .await
-        .map_err(|e| {
-            error!("Failed to delete tab {}: {:#}", id, e);
-            "Failed to delete tab. Please try again".to_string()
-        })?;
+#[specta::specta]
+pub async fn get_tags(db: State<'_, Database>) -> Result<Vec<TagUsage>, AppError> {
+    db.tags().list().await
+}
 
-    if result.rows_affected() == 0 {
-        return Err(format!("Tab with id {id} not found"));
-    }
+/// Which note carries which tag, for showing the tags of every note at once.
+#[tauri::command]
+#[specta::specta]
+pub async fn get_note_tags(db: State<'_, Database>) -> Result<Vec<NoteTag>, AppError> {
+    db.tags().assignments().await
+}
 
+/// Notes carrying all of `tag_ids`, or any one of them, across every tab.
+#[tauri::command]
+#[specta::specta]
+pub async fn get_notes_by_tags(
+    db: State<'_, Database>,
+    tag_ids: Vec<i64>,
+    mode: TagMatch,
+) -> Result<Vec<Note>, AppError> {
+    db.tags().notes(tag_ids, mode).await
+}
+
+/// Fails with a conflict carrying the existing tag if one has the same name, ignoring case.
+#[tauri::command]
+#[specta::specta]
+pub async fn create_tag(
+    app: AppHandle,
+    db: State<'_, Database>,
+    name: String,
+) -> Result<Tag, AppError> {
+    let tag = db.tags().create(name).await?;
+    changes::notify(&app, TagChanged::Created { tag: tag.clone() });
+    Ok(tag)
+}
+
+/// Fails with a conflict carrying the other tag if the name is taken, so it can be merged
+/// into that one instead.
+#[tauri::command]
+#[specta::specta]
+pub async fn rename_tag(
+    app: AppHandle,
+    db: State<'_, Database>,
+    id: i64,
+    name: String,
+) -> Result<Tag, AppError> {
+    let tag = db.tags().rename(id, name).await?;
+    changes::notify(&app, TagChanged::Updated { tag: tag.clone() });
+    Ok(tag)
+}
+
+/// Moves the notes of `from_id` over to `into_id` and deletes `from_id`.
+#[tauri::command]
+#[specta::specta]
+pub async fn merge_tags(
+    app: AppHandle,
+    db: State<'_, Database>,
+    from_id: i64,
+    into_id: i64,
+) -> Result<Tag, AppError> {
+    let tag = db.tags().merge(from_id, into_id).await?;
+    changes::notify(&app, TagChanged::Merged { from_id, tag: tag.clone() });
+    Ok(tag)
+}
+
+#[tauri::command]
+#[specta::specta]
+pub async fn delete_tag(app: AppHandle, db: State<'_, Database>, id: i64) -> Result<(), AppError> {
+    db.tags().delete(id).await?;
+    changes::notify(&app, TagChanged::Deleted { id });
     Ok(())
 }
 
+/// Returns every tag the note carries afterwards.
 #[tauri::command]
-pub async fn backup_database() -> Result<(), String> {
-    let local_data_dir: PathBuf = data_local_dir().ok_or("Failed to get local data directory")?;
-    let identifier = "com.stenberg.focusboard";
-    let app_local_data_dir = local_data_dir.join(identifier);
+#[specta::specta]
+pub async fn tag_note(
+    app: AppHandle,
+    db: State<'_, Database>,
+    note_id: i64,
+    tag_id: i64,
+) -> Result<Vec<Tag>, AppError> {
+    let tags = db.tags().attach(note_id, tag_id).await?;
+    notify_note_tagged(&app, note_id, &tags);
+    Ok(tags)
+}
 
-    let database_path: PathBuf = app_local_data_dir.join("database");
-    let backup_base_path: PathBuf = app_local_data_dir.join("database_backups");
+/// Returns the tags the note has left.
+#[tauri::command]
+#[specta::specta]
+pub async fn untag_note(
+    app: AppHandle,
+    db: State<'_, Database>,
+    note_id: i64,
+    tag_id: i64,
+) -> Result<Vec<Tag>, AppError> {
+    let tags = db.tags().detach(note_id, tag_id).await?;
+    notify_note_tagged(&app, note_id, &tags);
+    Ok(tags)
+}
 
-    match create_dir(&backup_base_path) {
-        Ok(()) => {
-            info!("Database backup path created: {:?}", backup_base_path);
-        }
-        Err(e) if e.kind() == ErrorKind::AlreadyExists => {
-            info!("Database backup path already exists {:?}", backup_base_path);
-        }
-        Err(e) => {
-            error!(
-                "Failed to create database backup path {:?}: {:#}",
-                backup_base_path, e
-            );
-        }
-    }
+fn notify_note_tagged(app: &AppHandle, note_id: i64, tags: &[Tag]) {
+    let tag_ids = tags.iter().map(|tag| tag.id).collect();
+    changes::notify(app, TagChanged::NoteTagged { note_id, tag_ids });
+}
 
-    let now = OffsetDateTime::now_local().map_err(|e| {
-        error!("Failed to get local time: {:#}", e);
-        e.to_string()
-    })?;
+#[tauri::command]
+#[specta::specta]
+pub async fn list_trash(db: State<'_, Database>) -> Result<Trash, AppError> {
+    db.trash().list().await
+}
 
-    let timestamp = now
-        .format(&format_description!(
-            "[year]-[month]-[day]_T[hour]H-[minute]M-[second]S"
-        ))
-        .map_err(|e| {
-            error!("Failed to format local time: {:#}", e);
-            e.to_string()
-        })?;
+#[tauri::command]
+#[specta::specta]
+pub async fn restore_note(
+    app: AppHandle,
+    db: State<'_, Database>,
+    id: i64,
+) -> Result<Note, AppError> {
+    let note = db.trash().restore_note(id).await?;
+    changes::notify(&app, NoteChanged::Restored { note: note.clone() });
+    Ok(note)
+}
 
-    let backup_path: PathBuf = backup_base_path.join(format!("database-backup_{}", timestamp));
+#[tauri::command]
+#[specta::specta]
+pub async fn restore_tab(
+    app: AppHandle,
+    db: State<'_, Database>,
+    id: i64,
+) -> Result<Tab, AppError> {
+    let tabs = db.trash().restore_tab(id).await?;
+    for tab in &tabs {
+        changes::notify(&app, TabChanged::Restored { tab: tab.clone() });
+    }
+    changes::notify(&app, NoteChanged::Reset);
+    tabs.into_iter()
+        .next()
+        .ok_or_else(|| AppError::not_found(format!("Tab with id {id} not found in trash")))
+}
 
-    match create_dir(&backup_path) {
-        Ok(()) => {
-            info!("Backup directory successfully created");
-        }
-        Err(e) if e.kind() == ErrorKind::AlreadyExists => {
-            info!("Backup directory already exists {:?}", backup_path);
+#[tauri::command]
+#[specta::specta]
+pub async fn empty_trash(db: State<'_, Database>) -> Result<(), AppError> {
+    db.trash().empty().await
+}
+
+/// Backs the database up into a compressed archive. By default the archive goes in
+/// `database_backups`; `path` writes it to that file (or into that folder) instead, and
+/// `choose_location` asks the user where to save it, returning `None` if they cancel.
+#[tauri::command]
+#[specta::specta]
+pub async fn backup_database(
+    app: AppHandle,
+    db: State<'_, Database>,
+    path: Option<String>,
+    choose_location: Option<bool>,
+) -> Result<Option<BackupSummary>, AppError> {
+    let pool = db.pool();
+    let backup_path = match path {
+        Some(path) if Path::new(&path).is_dir() => {
+            backup::timestamped_path(Path::new(&path), backup::MANUAL_PREFIX)
         }
-        Err(e) => {
-            error!("Failed to create backup directory: {:#}", e);
+        Some(path) => PathBuf::from(path),
+        None if choose_location.unwrap_or(false) => {
+            let file_name = backup::archive_name("focusboard-backup");
+            match dialogs::save_file(&app, "FocusBoard backup", &["gz"], file_name).await? {
+                Some(path) => path,
+                None => return Ok(None),
+            }
         }
+        None => backup::timestamped_path(&db.backup_dir(), backup::MANUAL_PREFIX),
+    };
+
+    if backup_path.exists() {
+        return Err(AppError::conflict(format!("{:?} already exists.", backup_path)));
     }
 
-    let entries = read_dir(&database_path).map_err(|e| {
-        error!("Failed to read entries from {:?}: {:#}", database_path, e);
-        "Failed to read entries from database directory.".to_string()
+    backup::write_backup(&pool, &backup_path)
+        .await
+        .map(Some)
+        .map_err(|e| {
+            error!("Failed to back up database to {:?}: {:#}", backup_path, e);
+            AppError::database("Failed to back up the database. Please try again")
+        })
+}
+
+/// Lists the backups in `database_backups`, newest first, with the counts read from each.
+#[tauri::command]
+#[specta::specta]
+pub async fn list_backups(db: State<'_, Database>) -> Result<Vec<BackupInfo>, AppError> {
+    let paths = backup::find_backups(&db.backup_dir()).map_err(|e| {
+        error!("Failed to read backups in {:?}: {:#}", db.backup_dir(), e);
+        AppError::io("Failed to read the backup folder.")
     })?;
 
-    for entry in entries {
-        let entry = entry.map_err(|e| {
-            error!("Failed to read directory entry: {:#}", e);
-            "Failed to read database directory entry.".to_string()
-        })?;
+    let mut backups = Vec::with_capacity(paths.len());
+    for path in paths {
+        backups.push(backup::describe(&path).await);
+    }
 
-        let src_path = entry.path();
-        let dest_path = backup_path.join(entry.file_name());
+    Ok(backups)
+}
+
+/// Closes the pool, moves `staged` over the live database file when given, and opens a new
+/// pool on the result, migrating it if it is older than this build.
+async fn reopen_database(db: &Database, staged: Option<&Path>) -> Result<(), AppError> {
+    db.pool().close().await;
 
-        copy(&src_path, &dest_path).map_err(|e| {
-            error!("Failed to copy {:?} to {:?}: {:#}", src_path, dest_path, e);
-            "Failed to copy database to destination.".to_string()
+    if let Some(staged) = staged {
+        backup::replace_database_file(&db.path(), staged).map_err(|e| {
+            error!("Failed to move {:?} to {:?}: {:#}", staged, db.path(), e);
+            AppError::io("Failed to replace the database file.")
         })?;
     }
-    info!("Database backup successfully completed");
+
+    let pool = db::init_db(&db.url(), &db.backup_dir())
+        .await
+        .map_err(|e| {
+            error!("Failed to reopen database {:?}: {:#}", db.path(), e);
+            AppError::database("Failed to open the database.")
+        })?;
+    db.replace(pool);
 
     Ok(())
 }
 
+/// Replaces the live database with the backup at `path`: an archive, or the database file or
+/// folder of an older backup. The backup is checked first, and the current data is archived
+/// as a `pre-restore` backup; if the restored database can't be opened, that copy is put back.
 #[tauri::command]
-pub async fn reorder_notes(
-    pool: State<'_, SqlitePool>,
-    tab_id: Option<i64>,
-    note_ids: Vec<i64>,
-) -> Result<(), String> {
-    let len = note_ids.len() as i64;
-    if len == 0 {
-        return Ok(());
+#[specta::specta]
+pub async fn restore_backup(
+    app: AppHandle,
+    db: State<'_, Database>,
+    shutdown: State<'_, Shutdown>,
+    path: String,
+) -> Result<BackupInfo, AppError> {
+    let _work = shutdown.hold("restoring a backup")?;
+    let mut source = PathBuf::from(path);
+    if source.is_dir() {
+        source = source.join(backup::BACKUP_DB_FILE);
+    }
+    if !source.is_file() {
+        return Err(AppError::not_found("The selected backup does not exist."));
     }
 
-    let mut transaction = pool.begin().await.map_err(|e| {
-        error!("Failed to start transaction: {:#}", e);
-        "Failed to start transaction".to_string()
-    })?;
+    // Stage a self-contained copy next to the live file so the swap is a single rename.
+    let mut staged = db.path().as_os_str().to_owned();
+    staged.push("-restore");
+    let staged = PathBuf::from(staged);
+    if let Err(e) = backup::stage_backup(&source, &staged).await {
+        let _ = fs::remove_file(&staged);
+        return Err(e);
+    }
 
-    for (index, &id) in note_ids.iter().enumerate() {
-        let order_id = (index + 1) as i64;
-
-        sqlx::query("UPDATE notes SET order_id = ? WHERE id = ? AND tab_id IS NOT DISTINCT FROM ?")
-            .bind(order_id)
-            .bind(id)
-            .bind(tab_id)
-            .execute(&mut *transaction)
-            .await
-            .map_err(|e| {
-                error!("Failed to reorder note {}: {:#}", id, e);
-                "Failed to reorder notes".to_string()
-            })?;
+    let safety_path = backup::timestamped_path(&db.backup_dir(), backup::PRE_RESTORE_PREFIX);
+    let safety = backup::write_backup(&db.pool(), &safety_path)
+        .await
+        .map_err(|e| {
+            error!("Failed to back up database to {:?}: {:#}", safety_path, e);
+            let _ = fs::remove_file(&staged);
+            AppError::database(
+                "Failed to back up the current data before restoring. Nothing was changed.",
+            )
+        })?;
+    if !safety.integrity_ok {
+        let _ = fs::remove_file(&staged);
+        return Err(AppError::database(
+            "The backup of the current data failed its integrity check. Nothing was changed.",
+        ));
     }
 
-    transaction.commit().await.map_err(|e| {
-        error!("Failed to commit transaction: {:#}", e);
-        "Failed to commit transaction".to_string()
-    })?;
+    if let Err(restore_error) = reopen_database(&db, Some(&staged)).await {
+        error!("Restoring {:?} failed, rolling back", source);
+
+        let rollback = match backup::extract_database(&safety_path, &staged) {
+            Ok(_) => reopen_database(&db, Some(&staged)).await,
+            Err(e) => Err(AppError::io(e.to_string())),
+        };
+        if let Err(e) = rollback {
+            error!("Failed to roll back to {:?}: {:#}", safety_path, e);
+            return Err(AppError::database(format!(
+                "Restoring the backup failed ({}), and so did putting the previous data back. \
+                 It is saved in {:?}.",
+                restore_error, safety_path
+            )));
+        }
 
-    Ok(())
+        return Err(AppError::database(format!(
+            "Restoring the backup failed: {}. Your previous data was kept.",
+            restore_error
+        )));
+    }
+
+    info!(
+        "Restored database from {:?}; previous data saved to {:?}",
+        source, safety_path
+    );
+    changes::notify_reset(&app);
+
+    Ok(backup::describe(&source).await)
 }
 
 #[tauri::command]
-pub async fn reorder_tabs(
-    pool: State<'_, SqlitePool>,
-    tab_ids: Vec<i64>
-) -> Result<(), String> {
-    let len = tab_ids.len() as i64;
-    if len == 0 {
-        return Ok(());
-    }
+#[specta::specta]
+pub async fn get_data_directory(
+    app: AppHandle,
+    db: State<'_, Database>,
+) -> Result<DataDirectory, AppError> {
+    storage::describe(&app, &db.storage()).map_err(|e| {
+        error!("Failed to resolve the data directory: {:#}", e);
+        AppError::io("Failed to find the data directory.")
+    })
+}
 
-    let mut transaction = pool.begin().await.map_err(|e| {
-        error!("Failed to start transaction: {:#}", e);
-        "Failed to start transaction".to_string()
+/// Moves the database to `path` (the user picks a folder without it; `None` is returned if
+/// they cancel) and keeps using it from then on. Existing backups are copied along, and a
+/// `pre-move` backup is left in the old location. The folder must not already hold a
+/// FocusBoard database.
+#[tauri::command]
+#[specta::specta]
+pub async fn move_data_directory(
+    app: AppHandle,
+    db: State<'_, Database>,
+    shutdown: State<'_, Shutdown>,
+    path: Option<String>,
+) -> Result<Option<DataDirectory>, AppError> {
+    let _work = shutdown.hold("moving the data directory")?;
+    let root = match path {
+        Some(path) => PathBuf::from(path),
+        None => match dialogs::pick_folder(&app).await? {
+            Some(path) => path,
+            None => return Ok(None),
+        },
+    };
+
+    let current = db.storage();
+    let target = Storage::new(root.clone());
+    let describe_error = |e: tauri::Error| {
+        error!("Failed to resolve the data directory: {:#}", e);
+        AppError::io("Failed to find the data directory.")
+    };
+
+    if target == current {
+        return storage::describe(&app, &current)
+            .map(Some)
+            .map_err(describe_error);
+    }
+    if target.database_path().exists() {
+        return Err(AppError::conflict(format!(
+            "{:?} already contains a FocusBoard database. Please choose another folder.",
+            root
+        )));
+    }
+    fs::create_dir_all(target.database_dir()).map_err(|e| {
+        error!("Failed to create {:?}: {:#}", target.database_dir(), e);
+        AppError::io("Failed to create the new data folder.")
     })?;
 
-    for (index, &id) in tab_ids.iter().enumerate() {
-        let order_id = (index + 1) as i64;
-
-        sqlx::query("UPDATE tabs SET order_id = ? WHERE id = ?")
-            .bind(order_id)
-            .bind(id)
-            .execute(&mut *transaction)
-            .await
-            .map_err(|e| {
-                error!("Failed to reorder tab {}: {:#}", id, e);
-                "Failed to reorder tabs".to_string()
-            })?;
+    let safety_path = backup::timestamped_path(&current.backup_dir(), backup::PRE_MOVE_PREFIX);
+    let safety = backup::write_backup(&db.pool(), &safety_path)
+        .await
+        .map_err(|e| {
+            error!("Failed to back up database to {:?}: {:#}", safety_path, e);
+            AppError::database(
+                "Failed to back up the current data before moving it. Nothing was changed.",
+            )
+        })?;
+    if !safety.integrity_ok {
+        return Err(AppError::database(
+            "The backup of the current data failed its integrity check. Nothing was changed.",
+        ));
+    }
+
+    match copy_dir_contents(&current.backup_dir(), &target.backup_dir()) {
+        Ok(copied) => info!("Copied {} backup(s) to {:?}", copied, target.backup_dir()),
+        Err(e) => error!(
+            "Failed to copy backups to {:?}: {:#}",
+            target.backup_dir(),
+            e
+        ),
     }
 
-    transaction.commit().await.map_err(|e| {
-        error!("Failed to commit transaction: {:#}", e);
-        "Failed to commit transaction".to_string()
-    })?;
+    // Close the pool first so no write lands in the old file after it has been copied.
+    db.pool().close().await;
+
+    let copied = async {
+        backup::copy_database(&current.database_path(), &target.database_path()).await?;
+        backup::integrity_check(&target.database_path()).await
+    }
+    .await;
+    let moved = match copied {
+        Ok(integrity) if integrity == ["ok"] => {
+            db::init_db(&target.database_url(), &target.backup_dir())
+                .await
+                .map_err(|e| e.to_string())
+        }
+        Ok(integrity) => Err(format!("integrity check failed: {}", integrity.join("; "))),
+        Err(e) => Err(e.to_string()),
+    };
+
+    let pool = match moved {
+        Ok(pool) => pool,
+        Err(e) => {
+            error!("Failed to move the database to {:?}: {}", root, e);
+            let _ = backup::remove_database_file(&target.database_path());
+            reopen_database(&db, None).await?;
+            return Err(AppError::io("Failed to move the data. Nothing was changed."));
+        }
+    };
+
+    let default_root = storage::default_root(&app).map_err(describe_error)?;
+    let setting = (root != default_root).then_some(root.as_path());
+    if let Err(e) = settings::set_data_directory(&app, setting) {
+        pool.close().await;
+        let _ = backup::remove_database_file(&target.database_path());
+        reopen_database(&db, None).await?;
+        return Err(AppError::io(format!("{} Nothing was changed.", e)));
+    }
 
+    db.set_storage(target.clone());
+    db.replace(pool);
+
+    // The data now lives in the new folder; drop the stale copy so it can't be mistaken for
+    // the live database. The pre-move backup stays behind.
+    if let Err(e) = backup::remove_database_file(&current.database_path()) {
+        error!(
+            "Failed to remove the old database {:?}: {:#}",
+            current.database_path(),
+            e
+        );
+    }
+
+    info!("Moved data directory from {:?} to {:?}", current.root(), root);
+    changes::notify_reset(&app);
+
+    storage::describe(&app, &target)
+        .map(Some)
+        .map_err(describe_error)
+}
+
+#[tauri::command]
+#[specta::specta]
+pub async fn reorder_notes(
+    app: AppHandle,
+    db: State<'_, Database>,
+    tab_id: Option<i64>,
+    note_ids: Vec<i64>,
+) -> Result<(), AppError> {
+    db.notes().reorder(tab_id, note_ids.clone()).await?;
+    changes::notify(&app, NoteChanged::Reordered { tab_id, note_ids });
+    Ok(())
+}
+
+/// Moves notes into `target_tab_id` at `target_index`, in the order given, and closes the gaps
+/// they leave in their old tabs. Returns every note whose tab or position changed.
+#[tauri::command]
+#[specta::specta]
+pub async fn move_notes(
+    app: AppHandle,
+    db: State<'_, Database>,
+    note_ids: Vec<i64>,
+    target_tab_id: Option<i64>,
+    target_index: u32,
+) -> Result<Vec<Note>, AppError> {
+    let notes = db
+        .notes()
+        .move_notes(note_ids, target_tab_id, target_index as usize)
+        .await?;
+    changes::notify(&app, NoteChanged::Moved { notes: notes.clone() });
+    Ok(notes)
+}
+
+/// Orders sibling tabs as given.
+#[tauri::command]
+#[specta::specta]
+pub async fn reorder_tabs(
+    app: AppHandle,
+    db: State<'_, Database>,
+    tab_ids: Vec<i64>
+) -> Result<(), AppError> {
+    db.tabs().reorder(tab_ids.clone()).await?;
+    changes::notify(&app, TabChanged::Reordered { tab_ids });
     Ok(())
 }
 
+/// Moves a tab under `parent_id`, or to the top level without one, at `index` among its new
+/// siblings. Returns every tab whose parent or position changed.
 #[tauri::command]
+#[specta::specta]
+pub async fn move_tab(
+    app: AppHandle,
+    db: State<'_, Database>,
+    id: i64,
+    parent_id: Option<i64>,
+    index: u32,
+) -> Result<Vec<Tab>, AppError> {
+    let tabs = db.tabs().move_to(id, parent_id, index as usize).await?;
+    changes::notify(&app, TabChanged::Moved { tabs: tabs.clone() });
+    Ok(tabs)
+}
+
+#[tauri::command]
+#[specta::specta]
 pub async fn create_timer(
-    pool: State<'_, SqlitePool>,
+    db: State<'_, Database>,
     initial_duration: i32,
     duration: i32,
     message: String
-) -> Result<Timer, String> {
-    sqlx::query(
-        r#"
-        INSERT OR REPLACE INTO timers (id, initial_duration, duration, message)
-        VALUES (1, ?, ?, ?)
-        "#
-    )
-    .bind(initial_duration)
-    .bind(duration)
-    .bind(message)
-    .execute(&*pool)
-    .await
-    .map_err(|e| {
-        error!("Failed to create/upsert timer: {:#}", e);
-        e.to_string()
-    })?;
-
-    let timer = sqlx::query_as::<_, Timer>(
-        r#"
-        SELECT * FROM timers WHERE id = 1
-        "#
-    )
-    .fetch_one(&*pool)
-    .await
-    .map_err(|e| {
-        error!("Failed to fetch timer after create: {:#}", e);
-        e.to_string()
-    })?;
-
-    Ok(timer)
+) -> Result<Timer, AppError> {
+    db.timers().save(initial_duration, duration, message).await
 }
 
 #[tauri::command]
+#[specta::specta]
 pub async fn get_timer(
-    pool: State<'_, SqlitePool>,
-) -> Result<Timer, String> {
-    let timer = query_as::<_, Timer>(
-        r#"
-        SELECT * FROM timers WHERE id = 1
-        "#
-    )
-    .fetch_optional(&*pool)
-    .await
-    .map_err(|e| {
-        error!("Failed to get timer: {:#}", e);
-        e.to_string()
-    })?;
-
-    match timer {
-        Some(t) => Ok(t),
-        None => Err("No timer found".to_string()),
-    }
+    db: State<'_, Database>,
+) -> Result<Timer, AppError> {
+    db.timers().get().await
 }
 
+// Each argument is a field of the invoke payload.
+#[allow(clippy::too_many_arguments)]
 #[tauri::command]
+#[specta::specta]
 pub async fn insert_event(
-    pool: State<'_, SqlitePool>,
+    app: AppHandle,
+    db: State<'_, Database>,
     event_date: String,
-    year_month: String,
     event_name: String,
     event_start: Option<i32>,
     event_end: Option<i32>,
     color: String,
-) -> Result<CalendarEvent, String> {
-    let event = query_as::<_, CalendarEvent>(
-        r#"
-        INSERT INTO events (event_date, year_month, event_name, event_start, event_end, color)
-        VALUES (?, ?, ?, ?, ?, ?)
-        RETURNING id, event_date, year_month, event_name, event_start, event_end, color
-        "#
-    )
-    .bind(event_date)
-    .bind(year_month)
-    .bind(event_name)
-    .bind(event_start)
-    .bind(event_end)
-    .bind(color)
-    .fetch_one(&*pool)
-    .await
-    .map_err(|e| {
-        error!("Failed to insert event to calendar: {:#}", e);
-        e.to_string()
-    })?;
-
+    recurrence: Option<RecurrenceRule>,
+) -> Result<CalendarEvent, AppError> {
+    let event = db
+        .events()
+        .create(event_date, event_name, event_start, event_end, color, recurrence)
+        .await?;
+    changes::notify(&app, EventChanged::Created { event: event.clone() });
     Ok(event)
 }
 
 #[tauri::command]
+#[specta::specta]
 pub async fn get_events(
-    pool: State<'_, SqlitePool>,
+    db: State<'_, Database>,
     year_month: String,
     onwards: Option<bool>,
-) -> Result<Vec<CalendarEvent>, String> {
-    let query = if onwards.unwrap_or(false) {
-        "SELECT * FROM events WHERE year_month >= ? ORDER BY year_month, event_start ASC"
-    } else {
-        "SELECT * FROM events WHERE year_month = ? ORDER BY event_start ASC"
-    };
+) -> Result<Vec<CalendarEvent>, AppError> {
+    db.events().month(year_month, onwards).await
+}
 
-    let events = query_as::<_, CalendarEvent>(query)
-        .bind(year_month)
-        .fetch_all(&*pool)
-        .await
-        .map_err(|e| {
-            error!("Failed to retrieve events: {:#}", e);
-            e.to_string()
-        })?;
+/// Events from `start_date` through `end_date`, both inclusive. Recurring events are expanded
+/// into one entry per occurrence, with their exceptions applied.
+#[tauri::command]
+#[specta::specta]
+pub async fn get_events_in_range(
+    db: State<'_, Database>,
+    start_date: String,
+    end_date: String,
+) -> Result<Vec<CalendarEvent>, AppError> {
+    db.events().range(start_date, end_date).await
+}
 
-    Ok(events)
+/// Sets or clears the recurrence rule of an event. Exceptions that no longer match an
+/// occurrence are left in place but have no effect.
+#[tauri::command]
+#[specta::specta]
+pub async fn set_event_recurrence(
+    app: AppHandle,
+    db: State<'_, Database>,
+    id: i64,
+    recurrence: Option<RecurrenceRule>,
+) -> Result<CalendarEvent, AppError> {
+    let event = db.events().set_recurrence(id, recurrence).await?;
+    changes::notify(&app, EventChanged::Updated { event: event.clone() });
+    Ok(event)
 }
 
+// Each argument is a field of the invoke payload.
+#[allow(clippy::too_many_arguments)]
 #[tauri::command]
+#[specta::specta]
 pub async fn update_event(
-    pool: State<'_, SqlitePool>,
+    app: AppHandle,
+    db: State<'_, Database>,
     id: i64,
+    version: i64,
     event_name: String,
     event_start: Option<i32>,
     event_end: Option<i32>,
-) -> Result<(), String> {
-    sqlx::query("UPDATE events SET event_name = ?, event_start = ?, event_end = ? WHERE id = ?")
-        .bind(event_name)
-        .bind(event_start)
-        .bind(event_end)
-        .bind(id)
-        .execute(&*pool)
-        .await
-        .map_err(|e| {
-            error!("Failed to update event: {:#}", e);
-            e.to_string()
-        })?;
+    occurrence_date: Option<String>,
+) -> Result<CalendarEvent, AppError> {
+    let event = db
+        .events()
+        .update(id, version, event_name, event_start, event_end, occurrence_date)
+        .await?;
+    changes::notify(&app, EventChanged::Updated { event: event.clone() });
+    Ok(event)
+}
 
+#[tauri::command]
+#[specta::specta]
+pub async fn delete_event(
+    app: AppHandle,
+    db: State<'_, Database>,
+    id: i64,
+    version: i64,
+    occurrence_date: Option<String>,
+) -> Result<(), AppError> {
+    let events = db.events();
+    events.delete(id, version, occurrence_date).await?;
+
+    // Deleting one occurrence of a recurring event leaves the series in place, at a new version.
+    match events.get(id).await {
+        Ok(event) => changes::notify(&app, EventChanged::Updated { event }),
+        Err(AppError::NotFound { .. }) => changes::notify(&app, EventChanged::Deleted { id }),
+        Err(_) => changes::notify(&app, EventChanged::Reset),
+    }
     Ok(())
 }
 
+/// Writes every event to an iCalendar file. Without `path` the user is asked where to save it;
+/// `None` is returned if they cancel.
 #[tauri::command]
-pub async fn delete_event(
-    pool: State<'_, SqlitePool>,
+#[specta::specta]
+pub async fn export_events_ics(
+    app: AppHandle,
+    db: State<'_, Database>,
+    path: Option<String>,
+) -> Result<Option<IcsExportSummary>, AppError> {
+    let path = match path {
+        Some(path) => PathBuf::from(path),
+        None => match dialogs::save_file(
+            &app,
+            "iCalendar",
+            &["ics"],
+            "focusboard-events.ics".to_string(),
+        )
+        .await?
+        {
+            Some(path) => path,
+            None => return Ok(None),
+        },
+    };
+
+    db.events().export_ics(&path).await.map(Some)
+}
+
+/// Imports the VEVENTs of an iCalendar file. Events whose UID is already in the calendar are
+/// skipped as duplicates. Without `path` the user picks the file; `None` is returned if they
+/// cancel.
+#[tauri::command]
+#[specta::specta]
+pub async fn import_events_ics(
+    app: AppHandle,
+    db: State<'_, Database>,
+    path: Option<String>,
+) -> Result<Option<IcsImportSummary>, AppError> {
+    let path = match path {
+        Some(path) => PathBuf::from(path),
+        None => match dialogs::pick_file(&app, "iCalendar", &["ics"]).await? {
+            Some(path) => path,
+            None => return Ok(None),
+        },
+    };
+
+    let summary = db.events().import_ics(&path).await?;
+    changes::notify(&app, EventChanged::Reset);
+    Ok(Some(summary))
+}
+
+/// Exports every tab as a folder of Markdown notes, inside a new timestamped folder under
+/// `path`. Without `path` the user picks the parent folder; `None` is returned if they cancel.
+#[tauri::command]
+#[specta::specta]
+pub async fn export_markdown(
+    app: AppHandle,
+    db: State<'_, Database>,
+    path: Option<String>,
+) -> Result<Option<MarkdownExportSummary>, AppError> {
+    let parent = match path {
+        Some(path) => PathBuf::from(path),
+        None => match dialogs::pick_folder(&app).await? {
+            Some(path) => path,
+            None => return Ok(None),
+        },
+    };
+
+    db.transfer().export_markdown(&parent).await.map(Some)
+}
+
+/// Imports a folder of Markdown (`.md`) and text (`.txt`) files. Each subfolder becomes a new
+/// tab holding the files inside it; files directly in the folder go into a tab named after it.
+/// Without `path` the user picks the folder; `None` is returned if they cancel.
+#[tauri::command]
+#[specta::specta]
+pub async fn import_folder(
+    app: AppHandle,
+    db: State<'_, Database>,
+    path: Option<String>,
+) -> Result<Option<FolderImportSummary>, AppError> {
+    let root = match path {
+        Some(path) => PathBuf::from(path),
+        None => match dialogs::pick_folder(&app).await? {
+            Some(path) => path,
+            None => return Ok(None),
+        },
+    };
+
+    let summary = db.transfer().import_folder(&root).await?;
+    changes::notify(&app, TabChanged::Reset);
+    changes::notify(&app, NoteChanged::Reset);
+    Ok(Some(summary))
+}
+
+/// Opens the note in a window of its own, or focuses that window if it is already open.
+#[tauri::command]
+#[specta::specta]
+pub async fn open_note_window(
+    app: AppHandle,
+    db: State<'_, Database>,
     id: i64,
-) -> Result<(), String> {
-    let result = sqlx::query("DELETE FROM events WHERE id = ?")
-        .bind(id)
-        .execute(&*pool)
-        .await
-        .map_err(|e| {
-            error!("Failed to delete event {}: {:#}", id, e);
-            e.to_string()
-        })?;
+) -> Result<(), AppError> {
+    let note = db.notes().get(id).await?;
+    let title = html::to_plain_text(&note.title);
+    let title = if title.trim().is_empty() { "Untitled" } else { title.trim() };
 
-    if result.rows_affected() == 0 {
-        return Err(format!("Event with {id} not found"));
-    }
+    windows::open_popout(&app, &format!("note-{id}"), &format!("note={id}"), title)
+}
 
-    Ok(())
+/// Opens every note of the tab in a window of its own, or focuses that window if it is already
+/// open.
+#[tauri::command]
+#[specta::specta]
+pub async fn open_tab_window(
+    app: AppHandle,
+    db: State<'_, Database>,
+    id: i64,
+) -> Result<(), AppError> {
+    let tab = db.tabs().get(id).await?;
+
+    windows::open_popout(&app, &format!("tab-{id}"), &format!("tab={id}"), &tab.name)
+}
+
+/// Makes shutdown wait, up to a timeout, until the calling window completes `key`, e.g. a
+/// note that is being edited. Everything a window registered is dropped when it closes.
+#[tauri::command]
+#[specta::specta]
+pub fn register_pending_work(window: Window, shutdown: State<'_, Shutdown>, key: String) {
+    shutdown.register(window.label(), &key);
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn complete_pending_work(window: Window, shutdown: State<'_, Shutdown>, key: String) {
+    shutdown.complete(window.label(), &key);
 }
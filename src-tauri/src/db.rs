@@ -1,81 +1,95 @@
-use sqlx::{sqlite::SqlitePoolOptions, Executor, SqlitePool};
-
-pub async fn init_db(db_url: &str) -> Result<SqlitePool, sqlx::Error> {
-    let db = SqlitePoolOptions::new()
-        .after_connect(|conn, _| {
-            Box::pin(async move {
-                conn.execute(sqlx::query(
-                    "PRAGMA journal_mode = WAL;\
-                    PRAGMA foreign_keys = ON;\
-                    PRAGMA auto_vacuum = INCREMENTAL;\
-                    PRAGMA optimize;",
-                ))
-                .await?;
-
-                Ok(())
-            })
-        })
-        .connect(db_url)
-        .await?;
-
-    let mut conn = db.acquire().await?;
-
-    sqlx::query(
-        "CREATE TABLE IF NOT EXISTS tabs (
-            id INTEGER PRIMARY KEY,
-            name TEXT NOT NULL,
-            order_id INTEGER,
-            created_at TEXT DEFAULT (datetime('now')),
-            updated_at TEXT DEFAULT (datetime('now'))
-        )",
-    )
-    .execute(&mut *conn)
-    .await?;
-
-    sqlx::query(
-        "CREATE TABLE IF NOT EXISTS notes (
-            id INTEGER PRIMARY KEY,
-            title TEXT NOT NULL,
-            content TEXT,
-            tab_id INTEGER,
-            order_id INTEGER,
-            created_at TEXT DEFAULT (datetime('now')),
-            updated_at TEXT DEFAULT (datetime('now')),
-            FOREIGN KEY (tab_id) REFERENCES tabs(id) ON DELETE CASCADE
-        )",
-    )
-    .execute(&mut *conn)
-    .await?;
-
-    sqlx::query(
-        "CREATE TABLE IF NOT EXISTS timers (
-            id INTEGER PRIMARY KEY,
-            initial_duration INTEGER,
-            duration INTEGER,
-            message TEXT
-        )"
-    )
-    .execute(&mut *conn)
-    .await?;
-
-    sqlx::query(
-        "CREATE TABLE IF NOT EXISTS events (
-            id INTEGER PRIMARY KEY,
-            event_date TEXT NOT NULL,
-            year_month TEXT,
-            event_name TEXT,
-            event_start INTEGER,
-            event_end INTEGER,
-            color TEXT
-        )"
-    )
-    .execute(&mut *conn)
-    .await?;
-
-    sqlx::query(
-        "CREATE INDEX IF NOT EXISTS idx_notes_tab_order
-        ON notes(tab_id, order_id)"
-    ).execute(&mut *conn).await?;
-
-    Ok(db)
+use focusboard_core::storage::Storage;
+use focusboard_core::{
+    EventService, NoteService, TabService, TagService, TimerService, TransferService, TrashService,
+};
+use sqlx::SqlitePool;
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+/// The app's connection pool and storage location, managed as Tauri state. Both sit behind
+/// locks so that restoring a backup or moving the data directory can swap them at runtime.
+pub struct Database {
+    pool: RwLock<SqlitePool>,
+    storage: RwLock<Storage>,
+}
+
+impl Database {
+    pub fn new(pool: SqlitePool, storage: Storage) -> Self {
+        Self {
+            pool: RwLock::new(pool),
+            storage: RwLock::new(storage),
+        }
+    }
+
+    /// A handle to the current pool. Clone it out instead of holding on to the `Database`.
+    pub fn pool(&self) -> SqlitePool {
+        self.pool
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// Installs `pool` and returns the one it replaces.
+    pub fn replace(&self, pool: SqlitePool) -> SqlitePool {
+        let mut current = self
+            .pool
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        std::mem::replace(&mut *current, pool)
+    }
+
+    pub fn storage(&self) -> Storage {
+        self.storage
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    pub fn set_storage(&self, storage: Storage) {
+        *self
+            .storage
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = storage;
+    }
+
+    /// Path of the live database file.
+    pub fn path(&self) -> PathBuf {
+        self.storage().database_path()
+    }
+
+    pub fn url(&self) -> String {
+        self.storage().database_url()
+    }
+
+    pub fn backup_dir(&self) -> PathBuf {
+        self.storage().backup_dir()
+    }
+
+    pub fn notes(&self) -> NoteService {
+        NoteService::new(self.pool())
+    }
+
+    pub fn tabs(&self) -> TabService {
+        TabService::new(self.pool())
+    }
+
+    pub fn tags(&self) -> TagService {
+        TagService::new(self.pool())
+    }
+
+    pub fn trash(&self) -> TrashService {
+        TrashService::new(self.pool())
+    }
+
+    pub fn timers(&self) -> TimerService {
+        TimerService::new(self.pool())
+    }
+
+    pub fn events(&self) -> EventService {
+        EventService::new(self.pool())
+    }
+
+    pub fn transfer(&self) -> TransferService {
+        TransferService::new(self.pool())
+    }
 }
@@ -0,0 +1,61 @@
+use focusboard_core::AppError;
+use log::error;
+use std::path::PathBuf;
+use tauri::{async_runtime, AppHandle};
+use tauri_plugin_dialog::{DialogExt, FileDialogBuilder, FilePath};
+
+/// Runs a blocking file dialog off the async runtime. `None` means the user cancelled.
+async fn show<F>(app: &AppHandle, show: F) -> Result<Option<PathBuf>, AppError>
+where
+    F: FnOnce(FileDialogBuilder<tauri::Wry>) -> Option<FilePath> + Send + 'static,
+{
+    let builder = app.dialog().file();
+
+    let picked = async_runtime::spawn_blocking(move || show(builder))
+        .await
+        .map_err(|e| {
+            error!("File dialog failed: {:#}", e);
+            AppError::io("Failed to open the file dialog")
+        })?;
+
+    picked
+        .map(|path| {
+            path.into_path().map_err(|e| {
+                error!("File dialog returned an unusable path: {:#}", e);
+                AppError::validation("The chosen location is not a local path")
+            })
+        })
+        .transpose()
+}
+
+pub async fn pick_file(
+    app: &AppHandle,
+    filter_name: &'static str,
+    extensions: &'static [&'static str],
+) -> Result<Option<PathBuf>, AppError> {
+    show(app, move |builder| {
+        builder
+            .add_filter(filter_name, extensions)
+            .blocking_pick_file()
+    })
+    .await
+}
+
+pub async fn save_file(
+    app: &AppHandle,
+    filter_name: &'static str,
+    extensions: &'static [&'static str],
+    file_name: String,
+) -> Result<Option<PathBuf>, AppError> {
+    show(app, move |builder| {
+        builder
+            .add_filter(filter_name, extensions)
+            .set_file_name(file_name)
+            .blocking_save_file()
+    })
+    .await
+}
+
+pub async fn pick_folder(app: &AppHandle) -> Result<Option<PathBuf>, AppError> {
+    show(app, |builder| builder.blocking_pick_folder()).await
+}
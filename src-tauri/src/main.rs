@@ -1,24 +1,38 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod bindings;
+mod changes;
 mod commands;
 mod db;
+mod dialogs;
+mod settings;
+mod shutdown;
+mod storage;
+mod windows;
 
 use colored::*;
+use focusboard_core::backup;
+use focusboard_core::db::init_db;
+use focusboard_core::storage::Storage;
 use log::{error, info, warn};
 use std::fs;
 use std::io::ErrorKind;
-use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
-use tauri::{async_runtime, Emitter, Manager};
+use tauri::{async_runtime, AppHandle, Manager, RunEvent, WindowEvent};
+use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
 use tauri_plugin_log::{Target, TargetKind};
-use tauri_plugin_updater;
-use tauri_plugin_dialog;
 
 #[async_std::main]
 async fn main() {
     let context = tauri::generate_context!();
 
+    let bindings = bindings::builder();
+    #[cfg(debug_assertions)]
+    if let Err(e) = bindings::export(&bindings) {
+        eprintln!("Failed to export TypeScript bindings: {:#}", e);
+    }
+    let invoke_handler = bindings.invoke_handler();
+
     tauri::Builder::default()
         .plugin(tauri_plugin_updater::Builder::new().build())
         .plugin(tauri_plugin_dialog::init())
@@ -46,129 +60,143 @@ async fn main() {
                 .build(),
         )
         .plugin(tauri_plugin_opener::init())
-        .setup(|app| {
-            let base_dir: PathBuf = app.path().app_local_data_dir()?.into();
-            let data_dir = base_dir.join("database");
-
-            info!("Attempting to create database directory: {:?}", data_dir);
-            if let Err(e) = fs::create_dir_all(&data_dir) {
-                if e.kind() != ErrorKind::AlreadyExists {
-                    error!(
-                        "Failed to create database directory {:?}: {:#}",
-                        data_dir, e
-                    );
-                }
-            } else {
-                info!("Database directory already exists: {:?}", data_dir);
+        .manage(shutdown::Shutdown::default())
+        .setup(move |app| {
+            bindings.mount_events(app);
+            shutdown::watch_signals(app.handle());
+
+            if let Some(window) = app.get_webview_window(windows::MAIN_WINDOW) {
+                windows::restore_geometry(&window);
             }
-            info!("Database directory ready: {:?}", data_dir);
-
-            let db_path = data_dir.join("data.db");
-            let db_path_str = db_path.to_string_lossy().replace("\\", "/");
-            let db_url = format!("sqlite://{}?mode=rwc", db_path_str);
-
-            info!("DB URL: {}", db_url);
-
-            let pool = async_runtime::block_on(db::init_db(&db_url)).map_err(|e| {
-                error!("Failed to initialize database at {:?}: {:#}", db_url, e);
-                e
-            })?;
-
-            let pool_cleanup_background = pool.clone();
-            let pool_cleanup_close = pool.clone();
-
-            tauri::async_runtime::spawn(async move {
-                let mut interval = tokio::time::interval(std::time::Duration::from_secs(600));
-                loop {
-                    interval.tick().await;
-                    let _ = sqlx::query("PRAGMA optimize")
-                        .execute(&pool_cleanup_background)
-                        .await
-                        .map_err(|e| {
-                            warn!("Periodic database optimization failed: {:#}", e);
-                            e
-                        });
+
+            match storage::resolve(app.handle())? {
+                storage::Startup::Ready(storage) => open_database(app.handle(), storage),
+                storage::Startup::Missing(root) => {
+                    storage::recover_missing(app.handle(), root, open_database)
                 }
-            });
-
-            let is_closing = Arc::new(Mutex::new(false));
-
-            if let Some(window) = app.get_webview_window("main") {
-                let window_clone = window.clone();
-                let is_closing = is_closing.clone();
-
-                window.on_window_event(move |event| {
-                    if let tauri::WindowEvent::CloseRequested { api, .. } = event {
-                        api.prevent_close();
-
-                        let mut closing = is_closing.lock().unwrap();
-                        if *closing {
-                            return;
-                        }
-                        *closing = true;
-                        drop(closing);
-
-                        let _ = window_clone.emit("app-closing", ());
-
-                        let win = window_clone.clone();
-                        let pool = pool_cleanup_close.clone();
-
-                        tauri::async_runtime::spawn(async move {
-                            let _ = sqlx::query("PRAGMA optimize;")
-                                .execute(&pool)
-                                .await
-                                .map_err(|e| {
-                                    warn!("Failed to optimize database: {:#}", e);
-                                    e
-                                });
-                            let _ = sqlx::query("PRAGMA incremental_vacuum(0);")
-                                .execute(&pool)
-                                .await
-                                .map_err(|e| {
-                                    warn!("Failed to incrementally vacuum database: {:#}", e);
-                                    e
-                                });
-                            let _ = sqlx::query("PRAGMA wal_checkpoint(TRUNCATE);")
-                                .execute(&pool)
-                                .await
-                                .map_err(|e| {
-                                    warn!("Failed to flush WAL: {:#}", e);
-                                    e
-                                });
-
-                            tokio::time::sleep(std::time::Duration::from_secs(3)).await;
-
-                            let _ = win.close();
-                            win.app_handle().exit(0);
-                        });
-                    }
-                });
             }
 
-            app.manage(pool);
-
             info!("App setup complete");
             Ok(())
         })
-        .invoke_handler(tauri::generate_handler![
-            commands::get_notes,
-            commands::create_note,
-            commands::update_note,
-            commands::delete_note,
-            commands::get_tabs,
-            commands::create_tab,
-            commands::update_tab,
-            commands::delete_tab,
-            commands::backup_database,
-            commands::reorder_notes,
-            commands::reorder_tabs,
-            commands::create_timer,
-            commands::get_timer,
-            commands::insert_event,
-            commands::get_events,
-            commands::update_event,
-            commands::delete_event,
-        ])
-        .run(context)
-        .expect("Error while running tauri application");
+        .on_window_event(|window, event| match event {
+            WindowEvent::CloseRequested { api, .. } => {
+                if window.label() == windows::MAIN_WINDOW {
+                    // The app exits once the shutdown is done.
+                    api.prevent_close();
+                    shutdown::request(window.app_handle());
+                } else {
+                    // Pop-outs close on their own; the main window still needs the database.
+                    windows::save_geometry(window);
+                }
+            }
+            WindowEvent::Destroyed => {
+                window
+                    .state::<shutdown::Shutdown>()
+                    .complete_all(window.label());
+            }
+            _ => {}
+        })
+        .invoke_handler(invoke_handler)
+        .build(context)
+        .expect("Error while building tauri application")
+        .run(|app, event| {
+            // Quitting from the OS, e.g. the dock or a logout, goes through the same shutdown.
+            if let RunEvent::ExitRequested { api, .. } = event {
+                if !app.state::<shutdown::Shutdown>().is_done() {
+                    api.prevent_exit();
+                    shutdown::request(app);
+                }
+            }
+        });
+}
+
+/// Opens the database in `storage`, makes it available to commands and starts the periodic
+/// maintenance. Failing to open it is reported to the user, and the app exits.
+fn open_database(app: &AppHandle, storage: Storage) {
+    let data_dir = storage.database_dir();
+
+    info!("Attempting to create database directory: {:?}", data_dir);
+    if let Err(e) = fs::create_dir_all(&data_dir) {
+        if e.kind() != ErrorKind::AlreadyExists {
+            error!(
+                "Failed to create database directory {:?}: {:#}",
+                data_dir, e
+            );
+        }
+    } else {
+        info!("Database directory already exists: {:?}", data_dir);
+    }
+    info!("Database directory ready: {:?}", data_dir);
+
+    let db_url = storage.database_url();
+
+    info!("DB URL: {}", db_url);
+
+    let backup_dir = storage.backup_dir();
+
+    let pool = match async_runtime::block_on(init_db(&db_url, &backup_dir)) {
+        Ok(pool) => pool,
+        Err(e) => {
+            error!("Failed to initialize database at {:?}: {:#}", db_url, e);
+            // The event loop may not be running yet, so the dialog must not block. Nothing
+            // is open that needs a clean shutdown, so the app exits right after it.
+            app.dialog()
+                .message(e.to_string())
+                .title("FocusBoard could not open its database")
+                .kind(MessageDialogKind::Error)
+                .show(|_| std::process::exit(1));
+            return;
+        }
+    };
+
+    // Managed before the maintenance task starts, since its first tick is immediate.
+    app.manage(db::Database::new(pool, storage));
+
+    let app_handle = app.clone();
+
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(600));
+        loop {
+            interval.tick().await;
+            let shutdown = app_handle.state::<shutdown::Shutdown>();
+            let Ok(_work) = shutdown.hold("scheduled maintenance") else {
+                break;
+            };
+            let database = app_handle.state::<db::Database>();
+            let pool_cleanup_background = database.pool();
+            let _ = sqlx::query("PRAGMA optimize")
+                .execute(&pool_cleanup_background)
+                .await
+                .map_err(|e| {
+                    warn!("Periodic database optimization failed: {:#}", e);
+                    e
+                });
+
+            let retention_days = settings::trash_retention_days(&app_handle);
+            if retention_days > 0 {
+                match database.trash().purge(Some(retention_days)).await {
+                    Ok(0) => {}
+                    Ok(purged) => info!("Purged {} expired item(s) from trash", purged),
+                    Err(e) => warn!("Failed to purge expired trash: {:#}", e),
+                }
+            }
+
+            if let Some(backup_interval) = settings::auto_backup_interval(&app_handle) {
+                let backup_dir = database.backup_dir();
+                if backup::automatic_backup_due(&backup_dir, backup_interval) {
+                    let _ = backup::automatic_backup(
+                        &pool_cleanup_background,
+                        &backup_dir,
+                        settings::backup_retention(&app_handle),
+                    )
+                    .await
+                    .map_err(|e| {
+                        warn!("Scheduled backup failed: {:#}", e);
+                        e
+                    });
+                }
+            }
+        }
+    });
 }
@@ -0,0 +1,113 @@
+use focusboard_core::backup::RetentionPolicy;
+use focusboard_core::AppError;
+use log::{error, warn};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::{AppHandle, Runtime};
+use tauri_plugin_store::StoreExt;
+
+/// Backend settings live in their own store, separate from the frontend's `ui-state.json`.
+pub const SETTINGS_STORE: &str = "settings.json";
+
+pub const TRASH_RETENTION_DAYS: &str = "trashRetentionDays";
+const DEFAULT_TRASH_RETENTION_DAYS: i64 = 30;
+
+pub const AUTO_BACKUP_INTERVAL_HOURS: &str = "autoBackupIntervalHours";
+const DEFAULT_AUTO_BACKUP_INTERVAL_HOURS: i64 = 24;
+
+pub const BACKUP_ON_SHUTDOWN: &str = "backupOnShutdown";
+const DEFAULT_BACKUP_ON_SHUTDOWN: bool = true;
+
+pub const BACKUP_KEEP_DAILY: &str = "backupKeepDaily";
+pub const BACKUP_KEEP_WEEKLY: &str = "backupKeepWeekly";
+pub const BACKUP_KEEP_MONTHLY: &str = "backupKeepMonthly";
+const DEFAULT_BACKUP_KEEP_DAILY: i64 = 7;
+const DEFAULT_BACKUP_KEEP_WEEKLY: i64 = 4;
+const DEFAULT_BACKUP_KEEP_MONTHLY: i64 = 6;
+
+/// Folder holding the database and backups, when the user moved it away from the default.
+pub const DATA_DIRECTORY: &str = "dataDirectory";
+
+fn get<R: Runtime>(app: &AppHandle<R>, key: &str) -> Option<Value> {
+    let store = app
+        .store(SETTINGS_STORE)
+        .map_err(|e| {
+            warn!("Failed to open settings store: {:#}", e);
+            e
+        })
+        .ok()?;
+
+    store.get(key)
+}
+
+fn get_i64<R: Runtime>(app: &AppHandle<R>, key: &str) -> Option<i64> {
+    get(app, key).and_then(|value| value.as_i64())
+}
+
+fn get_string<R: Runtime>(app: &AppHandle<R>, key: &str) -> Option<String> {
+    get(app, key).and_then(|value| value.as_str().map(str::to_string))
+}
+
+fn get_bool<R: Runtime>(app: &AppHandle<R>, key: &str) -> Option<bool> {
+    get(app, key).and_then(|value| value.as_bool())
+}
+
+/// How many days trashed notes and tabs are kept before being purged. 0 keeps them forever.
+pub fn trash_retention_days<R: Runtime>(app: &AppHandle<R>) -> i64 {
+    get_i64(app, TRASH_RETENTION_DAYS)
+        .unwrap_or(DEFAULT_TRASH_RETENTION_DAYS)
+        .max(0)
+}
+
+/// Time between automatic backups, or `None` when scheduled backups are turned off (0 hours).
+pub fn auto_backup_interval<R: Runtime>(app: &AppHandle<R>) -> Option<Duration> {
+    let hours = get_i64(app, AUTO_BACKUP_INTERVAL_HOURS)
+        .unwrap_or(DEFAULT_AUTO_BACKUP_INTERVAL_HOURS)
+        .max(0) as u64;
+
+    (hours > 0).then(|| Duration::from_secs(hours * 3600))
+}
+
+pub fn backup_on_shutdown<R: Runtime>(app: &AppHandle<R>) -> bool {
+    get_bool(app, BACKUP_ON_SHUTDOWN).unwrap_or(DEFAULT_BACKUP_ON_SHUTDOWN)
+}
+
+pub fn backup_retention<R: Runtime>(app: &AppHandle<R>) -> RetentionPolicy {
+    let count = |key, default| get_i64(app, key).unwrap_or(default).max(0) as usize;
+
+    RetentionPolicy {
+        daily: count(BACKUP_KEEP_DAILY, DEFAULT_BACKUP_KEEP_DAILY),
+        weekly: count(BACKUP_KEEP_WEEKLY, DEFAULT_BACKUP_KEEP_WEEKLY),
+        monthly: count(BACKUP_KEEP_MONTHLY, DEFAULT_BACKUP_KEEP_MONTHLY),
+    }
+}
+
+pub fn data_directory<R: Runtime>(app: &AppHandle<R>) -> Option<PathBuf> {
+    get_string(app, DATA_DIRECTORY)
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Saves the data directory to open on the next start; `None` goes back to the default.
+pub fn set_data_directory<R: Runtime>(
+    app: &AppHandle<R>,
+    path: Option<&Path>,
+) -> Result<(), AppError> {
+    let store = app.store(SETTINGS_STORE).map_err(|e| {
+        error!("Failed to open settings store: {:#}", e);
+        AppError::io("Failed to open the settings.")
+    })?;
+
+    match path {
+        Some(path) => store.set(DATA_DIRECTORY, path.to_string_lossy().into_owned()),
+        None => {
+            store.delete(DATA_DIRECTORY);
+        }
+    }
+
+    store.save().map_err(|e| {
+        error!("Failed to save settings: {:#}", e);
+        AppError::io("Failed to save the settings.")
+    })
+}
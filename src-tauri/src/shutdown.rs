@@ -0,0 +1,277 @@
+use crate::{db, settings, windows};
+use focusboard_core::{backup, AppError};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::collections::BTreeMap;
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tauri_specta::Event;
+use tokio::sync::Notify;
+
+/// How long shutdown waits for pending work before it gives up on it and exits anyway.
+const PENDING_WORK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Owner of the work held by the backend itself, as opposed to a window.
+const BACKEND: &str = "backend";
+
+/// Sent as `app-closing` to every window once the app starts shutting down. Windows save what
+/// they still have open, then complete the work they registered.
+#[derive(Serialize, Deserialize, Debug, Clone, Type, Event)]
+pub struct AppClosing;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    Running,
+    ShuttingDown,
+    /// Maintenance is done and the pool is closed; the next exit request is ours.
+    Done,
+}
+
+struct State {
+    phase: Phase,
+    /// `(owner, key)` of every piece of work shutdown has to wait for, with how many times it
+    /// is currently registered, so overlapping work under one key is waited for until the last
+    /// of it completes. The owner is the label of the window that registered it, so a window
+    /// that closes can't leave any behind.
+    pending: BTreeMap<(String, String), usize>,
+}
+
+/// Managed as Tauri state. Keeps track of work that must finish before the app exits, and runs
+/// the shutdown once: on closing the main window, on an exit request from the OS, or on
+/// Ctrl+C / SIGTERM.
+pub struct Shutdown {
+    state: Mutex<State>,
+    completed: Notify,
+}
+
+/// Work the backend is doing, e.g. restoring a backup. Shutdown waits until it is dropped.
+pub struct PendingWork<'a> {
+    shutdown: &'a Shutdown,
+    key: &'static str,
+}
+
+impl Drop for PendingWork<'_> {
+    fn drop(&mut self) {
+        self.shutdown.complete(BACKEND, self.key);
+    }
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self {
+            state: Mutex::new(State {
+                phase: Phase::Running,
+                pending: BTreeMap::new(),
+            }),
+            completed: Notify::new(),
+        }
+    }
+}
+
+impl Shutdown {
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Makes shutdown wait for `key` of `owner` until it is completed.
+    pub fn register(&self, owner: &str, key: &str) {
+        *self
+            .state()
+            .pending
+            .entry((owner.to_string(), key.to_string()))
+            .or_default() += 1;
+    }
+
+    /// Completes one registration of `key`. Others under the same key are still waited for.
+    pub fn complete(&self, owner: &str, key: &str) {
+        let mut state = self.state();
+        let entry = (owner.to_string(), key.to_string());
+        if let Some(count) = state.pending.get_mut(&entry) {
+            *count -= 1;
+            if *count == 0 {
+                state.pending.remove(&entry);
+            }
+        }
+        drop(state);
+        self.completed.notify_waiters();
+    }
+
+    /// Drops everything `owner` registered, once its window is gone.
+    pub fn complete_all(&self, owner: &str) {
+        self.state().pending.retain(|(o, _), _| o != owner);
+        self.completed.notify_waiters();
+    }
+
+    /// Registers backend work for as long as the returned guard lives. Refused once shutdown
+    /// has started, so nothing new begins while the database is being closed.
+    pub fn hold(&self, key: &'static str) -> Result<PendingWork<'_>, AppError> {
+        let mut state = self.state();
+        if state.phase != Phase::Running {
+            return Err(AppError::validation(
+                "FocusBoard is closing. Please try again after it restarts",
+            ));
+        }
+        *state
+            .pending
+            .entry((BACKEND.to_string(), key.to_string()))
+            .or_default() += 1;
+
+        Ok(PendingWork {
+            shutdown: self,
+            key,
+        })
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.state().phase == Phase::Done
+    }
+
+    /// Moves to shutting down. `false` if shutdown had already started.
+    fn begin(&self) -> bool {
+        let mut state = self.state();
+        if state.phase != Phase::Running {
+            return false;
+        }
+        state.phase = Phase::ShuttingDown;
+        true
+    }
+
+    fn finish(&self) {
+        self.state().phase = Phase::Done;
+    }
+
+    /// Waits until no work is pending, for at most `timeout`. On timeout, returns what is still
+    /// pending.
+    async fn settle(&self, timeout: Duration) -> Result<(), Vec<String>> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            let completed = self.completed.notified();
+            tokio::pin!(completed);
+            // Listen before checking, so a completion in between isn't missed.
+            completed.as_mut().enable();
+
+            let pending = self
+                .state()
+                .pending
+                .keys()
+                .map(|(owner, key)| format!("{key} ({owner})"))
+                .collect::<Vec<_>>();
+            if pending.is_empty() {
+                return Ok(());
+            }
+
+            if tokio::time::timeout_at(deadline, completed).await.is_err() {
+                return Err(pending);
+            }
+        }
+    }
+}
+
+/// Starts shutting the app down, unless it already is. The app exits once it is done.
+pub fn request(app: &AppHandle) {
+    if !app.state::<Shutdown>().begin() {
+        return;
+    }
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        run(&app).await;
+        app.exit(0);
+    });
+}
+
+async fn run(app: &AppHandle) {
+    info!("Shutting down");
+
+    // Exiting closes the pop-outs without a close request of their own.
+    windows::save_all_geometry(app);
+    if let Err(e) = AppClosing.emit(app) {
+        warn!("Failed to emit {}: {:#}", AppClosing::NAME, e);
+    }
+
+    let shutdown = app.state::<Shutdown>();
+    if let Err(pending) = shutdown.settle(PENDING_WORK_TIMEOUT).await {
+        warn!(
+            "Shutting down without waiting any longer for: {}",
+            pending.join(", ")
+        );
+    }
+
+    // Not there yet if the app is stopped before setup opened the database.
+    if let Some(database) = app.try_state::<db::Database>() {
+        let pool = database.pool();
+
+        if settings::backup_on_shutdown(app) {
+            let _ = backup::automatic_backup(
+                &pool,
+                &database.backup_dir(),
+                settings::backup_retention(app),
+            )
+            .await
+            .map_err(|e| {
+                warn!("Shutdown backup failed: {:#}", e);
+                e
+            });
+        }
+
+        let _ = sqlx::query("PRAGMA optimize;")
+            .execute(&pool)
+            .await
+            .map_err(|e| {
+                warn!("Failed to optimize database: {:#}", e);
+                e
+            });
+        let _ = sqlx::query("PRAGMA incremental_vacuum(0);")
+            .execute(&pool)
+            .await
+            .map_err(|e| {
+                warn!("Failed to incrementally vacuum database: {:#}", e);
+                e
+            });
+        let _ = sqlx::query("PRAGMA wal_checkpoint(TRUNCATE);")
+            .execute(&pool)
+            .await
+            .map_err(|e| {
+                warn!("Failed to flush WAL: {:#}", e);
+                e
+            });
+
+        pool.close().await;
+        info!("Database closed");
+    }
+
+    shutdown.finish();
+}
+
+/// Shuts down cleanly on Ctrl+C in the terminal, and on SIGTERM, e.g. from a logout or
+/// `kill`.
+pub fn watch_signals(app: &AppHandle) {
+    let handle = app.clone();
+    tauri::async_runtime::spawn(async move {
+        match tokio::signal::ctrl_c().await {
+            Ok(()) => request(&handle),
+            Err(e) => warn!("Failed to listen for Ctrl+C: {:#}", e),
+        }
+    });
+
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let handle = app.clone();
+        tauri::async_runtime::spawn(async move {
+            match signal(SignalKind::terminate()) {
+                Ok(mut terminate) => {
+                    if terminate.recv().await.is_some() {
+                        request(&handle);
+                    }
+                }
+                Err(e) => warn!("Failed to listen for SIGTERM: {:#}", e),
+            }
+        });
+    }
+}
@@ -0,0 +1,154 @@
+use crate::{changes, settings};
+use focusboard_core::storage::Storage;
+use log::{error, warn};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager, Runtime};
+use tauri_plugin_dialog::{
+    DialogExt, MessageDialogButtons, MessageDialogKind, MessageDialogResult,
+};
+
+const RETRY: &str = "Retry";
+const CHOOSE_FOLDER: &str = "Choose Folder";
+const USE_DEFAULT: &str = "Use Default";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Type)]
+pub struct DataDirectory {
+    pub path: String,
+    pub default_path: String,
+    pub is_default: bool,
+}
+
+pub fn default_root<R: Runtime>(app: &AppHandle<R>) -> tauri::Result<PathBuf> {
+    app.path().app_local_data_dir()
+}
+
+/// Where the data is at startup.
+pub enum Startup {
+    Ready(Storage),
+    /// The data directory the user chose isn't there, e.g. on a drive that isn't connected.
+    Missing(PathBuf),
+}
+
+/// The storage location to open at startup: the user's chosen data directory, or the default
+/// one if they never chose one.
+pub fn resolve<R: Runtime>(app: &AppHandle<R>) -> tauri::Result<Startup> {
+    match settings::data_directory(app) {
+        Some(root) if root.is_dir() => Ok(Startup::Ready(Storage::new(root))),
+        Some(root) => {
+            warn!("Configured data directory {:?} is not available", root);
+            Ok(Startup::Missing(root))
+        }
+        None => Ok(Startup::Ready(Storage::new(default_root(app)?))),
+    }
+}
+
+/// Asks the user what to do about the missing data directory `root`: retry, choose the folder
+/// again, or go back to the default one. Nothing is opened, saved or backed up until they
+/// settle on one, which is then passed to `open`.
+pub fn recover_missing(app: &AppHandle, root: PathBuf, open: fn(&AppHandle, Storage)) {
+    let handle = app.clone();
+    app.dialog()
+        .message(format!(
+            "FocusBoard keeps its data in {}, but that folder is not available. If it is on a \
+             drive that isn't connected, connect it and retry.",
+            root.display()
+        ))
+        .title("Data folder not found")
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::YesNoCancelCustom(
+            RETRY.to_string(),
+            CHOOSE_FOLDER.to_string(),
+            USE_DEFAULT.to_string(),
+        ))
+        .show_with_result(move |result| {
+            // Custom labels come back as such on some platforms, as yes/no/cancel on others.
+            let choice = match &result {
+                MessageDialogResult::Yes => RETRY,
+                MessageDialogResult::No => CHOOSE_FOLDER,
+                MessageDialogResult::Custom(label) => label.as_str(),
+                _ => USE_DEFAULT,
+            };
+
+            match choice {
+                RETRY if root.is_dir() => open_recovered(&handle, Storage::new(root), open),
+                RETRY => recover_missing(&handle, root, open),
+                CHOOSE_FOLDER => choose_folder(&handle, root, open),
+                _ => confirm_default(&handle, root, open),
+            }
+        });
+}
+
+fn choose_folder(app: &AppHandle, root: PathBuf, open: fn(&AppHandle, Storage)) {
+    let handle = app.clone();
+    app.dialog()
+        .file()
+        .set_title("Choose the FocusBoard data folder")
+        .pick_folder(move |folder| {
+            let Some(path) = folder.and_then(|folder| folder.into_path().ok()) else {
+                recover_missing(&handle, root, open);
+                return;
+            };
+
+            let is_default = default_root(&handle).is_ok_and(|default| default == path);
+            let setting = (!is_default).then_some(path.as_path());
+            if let Err(e) = settings::set_data_directory(&handle, setting) {
+                error!("Failed to save the data directory {:?}: {}", path, e);
+            }
+            open_recovered(&handle, Storage::new(path), open);
+        });
+}
+
+/// Going back to the default folder shows other data, so it is confirmed first.
+fn confirm_default(app: &AppHandle, root: PathBuf, open: fn(&AppHandle, Storage)) {
+    let default = match default_root(app) {
+        Ok(default) => default,
+        Err(e) => {
+            error!("Failed to find the default data directory: {:#}", e);
+            recover_missing(app, root, open);
+            return;
+        }
+    };
+
+    let handle = app.clone();
+    app.dialog()
+        .message(format!(
+            "Open the data in the default folder, {}, instead? The data in {} is left as it is.",
+            default.display(),
+            root.display()
+        ))
+        .title("Use the default data folder")
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            USE_DEFAULT.to_string(),
+            "Back".to_string(),
+        ))
+        .show(move |confirmed| {
+            if !confirmed {
+                recover_missing(&handle, root, open);
+                return;
+            }
+            if let Err(e) = settings::set_data_directory(&handle, None) {
+                error!("Failed to reset the data directory: {}", e);
+            }
+            open_recovered(&handle, Storage::new(default), open);
+        });
+}
+
+/// Opens the storage the user settled on. The windows have loaded without a database by now,
+/// so they are told to load everything again.
+fn open_recovered(app: &AppHandle, storage: Storage, open: fn(&AppHandle, Storage)) {
+    open(app, storage);
+    changes::notify_reset(app);
+}
+
+pub fn describe<R: Runtime>(app: &AppHandle<R>, storage: &Storage) -> tauri::Result<DataDirectory> {
+    let default_path = default_root(app)?;
+
+    Ok(DataDirectory {
+        path: storage.root().to_string_lossy().into_owned(),
+        is_default: storage.root() == default_path,
+        default_path: default_path.to_string_lossy().into_owned(),
+    })
+}
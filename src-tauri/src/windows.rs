@@ -0,0 +1,133 @@
+use focusboard_core::AppError;
+use log::{error, warn};
+use serde::{Deserialize, Serialize};
+use tauri::{
+    AppHandle, Manager, PhysicalPosition, PhysicalSize, Runtime, WebviewUrl, WebviewWindow,
+    WebviewWindowBuilder, Window,
+};
+use tauri_plugin_store::StoreExt;
+
+/// The window from `tauri.conf.json`. Closing it shuts the app down; any other window is a
+/// pop-out that just closes.
+pub const MAIN_WINDOW: &str = "main";
+
+/// Sizes and positions of windows, by label, so each one reopens where it was left.
+const WINDOW_STATE_STORE: &str = "window-state.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+struct Geometry {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    maximized: bool,
+}
+
+/// Opens a pop-out window on the `popout` page, or focuses it if it is already open. `label`
+/// identifies what it shows (e.g. `note-4`), so each note or tab gets at most one window.
+/// Labels must match the `popout` capability.
+pub fn open_popout(app: &AppHandle, label: &str, query: &str, title: &str) -> Result<(), AppError> {
+    let open_error = |e: tauri::Error| {
+        error!("Failed to open window {}: {:#}", label, e);
+        AppError::io("Failed to open the window.")
+    };
+
+    if let Some(window) = app.get_webview_window(label) {
+        let _ = window.unminimize();
+        return window.set_focus().map_err(open_error);
+    }
+
+    let url = WebviewUrl::App(format!("popout?{query}").into());
+    let window = WebviewWindowBuilder::new(app, label, url)
+        .title(title)
+        .inner_size(600.0, 700.0)
+        .min_inner_size(360.0, 320.0)
+        // Shown once the saved geometry is applied, so it doesn't jump into place.
+        .visible(false)
+        .build()
+        .map_err(open_error)?;
+
+    restore_geometry(&window);
+    window.show().map_err(open_error)
+}
+
+/// Moves and resizes `window` to where it was when it was last closed.
+pub fn restore_geometry<R: Runtime>(window: &WebviewWindow<R>) {
+    let Some(geometry) = load_geometry(window.app_handle(), window.label()) else {
+        return;
+    };
+
+    let restored = window
+        .set_size(PhysicalSize::new(geometry.width, geometry.height))
+        .and_then(|_| window.set_position(PhysicalPosition::new(geometry.x, geometry.y)))
+        .and_then(|_| {
+            if geometry.maximized {
+                window.maximize()
+            } else {
+                Ok(())
+            }
+        });
+    if let Err(e) = restored {
+        warn!("Failed to restore window {}: {:#}", window.label(), e);
+    }
+}
+
+/// Remembers the size and position of `window` for the next time it opens.
+pub fn save_geometry<R: Runtime>(window: &Window<R>) {
+    let geometry = window
+        .outer_position()
+        .and_then(|position| Ok((position, window.inner_size()?, window.is_maximized()?)))
+        .map(|(position, size, maximized)| Geometry {
+            x: position.x,
+            y: position.y,
+            width: size.width,
+            height: size.height,
+            maximized,
+        });
+
+    let geometry = match geometry {
+        Ok(geometry) => geometry,
+        Err(e) => {
+            warn!(
+                "Failed to read the geometry of window {}: {:#}",
+                window.label(),
+                e
+            );
+            return;
+        }
+    };
+
+    let saved = window
+        .app_handle()
+        .store(WINDOW_STATE_STORE)
+        .map_err(|e| e.to_string())
+        .and_then(|store| {
+            store.set(window.label(), serde_json::json!(geometry));
+            store.save().map_err(|e| e.to_string())
+        });
+    if let Err(e) = saved {
+        warn!(
+            "Failed to save the geometry of window {}: {}",
+            window.label(),
+            e
+        );
+    }
+}
+
+/// Remembers the geometry of every open window, before the app exits and closes them all.
+pub fn save_all_geometry<R: Runtime>(app: &AppHandle<R>) {
+    for window in app.webview_windows().values() {
+        save_geometry(&window.as_ref().window());
+    }
+}
+
+fn load_geometry<R: Runtime>(app: &AppHandle<R>, label: &str) -> Option<Geometry> {
+    let store = app
+        .store(WINDOW_STATE_STORE)
+        .map_err(|e| warn!("Failed to open window state store: {:#}", e))
+        .ok()?;
+
+    serde_json::from_value(store.get(label)?)
+        .map_err(|e| warn!("Ignoring saved geometry of window {}: {:#}", label, e))
+        .ok()
+}
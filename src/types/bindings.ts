@@ -0,0 +1,557 @@
+
+// This file was generated by [tauri-specta](https://github.com/oscartbeaumont/tauri-specta). Do not edit this file manually.
+
+/** user-defined commands **/
+
+
+export const commands = {
+async getNotes() : Promise<Note[]> {
+    return await TAURI_INVOKE("get_notes");
+},
+async createNote(title: string, content: string, tabId: number | null) : Promise<Note> {
+    return await TAURI_INVOKE("create_note", { title, content, tabId });
+},
+async updateNote(id: number, version: number, title: string, content: string) : Promise<Note> {
+    return await TAURI_INVOKE("update_note", { id, version, title, content });
+},
+async deleteNote(id: number) : Promise<null> {
+    return await TAURI_INVOKE("delete_note", { id });
+},
+async listNoteRevisions(noteId: number) : Promise<NoteRevisionSummary[]> {
+    return await TAURI_INVOKE("list_note_revisions", { noteId });
+},
+async getNoteRevision(id: number) : Promise<NoteRevision> {
+    return await TAURI_INVOKE("get_note_revision", { id });
+},
+/**
+ * Diffs the plain text of two revisions. Without `to_id` the revision is compared against the
+ * note as it is now.
+ */
+async diffNoteRevisions(fromId: number, toId: number | null) : Promise<DiffLine[]> {
+    return await TAURI_INVOKE("diff_note_revisions", { fromId, toId });
+},
+async restoreNoteRevision(id: number, version: number) : Promise<Note> {
+    return await TAURI_INVOKE("restore_note_revision", { id, version });
+},
+async searchNotes(query: string, tabId: number | null, limit: number | null) : Promise<SearchHit[]> {
+    return await TAURI_INVOKE("search_notes", { query, tabId, limit });
+},
+async getTabs() : Promise<Tab[]> {
+    return await TAURI_INVOKE("get_tabs");
+},
+/**
+ * Every tab outside the trash, nested under its parent.
+ */
+async getTabTree() : Promise<TabNode[]> {
+    return await TAURI_INVOKE("get_tab_tree");
+},
+async createTab(name: string) : Promise<Tab> {
+    return await TAURI_INVOKE("create_tab", { name });
+},
+async createSubTab(parentId: number, name: string) : Promise<Tab> {
+    return await TAURI_INVOKE("create_sub_tab", { parentId, name });
+},
+async updateTab(id: number, name: string) : Promise<Tab> {
+    return await TAURI_INVOKE("update_tab", { id, name });
+},
+/**
+ * Moves the tab and its notes to the trash. `children` says whether its sub-tabs go with it or
+ * move up a level.
+ */
+async deleteTab(id: number, children: ChildTabs) : Promise<null> {
+    return await TAURI_INVOKE("delete_tab", { id, children });
+},
+/**
+ * Every tag with the number of notes outside the trash that carry it.
+ */
+async getTags() : Promise<TagUsage[]> {
+    return await TAURI_INVOKE("get_tags");
+},
+/**
+ * Which note carries which tag, for showing the tags of every note at once.
+ */
+async getNoteTags() : Promise<NoteTag[]> {
+    return await TAURI_INVOKE("get_note_tags");
+},
+/**
+ * Notes carrying all of `tag_ids`, or any one of them, across every tab.
+ */
+async getNotesByTags(tagIds: number[], mode: TagMatch) : Promise<Note[]> {
+    return await TAURI_INVOKE("get_notes_by_tags", { tagIds, mode });
+},
+/**
+ * Fails with a conflict carrying the existing tag if one has the same name, ignoring case.
+ */
+async createTag(name: string) : Promise<Tag> {
+    return await TAURI_INVOKE("create_tag", { name });
+},
+/**
+ * Fails with a conflict carrying the other tag if the name is taken, so it can be merged
+ * into that one instead.
+ */
+async renameTag(id: number, name: string) : Promise<Tag> {
+    return await TAURI_INVOKE("rename_tag", { id, name });
+},
+/**
+ * Moves the notes of `from_id` over to `into_id` and deletes `from_id`.
+ */
+async mergeTags(fromId: number, intoId: number) : Promise<Tag> {
+    return await TAURI_INVOKE("merge_tags", { fromId, intoId });
+},
+async deleteTag(id: number) : Promise<null> {
+    return await TAURI_INVOKE("delete_tag", { id });
+},
+/**
+ * Returns every tag the note carries afterwards.
+ */
+async tagNote(noteId: number, tagId: number) : Promise<Tag[]> {
+    return await TAURI_INVOKE("tag_note", { noteId, tagId });
+},
+/**
+ * Returns the tags the note has left.
+ */
+async untagNote(noteId: number, tagId: number) : Promise<Tag[]> {
+    return await TAURI_INVOKE("untag_note", { noteId, tagId });
+},
+async listTrash() : Promise<Trash> {
+    return await TAURI_INVOKE("list_trash");
+},
+async restoreNote(id: number) : Promise<Note> {
+    return await TAURI_INVOKE("restore_note", { id });
+},
+async restoreTab(id: number) : Promise<Tab> {
+    return await TAURI_INVOKE("restore_tab", { id });
+},
+async emptyTrash() : Promise<null> {
+    return await TAURI_INVOKE("empty_trash");
+},
+/**
+ * Backs the database up into a compressed archive. By default the archive goes in
+ * `database_backups`; `path` writes it to that file (or into that folder) instead, and
+ * `choose_location` asks the user where to save it, returning `None` if they cancel.
+ */
+async backupDatabase(path: string | null, chooseLocation: boolean | null) : Promise<BackupSummary | null> {
+    return await TAURI_INVOKE("backup_database", { path, chooseLocation });
+},
+/**
+ * Lists the backups in `database_backups`, newest first, with the counts read from each.
+ */
+async listBackups() : Promise<BackupInfo[]> {
+    return await TAURI_INVOKE("list_backups");
+},
+/**
+ * Replaces the live database with the backup at `path`: an archive, or the database file or
+ * folder of an older backup. The backup is checked first, and the current data is archived
+ * as a `pre-restore` backup; if the restored database can't be opened, that copy is put back.
+ */
+async restoreBackup(path: string) : Promise<BackupInfo> {
+    return await TAURI_INVOKE("restore_backup", { path });
+},
+async getDataDirectory() : Promise<DataDirectory> {
+    return await TAURI_INVOKE("get_data_directory");
+},
+/**
+ * Moves the database to `path` (the user picks a folder without it; `None` is returned if
+ * they cancel) and keeps using it from then on. Existing backups are copied along, and a
+ * `pre-move` backup is left in the old location. The folder must not already hold a
+ * FocusBoard database.
+ */
+async moveDataDirectory(path: string | null) : Promise<DataDirectory | null> {
+    return await TAURI_INVOKE("move_data_directory", { path });
+},
+/**
+ * Exports every tab as a folder of Markdown notes, inside a new timestamped folder under
+ * `path`. Without `path` the user picks the parent folder; `None` is returned if they cancel.
+ */
+async exportMarkdown(path: string | null) : Promise<MarkdownExportSummary | null> {
+    return await TAURI_INVOKE("export_markdown", { path });
+},
+/**
+ * Imports a folder of Markdown (`.md`) and text (`.txt`) files. Each subfolder becomes a new
+ * tab holding the files inside it; files directly in the folder go into a tab named after it.
+ * Without `path` the user picks the folder; `None` is returned if they cancel.
+ */
+async importFolder(path: string | null) : Promise<FolderImportSummary | null> {
+    return await TAURI_INVOKE("import_folder", { path });
+},
+async reorderNotes(tabId: number | null, noteIds: number[]) : Promise<null> {
+    return await TAURI_INVOKE("reorder_notes", { tabId, noteIds });
+},
+/**
+ * Moves notes into `target_tab_id` at `target_index`, in the order given, and closes the gaps
+ * they leave in their old tabs. Returns every note whose tab or position changed.
+ */
+async moveNotes(noteIds: number[], targetTabId: number | null, targetIndex: number) : Promise<Note[]> {
+    return await TAURI_INVOKE("move_notes", { noteIds, targetTabId, targetIndex });
+},
+/**
+ * Orders sibling tabs as given.
+ */
+async reorderTabs(tabIds: number[]) : Promise<null> {
+    return await TAURI_INVOKE("reorder_tabs", { tabIds });
+},
+/**
+ * Moves a tab under `parent_id`, or to the top level without one, at `index` among its new
+ * siblings. Returns every tab whose parent or position changed.
+ */
+async moveTab(id: number, parentId: number | null, index: number) : Promise<Tab[]> {
+    return await TAURI_INVOKE("move_tab", { id, parentId, index });
+},
+async createTimer(initialDuration: number, duration: number, message: string) : Promise<Timer> {
+    return await TAURI_INVOKE("create_timer", { initialDuration, duration, message });
+},
+async getTimer() : Promise<Timer> {
+    return await TAURI_INVOKE("get_timer");
+},
+async insertEvent(eventDate: string, eventName: string, eventStart: number | null, eventEnd: number | null, color: string, recurrence: RecurrenceRule | null) : Promise<CalendarEvent> {
+    return await TAURI_INVOKE("insert_event", { eventDate, eventName, eventStart, eventEnd, color, recurrence });
+},
+async getEvents(yearMonth: string, onwards: boolean | null) : Promise<CalendarEvent[]> {
+    return await TAURI_INVOKE("get_events", { yearMonth, onwards });
+},
+/**
+ * Events from `start_date` through `end_date`, both inclusive. Recurring events are expanded
+ * into one entry per occurrence, with their exceptions applied.
+ */
+async getEventsInRange(startDate: string, endDate: string) : Promise<CalendarEvent[]> {
+    return await TAURI_INVOKE("get_events_in_range", { startDate, endDate });
+},
+async updateEvent(id: number, version: number, eventName: string, eventStart: number | null, eventEnd: number | null, occurrenceDate: string | null) : Promise<CalendarEvent> {
+    return await TAURI_INVOKE("update_event", { id, version, eventName, eventStart, eventEnd, occurrenceDate });
+},
+/**
+ * Sets or clears the recurrence rule of an event. Exceptions that no longer match an
+ * occurrence are left in place but have no effect.
+ */
+async setEventRecurrence(id: number, recurrence: RecurrenceRule | null) : Promise<CalendarEvent> {
+    return await TAURI_INVOKE("set_event_recurrence", { id, recurrence });
+},
+async deleteEvent(id: number, version: number, occurrenceDate: string | null) : Promise<null> {
+    return await TAURI_INVOKE("delete_event", { id, version, occurrenceDate });
+},
+/**
+ * Writes every event to an iCalendar file. Without `path` the user is asked where to save it;
+ * `None` is returned if they cancel.
+ */
+async exportEventsIcs(path: string | null) : Promise<IcsExportSummary | null> {
+    return await TAURI_INVOKE("export_events_ics", { path });
+},
+/**
+ * Imports the VEVENTs of an iCalendar file. Events whose UID is already in the calendar are
+ * skipped as duplicates. Without `path` the user picks the file; `None` is returned if they
+ * cancel.
+ */
+async importEventsIcs(path: string | null) : Promise<IcsImportSummary | null> {
+    return await TAURI_INVOKE("import_events_ics", { path });
+},
+/**
+ * Opens the note in a window of its own, or focuses that window if it is already open.
+ */
+async openNoteWindow(id: number) : Promise<null> {
+    return await TAURI_INVOKE("open_note_window", { id });
+},
+/**
+ * Opens every note of the tab in a window of its own, or focuses that window if it is already
+ * open.
+ */
+async openTabWindow(id: number) : Promise<null> {
+    return await TAURI_INVOKE("open_tab_window", { id });
+},
+/**
+ * Makes shutdown wait, up to a timeout, until the calling window completes `key`, e.g. a
+ * note that is being edited. Everything a window registered is dropped when it closes.
+ */
+async registerPendingWork(key: string) : Promise<void> {
+    await TAURI_INVOKE("register_pending_work", { key });
+},
+async completePendingWork(key: string) : Promise<void> {
+    await TAURI_INVOKE("complete_pending_work", { key });
+}
+}
+
+/** user-defined events **/
+
+
+export const events = __makeEvents__<{
+appClosing: AppClosing,
+eventChanged: EventChanged,
+noteChanged: NoteChanged,
+tabChanged: TabChanged,
+tagChanged: TagChanged
+}>({
+appClosing: "app-closing",
+eventChanged: "event-changed",
+noteChanged: "note-changed",
+tabChanged: "tab-changed",
+tagChanged: "tag-changed"
+})
+
+/** user-defined constants **/
+
+
+
+/** user-defined types **/
+
+/**
+ * Sent as `app-closing` to every window once the app starts shutting down. Windows save what
+ * they still have open, then complete the work they registered.
+ */
+export type AppClosing = null
+/**
+ * The error every service and command returns. It serializes as
+ * `{ "code": "not_found", "message": "...", "details": ... }`, so the frontend can branch on
+ * `code` and show `message` as is. Messages are written for users; the underlying SQL or I/O
+ * error is logged, never returned.
+ */
+export type AppError = 
+/**
+ * The requested record does not exist (or is in the trash when it must not be).
+ */
+{ code: "not_found"; message: string; details: JsonValue | null } | 
+/**
+ * The request itself is invalid; retrying it unchanged will fail again.
+ */
+{ code: "validation"; message: string; details: JsonValue | null } | 
+/**
+ * The request clashes with the current state, e.g. a file or record already exists.
+ */
+{ code: "conflict"; message: string; details: JsonValue | null } | 
+/**
+ * The database failed; retrying may help.
+ */
+{ code: "database"; message: string; details: JsonValue | null } | 
+/**
+ * Reading or writing a file failed.
+ */
+{ code: "io"; message: string; details: JsonValue | null }
+export type BackupInfo = { 
+/**
+ * Path of the backup archive, or of the database file for older backups.
+ */
+path: string; name: string; 
+/**
+ * Modification time of the backup, UTC, in the same format as the app's tables.
+ */
+created_at: string; size_bytes: number; 
+/**
+ * FocusBoard version that wrote the backup, when it has a manifest.
+ */
+app_version: string | null; 
+/**
+ * `None` when the backup could not be read; `error` says why.
+ */
+stats: BackupStats | null; error: string | null }
+/**
+ * Counts read from a backup without opening it as the live database.
+ */
+export type BackupStats = { schema_version: number; note_count: number; tab_count: number; event_count: number }
+export type BackupSummary = { path: string; 
+/**
+ * Size of the compressed archive.
+ */
+size_bytes: number; duration_ms: number; integrity_ok: boolean; 
+/**
+ * Output of `PRAGMA integrity_check` on the copy; `["ok"]` when healthy.
+ */
+integrity_check: string[] }
+export type CalendarEvent = { id: number; event_date: string; year_month: string; event_name: string; event_start: number | null; event_end: number | null; color: string; recurrence: RecurrenceRule | null; 
+/**
+ * Bumped on every edit, including edits to a single occurrence. See `Note::version`.
+ */
+version: number; 
+/**
+ * Set on the occurrences of a recurring event expanded by `EventService::range`: the
+ * date that identifies the occurrence when editing or deleting just that one.
+ */
+occurrence_date: string | null }
+/**
+ * What deleting a tab does with its sub-tabs.
+ */
+export type ChildTabs = 
+/**
+ * Into the trash along with the tab, notes and all, and back out when it is restored.
+ */
+"trash" | 
+/**
+ * Up to the tab's own parent, after its other sub-tabs. Only the tab's own notes go to
+ * the trash.
+ */
+"move_up"
+export type DataDirectory = { path: string; default_path: string; is_default: boolean }
+export type DiffKind = "equal" | "insert" | "delete"
+export type DiffLine = { kind: DiffKind; text: string }
+/**
+ * Sent as `event-changed` after a command changes calendar events. `event` is always the
+ * stored event, so editing or deleting one occurrence of a recurring event sends the series as
+ * `Updated`.
+ */
+export type EventChanged = { kind: "created"; event: CalendarEvent } | { kind: "updated"; event: CalendarEvent } | { kind: "deleted"; id: number } | { kind: "reset" }
+export type FolderImportSummary = { path: string; tabs_created: number; notes_imported: number; files: ImportedFile[] }
+export type Frequency = "daily" | "weekly" | "monthly" | "yearly"
+export type IcsExportSummary = { path: string; event_count: number }
+export type IcsImportSummary = { path: string; imported: number; duplicates: number; failed: string[] }
+export type ImportedFile = { path: string; note_id: number | null; error: string | null }
+export type JsonValue = null | boolean | number | string | JsonValue[] | Partial<{ [key in string]: JsonValue }>
+export type MarkdownExportSummary = { path: string; tab_count: number; note_count: number }
+export type Note = { id: number; title: string; content: string; tab_id: number | null; order_id: number | null; created_at: string; updated_at: string; deleted_at: string | null; 
+/**
+ * Bumped on every edit. Updates pass the version they started from and are rejected
+ * with a conflict if the note has been changed since.
+ */
+version: number }
+/**
+ * Sent as `note-changed` to every window after a command changes notes, so each view can
+ * patch its list instead of fetching all notes again.
+ */
+export type NoteChanged = { kind: "created"; note: Note } | { kind: "updated"; note: Note } | 
+/**
+ * Moved to the trash.
+ */
+{ kind: "deleted"; id: number } | 
+/**
+ * Brought back from the trash.
+ */
+{ kind: "restored"; note: Note } | 
+/**
+ * The notes of `tab_id`, in their new order.
+ */
+{ kind: "reordered"; tab_id: number | null; note_ids: number[] } | 
+/**
+ * Notes moved to another tab, with every note whose tab or position changed on the way.
+ */
+{ kind: "moved"; notes: Note[] } | 
+/**
+ * Too many notes changed to list, e.g. after an import or a backup restore. Load them again.
+ */
+{ kind: "reset" }
+export type NoteRevision = { id: number; note_id: number; title: string; content: string; created_at: string; updated_at: string }
+export type NoteRevisionSummary = { id: number; note_id: number; title: string; created_at: string; updated_at: string }
+export type NoteTag = { note_id: number; tag_id: number }
+/**
+ * A subset of RFC 5545 RRULE: FREQ, INTERVAL, BYDAY (daily and weekly rules only),
+ * COUNT and UNTIL. Stored in `events.recurrence` in its RRULE text form.
+ */
+export type RecurrenceRule = { frequency: Frequency; interval?: number; by_weekday?: Weekday[]; count: number | null; 
+/**
+ * Last possible occurrence date, inclusive, as YYYY-MM-DD.
+ */
+until: string | null }
+export type SearchHit = { id: number; tab_id: number | null; title: string; title_highlight: string; snippet: string; rank: number }
+export type Tab = { id: number; name: string; 
+/**
+ * The tab this one is a sub-tab of, or `None` at the top level.
+ */
+parent_id: number | null; 
+/**
+ * Position among the tabs with the same parent.
+ */
+order_id: number | null; created_at: string; updated_at: string; deleted_at: string | null }
+/**
+ * Sent as `tab-changed` after a command changes tabs. Trashing or restoring a tab also moves
+ * its notes, which is sent as its own `note-changed`. `Deleted` takes along whatever sub-tabs
+ * the tab still has; sub-tabs restored with a tab are sent as `Restored` one by one. `Moved`
+ * lists every tab whose parent or position changed.
+ */
+export type TabChanged = { kind: "created"; tab: Tab } | { kind: "updated"; tab: Tab } | { kind: "deleted"; id: number } | { kind: "restored"; tab: Tab } | { kind: "reordered"; tab_ids: number[] } | { kind: "moved"; tabs: Tab[] } | { kind: "reset" }
+/**
+ * A tab with its sub-tabs, each level in order.
+ */
+export type TabNode = ({ id: number; name: string; 
+/**
+ * The tab this one is a sub-tab of, or `None` at the top level.
+ */
+parent_id: number | null; 
+/**
+ * Position among the tabs with the same parent.
+ */
+order_id: number | null; created_at: string; updated_at: string; deleted_at: string | null }) & { children: TabNode[] }
+/**
+ * A label notes can carry any number of, across tabs. Names are unique, ignoring case.
+ */
+export type Tag = { id: number; name: string; created_at: string; updated_at: string }
+/**
+ * Sent as `tag-changed` after a command changes tags or which notes carry them. Usage counts
+ * aren't sent; they follow from `NoteTagged` and the notes in the trash.
+ */
+export type TagChanged = { kind: "created"; tag: Tag } | { kind: "updated"; tag: Tag } | 
+/**
+ * `from_id` is gone; its notes now carry `tag`.
+ */
+{ kind: "merged"; from_id: number; tag: Tag } | { kind: "deleted"; id: number } | 
+/**
+ * Every tag the note now carries.
+ */
+{ kind: "note_tagged"; note_id: number; tag_ids: number[] } | { kind: "reset" }
+/**
+ * Whether notes filtered by several tags need all of them or any one.
+ */
+export type TagMatch = "all" | "any"
+export type TagUsage = { id: number; name: string; 
+/**
+ * Notes outside the trash that carry the tag.
+ */
+note_count: number }
+export type Timer = { id: number; initial_duration: number; duration: number; message: string }
+export type Trash = { tabs: TrashedTab[]; notes: Note[] }
+export type TrashedTab = { id: number; name: string; deleted_at: string; note_count: number }
+export type Weekday = "monday" | "tuesday" | "wednesday" | "thursday" | "friday" | "saturday" | "sunday"
+
+/** tauri-specta globals **/
+
+import {
+	invoke as TAURI_INVOKE,
+	Channel as TAURI_CHANNEL,
+} from "@tauri-apps/api/core";
+import * as TAURI_API_EVENT from "@tauri-apps/api/event";
+import { type WebviewWindow as __WebviewWindow__ } from "@tauri-apps/api/webviewWindow";
+
+type __EventObj__<T> = {
+	listen: (
+		cb: TAURI_API_EVENT.EventCallback<T>,
+	) => ReturnType<typeof TAURI_API_EVENT.listen<T>>;
+	once: (
+		cb: TAURI_API_EVENT.EventCallback<T>,
+	) => ReturnType<typeof TAURI_API_EVENT.once<T>>;
+	emit: null extends T
+		? (payload?: T) => ReturnType<typeof TAURI_API_EVENT.emit>
+		: (payload: T) => ReturnType<typeof TAURI_API_EVENT.emit>;
+};
+
+export type Result<T, E> =
+	| { status: "ok"; data: T }
+	| { status: "error"; error: E };
+
+function __makeEvents__<T extends Record<string, any>>(
+	mappings: Record<keyof T, string>,
+) {
+	return new Proxy(
+		{} as unknown as {
+			[K in keyof T]: __EventObj__<T[K]> & {
+				(handle: __WebviewWindow__): __EventObj__<T[K]>;
+			};
+		},
+		{
+			get: (_, event) => {
+				const name = mappings[event as keyof T];
+
+				return new Proxy((() => {}) as any, {
+					apply: (_, __, [window]: [__WebviewWindow__]) => ({
+						listen: (arg: any) => window.listen(name, arg),
+						once: (arg: any) => window.once(name, arg),
+						emit: (arg: any) => window.emit(name, arg),
+					}),
+					get: (_, command: keyof __EventObj__<any>) => {
+						switch (command) {
+							case "listen":
+								return (arg: any) => TAURI_API_EVENT.listen(name, arg);
+							case "once":
+								return (arg: any) => TAURI_API_EVENT.once(name, arg);
+							case "emit":
+								return (arg: any) => TAURI_API_EVENT.emit(name, arg);
+						}
+					},
+				});
+			},
+		},
+	);
+}
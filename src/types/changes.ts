@@ -0,0 +1,156 @@
+import type {
+  CalendarEvent, EventChanged, Note, NoteChanged, NoteTag, Tab, TabChanged, TagChanged, TagUsage,
+} from './types';
+
+type Ordered = { id: number; order_id: number | null };
+
+function byOrder(a: Ordered, b: Ordered): number {
+  return (a.order_id ?? 0) - (b.order_id ?? 0);
+}
+
+function upsert<T extends { id: number }>(items: T[], item: T): T[] {
+  return items.some(i => i.id === item.id)
+    ? items.map(i => (i.id === item.id ? item : i))
+    : [...items, item];
+}
+
+function reorder<T extends Ordered>(items: T[], ids: number[]): T[] {
+  return items
+    .map(item => {
+      const index = ids.indexOf(item.id);
+      return index === -1 ? item : { ...item, order_id: index + 1 };
+    })
+    .sort(byOrder);
+}
+
+/**
+ * `notes` with a `note-changed` event applied, or `null` when the change doesn't say which
+ * notes changed and they have to be loaded again.
+ */
+export function applyNoteChange(notes: Note[], change: NoteChanged): Note[] | null {
+  switch (change.kind) {
+    case 'created':
+    case 'updated':
+    case 'restored':
+      return upsert(notes, change.note).sort(byOrder);
+    case 'deleted':
+      return notes.filter(n => n.id !== change.id);
+    case 'reordered':
+      return reorder(notes, change.note_ids);
+    case 'moved':
+      return change.notes.reduce((all, note) => upsert(all, note), notes).sort(byOrder);
+    case 'reset':
+      return null;
+  }
+}
+
+/** `tabs` with a `tab-changed` event applied, or `null` when they have to be loaded again. */
+export function applyTabChange(tabs: Tab[], change: TabChanged): Tab[] | null {
+  switch (change.kind) {
+    case 'created':
+    case 'updated':
+    case 'restored':
+      return upsert(tabs, change.tab).sort(byOrder);
+    case 'deleted': {
+      const gone = [change.id, ...descendantIds(tabs, change.id)];
+      return tabs.filter(t => !gone.includes(t.id));
+    }
+    case 'reordered':
+      return reorder(tabs, change.tab_ids);
+    case 'moved':
+      return change.tabs.reduce((all, tab) => upsert(all, tab), tabs).sort(byOrder);
+    case 'reset':
+      return null;
+  }
+}
+
+/** The ids of every tab nested under `id`, however deep. */
+export function descendantIds(tabs: Tab[], id: number): number[] {
+  const children = tabs.filter(t => t.parent_id === id).map(t => t.id);
+  return children.flatMap(child => [child, ...descendantIds(tabs, child)]);
+}
+
+/** Every tag, and which notes carry them, as loaded by `getTags` and `getNoteTags`. */
+export type TagState = { tags: TagUsage[]; noteTags: NoteTag[] };
+
+function byName(a: { name: string }, b: { name: string }): number {
+  return a.name.localeCompare(b.name, undefined, { sensitivity: 'base' });
+}
+
+/** `state` with a `tag-changed` event applied, or `null` when it has to be loaded again. */
+export function applyTagChange(state: TagState, change: TagChanged): TagState | null {
+  switch (change.kind) {
+    case 'created':
+    case 'updated': {
+      const { id, name } = change.tag;
+      const current = state.tags.find(t => t.id === id);
+      const tag = { id, name, note_count: current?.note_count ?? 0 };
+      return { ...state, tags: upsert(state.tags, tag).sort(byName) };
+    }
+    case 'merged': {
+      const { from_id, tag } = change;
+      const tags = state.tags.filter(t => t.id !== from_id);
+      const into = { id: tag.id, name: tag.name, note_count: 0 };
+      const merged = state.noteTags.map(nt => (nt.tag_id === from_id ? { ...nt, tag_id: tag.id } : nt));
+      const noteTags = merged.filter(
+        (nt, i) => merged.findIndex(o => o.note_id === nt.note_id && o.tag_id === nt.tag_id) === i
+      );
+      return { tags: upsert(tags, into).sort(byName), noteTags };
+    }
+    case 'deleted':
+      return {
+        tags: state.tags.filter(t => t.id !== change.id),
+        noteTags: state.noteTags.filter(nt => nt.tag_id !== change.id),
+      };
+    case 'note_tagged': {
+      const { note_id, tag_ids } = change;
+      const others = state.noteTags.filter(nt => nt.note_id !== note_id);
+      return { ...state, noteTags: [...others, ...tag_ids.map(tag_id => ({ note_id, tag_id }))] };
+    }
+    case 'reset':
+      return null;
+  }
+}
+
+/**
+ * The tags of `state` with `note_count` counted among `notes`, so counts stay right as notes
+ * are tagged, trashed and restored without loading the tags again.
+ */
+export function countTagged(state: TagState, notes: Note[]): TagUsage[] {
+  const live = new Set(notes.map(n => n.id));
+  return state.tags.map(tag => ({
+    ...tag,
+    note_count: state.noteTags.filter(nt => nt.tag_id === tag.id && live.has(nt.note_id)).length,
+  }));
+}
+
+/** The tags `noteId` carries, by name. */
+export function tagsOf(state: TagState, noteId: number): TagUsage[] {
+  const ids = new Set(state.noteTags.filter(nt => nt.note_id === noteId).map(nt => nt.tag_id));
+  return state.tags.filter(t => ids.has(t.id));
+}
+
+/**
+ * `events` with an `event-changed` event applied, or `null` when they have to be loaded again.
+ * `shows` decides whether an event belongs in the list, e.g. whether it falls in the month on
+ * screen.
+ */
+export function applyEventChange(
+  events: CalendarEvent[],
+  change: EventChanged,
+  shows: (event: CalendarEvent) => boolean = () => true,
+): CalendarEvent[] | null {
+  switch (change.kind) {
+    case 'created':
+    case 'updated': {
+      const rest = events.filter(e => e.id !== change.event.id);
+      return shows(change.event)
+        ? upsert(events, change.event).sort((a, b) => (a.event_start ?? 0) - (b.event_start ?? 0))
+        : rest;
+    }
+    case 'deleted':
+      return events.filter(e => e.id !== change.id);
+    case 'reset':
+      return null;
+  }
+}
@@ -0,0 +1,27 @@
+import type { AppError } from './types';
+
+export function isAppError(error: unknown): error is AppError {
+  return typeof error === 'object' && error !== null && 'code' in error && 'message' in error;
+}
+
+/** The text to show for an error thrown by `invoke` or any other call. */
+export function errorMessage(error: unknown): string {
+  if (isAppError(error)) {
+    return error.message;
+  }
+  if (error instanceof Error) {
+    return error.message;
+  }
+  return String(error);
+}
+
+/**
+ * The current copy of a note or event sent with the `conflict` error an update returns when
+ * someone else saved it first, or `null` for any other error.
+ */
+export function conflictCopy<T>(error: unknown): T | null {
+  if (isAppError(error) && error.code === 'conflict') {
+    return error.details as T | null;
+  }
+  return null;
+}
@@ -1,27 +1,45 @@
-export type Note = {
-  id: number;
-  title: string;
-  content: string;
-  tab_id: number | null;
-  order_id: number | null;
-  created_at: string;
-  updated_at: string;
-};
+// Types shared with the backend are generated into `bindings.ts` from the Rust structs; only
+// frontend-only types are written here.
+export type {
+  AppError,
+  BackupInfo,
+  BackupStats,
+  BackupSummary,
+  CalendarEvent,
+  ChildTabs,
+  DataDirectory,
+  DiffKind,
+  DiffLine,
+  EventChanged,
+  FolderImportSummary,
+  Frequency,
+  IcsExportSummary,
+  IcsImportSummary,
+  ImportedFile,
+  MarkdownExportSummary,
+  Note,
+  NoteChanged,
+  NoteRevision,
+  NoteRevisionSummary,
+  NoteTag,
+  RecurrenceRule,
+  SearchHit,
+  Tab,
+  TabChanged,
+  TabNode,
+  Tag,
+  TagChanged,
+  TagMatch,
+  TagUsage,
+  Timer,
+  Trash,
+  TrashedTab,
+  Weekday,
+} from './bindings';
 
-export type Tab = {
-  id: number;
-  name: string;
-  order_id: number;
-  created_at: string;
-  updated_at: string;
-};
+import type { AppError, CalendarEvent } from './bindings';
 
-export type Timer = {
-  id: number;
-  initial_duration: number;
-  duration: number;
-  message: string | null;
-}
+export type AppErrorCode = AppError['code'];
 
 export type CalendarDay = {
   date: Date;
@@ -31,15 +49,5 @@ export type CalendarDay = {
   monthabbrev: string;
 }
 
-export type CalendarEvent = {
-  id: number;
-  event_date: string;
-  year_month: string;
-  event_name: string;
-  event_start: number;
-  event_end: number;
-  color: string;
-}
-
 export type CalendarEventWithLane = CalendarEvent & { lane: number };
 export type CalendarEventWithLaneVertical = CalendarEvent & { lane: number, maxLanes: number };